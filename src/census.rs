@@ -0,0 +1,635 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::life::{emod, Cell, Rule};
+use crate::pattern::library::{Kind, LIBRARY};
+
+type Rows = usize;
+type Cols = usize;
+
+// A cell on the unbounded plane islands are evolved on, as (row, col).
+pub(crate) type Point = (i64, i64);
+
+/// Longest period an island is evolved for before it is left unclassified.
+pub const MAX_PERIOD: u64 = 1024;
+
+// An island that grows past this many times its starting population is not a settled object.
+const MAX_GROWTH: usize = 16;
+
+const WECHSLER_DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+pub fn census_error(err: &str) -> String {
+    format!("[CENSUS ERROR]: {err}.")
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Class {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    /// Neither dies out nor repeats within [`MAX_PERIOD`] generations on its own.
+    Unclassified,
+}
+
+impl Class {
+    pub fn name(&self) -> &str {
+        match self {
+            Class::StillLife => "still life",
+            Class::Oscillator => "oscillator",
+            Class::Spaceship => "spaceship",
+            Class::Unclassified => "unclassified",
+        }
+    }
+}
+
+/// One object of the board, as it behaves when evolved alone.
+#[derive(Clone, Debug, Serialize)]
+pub struct Object {
+    /// Canonical apgcode, such as `xs4_33` or `xq4_153`; `None` when unclassified.
+    pub apgcode: Option<String>,
+    pub name: Option<String>,
+    pub class: Class,
+    pub period: Option<u64>,
+    /// Cells moved per period, as (x, y).
+    pub displacement: Option<(i64, i64)>,
+    pub population: usize,
+    /// Top-left corner of the object's bounding box on the board.
+    pub x: Cols,
+    pub y: Rows,
+}
+
+/// How many objects of one kind the board holds.
+#[derive(Clone, Debug, Serialize)]
+pub struct Tally {
+    pub apgcode: Option<String>,
+    pub name: Option<String>,
+    pub class: Class,
+    pub period: Option<u64>,
+    pub displacement: Option<(i64, i64)>,
+    pub population: usize,
+    pub count: usize,
+}
+
+/// The objects of a settled board, classified and named by apgcode.
+///
+/// Live cells are split into islands of touching cells, wrapping around the board edges. Each
+/// island is evolved alone on an unbounded plane until it repeats, which gives its period and
+/// displacement. Islands that do not repeat alone (such as a quarter of a pulsar) are merged with
+/// the islands at most one empty cell away and tried again.
+#[derive(Clone, Debug, Serialize)]
+pub struct Census {
+    pub rule: String,
+    pub generation: u64,
+    pub population: usize,
+    pub tally: Vec<Tally>,
+    pub objects: Vec<Object>,
+}
+
+impl Census {
+    pub fn take(rule: &Rule, cells: &[Vec<Cell>], generation: u64) -> Result<Self, String> {
+        if !rule.is_deterministic() {
+            return Err(census_error(
+                "objects of a probabilistic rule have no period",
+            ));
+        }
+        if rule.next(Cell::DEAD, 0).is_alive() {
+            return Err(census_error(&format!(
+                "{rule} fills empty space, so it has no isolated objects"
+            )));
+        }
+
+        let names = known_names(rule);
+        let (rows, cols) = (cells.len(), cells[0].len());
+        let groups = clusters(cells, 2);
+        let mut group_at = vec![vec![0; cols]; rows];
+        for (i, group) in groups.iter().enumerate() {
+            for &(r, c) in group.iter() {
+                group_at[wrap(r, rows)][wrap(c, cols)] = i;
+            }
+        }
+
+        let evolved: Vec<(usize, Vec<Point>, Option<Evolution>)> = clusters(cells, 1)
+            .into_iter()
+            .map(|island| {
+                let group = group_at[wrap(island[0].0, rows)][wrap(island[0].1, cols)];
+                let evolution = Evolution::of(rule, &island);
+                (group, island, evolution)
+            })
+            .collect();
+        // Islands that do not repeat alone are retried as the whole group they belong to.
+        let merged: HashSet<usize> = evolved
+            .iter()
+            .filter(|(_, _, evolution)| evolution.is_none())
+            .map(|&(group, _, _)| group)
+            .collect();
+
+        let mut objects = Vec::new();
+        for (group, island, evolution) in evolved.iter() {
+            if let (false, Some(evolution)) = (merged.contains(group), evolution) {
+                objects.push(evolution.object(island, cells, &names));
+            }
+        }
+        for &group in merged.iter() {
+            let cluster = &groups[group];
+            objects.push(match Evolution::of(rule, cluster) {
+                Some(evolution) => evolution.object(cluster, cells, &names),
+                None => unclassified(cluster, cells),
+            });
+        }
+        objects.sort_by_key(|object| (object.y, object.x));
+
+        Ok(Self {
+            rule: rule.to_string(),
+            generation,
+            population: cells.iter().flatten().filter(|c| c.is_alive()).count(),
+            tally: tally(&objects),
+            objects,
+        })
+    }
+
+    /// Census of live cells on the unbounded plane, with objects counted apart from them, such as
+    /// spaceships taken off the plane as they flew away, each with the cell it was at. Positions
+    /// are given from the top-left corner of the live cells, objects apart clamped to it.
+    pub(crate) fn of_plane(
+        rule: &Rule,
+        cells: &HashSet<Point>,
+        generation: u64,
+        apart: Vec<(Object, Point)>,
+    ) -> Result<Self, String> {
+        // Two dead cells around the live ones keep islands from meeting across the board edges.
+        let (r0, c0) = cells
+            .iter()
+            .fold((i64::MAX, i64::MAX), |(r0, c0), &(r, c)| {
+                (r0.min(r), c0.min(c))
+            });
+        let (r1, c1) = cells
+            .iter()
+            .fold((i64::MIN, i64::MIN), |(r1, c1), &(r, c)| {
+                (r1.max(r), c1.max(c))
+            });
+        let (r0, c0, rows, cols) = match cells.is_empty() {
+            true => (0, 0, 1, 1),
+            false => (
+                r0 - 2,
+                c0 - 2,
+                (r1 - r0 + 5) as usize,
+                (c1 - c0 + 5) as usize,
+            ),
+        };
+        let mut board = vec![vec![Cell::DEAD; cols]; rows];
+        for &(r, c) in cells.iter() {
+            board[(r - r0) as usize][(c - c0) as usize] = Cell::ALIVE;
+        }
+
+        let mut census = Self::take(rule, &board, generation)?;
+        let names = known_names(rule);
+        for (mut object, (r, c)) in apart.into_iter() {
+            object.name = object.apgcode.as_ref().and_then(|a| names.get(a)).cloned();
+            object.y = (r - r0).clamp(0, rows as i64 - 1) as usize;
+            object.x = (c - c0).clamp(0, cols as i64 - 1) as usize;
+            census.population += object.population;
+            census.objects.push(object);
+        }
+        census.objects.sort_by_key(|object| (object.y, object.x));
+        census.tally = tally(&census.objects);
+        Ok(census)
+    }
+
+    /// Summary table, one line per kind of object, most common first.
+    pub fn table(&self) -> String {
+        let mut out = format!(
+            "Census of {} at generation {}: {} objects, {} cells\n",
+            self.rule,
+            self.generation,
+            self.objects.len(),
+            self.population
+        );
+        let width = self
+            .tally
+            .iter()
+            .filter_map(|t| t.apgcode.as_ref().map(String::len))
+            .max()
+            .unwrap_or(0)
+            .max("apgcode".len());
+        writeln!(
+            out,
+            "{:>6}  {:<width$}  {:<12}  {:>6}  name",
+            "count", "apgcode", "class", "period"
+        )
+        .unwrap();
+        for t in self.tally.iter() {
+            let period = t.period.map_or("-".to_string(), |p| p.to_string());
+            writeln!(
+                out,
+                "{:>6}  {:<width$}  {:<12}  {:>6}  {}",
+                t.count,
+                t.apgcode.as_deref().unwrap_or("?"),
+                t.class.name(),
+                period,
+                t.name.as_deref().unwrap_or("")
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a census always serializes")
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json() + "\n")
+            .map_err(|err| census_error(&format!("cannot write {}: {err}", path.display())))
+    }
+}
+
+// What an island does when left alone: every phase it goes through and how far it moves.
+struct Evolution {
+    period: u64,
+    shift: Point,
+    phases: Vec<Vec<Point>>,
+}
+
+impl Evolution {
+    fn of(rule: &Rule, island: &[Point]) -> Option<Self> {
+        let (start, origin) = normalized(island);
+        let mut phases = vec![island.to_vec()];
+        let mut current: HashSet<Point> = island.iter().copied().collect();
+
+        for period in 1..=MAX_PERIOD {
+            current = step(rule, &current);
+            if current.is_empty() || current.len() > island.len() * MAX_GROWTH {
+                return None;
+            }
+            let cells: Vec<Point> = current.iter().copied().collect();
+            let (shape, at) = normalized(&cells);
+            if shape == start {
+                return Some(Self {
+                    period,
+                    shift: (at.0 - origin.0, at.1 - origin.1),
+                    phases,
+                });
+            }
+            phases.push(cells);
+        }
+        None
+    }
+
+    fn class(&self) -> Class {
+        match (self.period, self.shift) {
+            (1, (0, 0)) => Class::StillLife,
+            (_, (0, 0)) => Class::Oscillator,
+            _ => Class::Spaceship,
+        }
+    }
+
+    fn apgcode(&self) -> String {
+        let prefix = match self.class() {
+            Class::StillLife => format!("xs{}", self.phases[0].len()),
+            Class::Oscillator => format!("xp{}", self.period),
+            _ => format!("xq{}", self.period),
+        };
+        let best = self
+            .phases
+            .iter()
+            .flat_map(|phase| orientations(phase))
+            .map(|shape| wechsler(&shape))
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+            .unwrap_or_default();
+        format!("{prefix}_{best}")
+    }
+
+    fn object(
+        &self,
+        island: &[Point],
+        cells: &[Vec<Cell>],
+        names: &HashMap<String, String>,
+    ) -> Object {
+        let apgcode = self.apgcode();
+        let (y, x) = board_origin(island, cells);
+        Object {
+            name: names.get(&apgcode).cloned(),
+            apgcode: Some(apgcode),
+            class: self.class(),
+            period: Some(self.period),
+            displacement: Some((self.shift.1, self.shift.0)),
+            population: island.len(),
+            x,
+            y,
+        }
+    }
+}
+
+/// The spaceship `island` is when left alone, unnamed, with the cells it moves every period as
+/// (rows, cols); `None` if it is anything else.
+pub(crate) fn spaceship(rule: &Rule, island: &[Point]) -> Option<(Object, Point)> {
+    let evolution = Evolution::of(rule, island).filter(|e| e.class() == Class::Spaceship)?;
+    let object = evolution.object(island, &[vec![Cell::DEAD]], &HashMap::new());
+    Some((object, evolution.shift))
+}
+
+fn unclassified(island: &[Point], cells: &[Vec<Cell>]) -> Object {
+    let (y, x) = board_origin(island, cells);
+    Object {
+        apgcode: None,
+        name: None,
+        class: Class::Unclassified,
+        period: None,
+        displacement: None,
+        population: island.len(),
+        x,
+        y,
+    }
+}
+
+fn tally(objects: &[Object]) -> Vec<Tally> {
+    let mut tally: Vec<Tally> = Vec::new();
+    for object in objects.iter() {
+        // Unclassified objects are told apart by their population only.
+        let same = |t: &&mut Tally| {
+            t.apgcode == object.apgcode
+                && (object.apgcode.is_some() || t.population == object.population)
+        };
+        match tally.iter_mut().find(same) {
+            Some(t) => t.count += 1,
+            None => tally.push(Tally {
+                apgcode: object.apgcode.clone(),
+                name: object.name.clone(),
+                class: object.class,
+                period: object.period,
+                displacement: object.displacement,
+                population: object.population,
+                count: 1,
+            }),
+        }
+    }
+    tally.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.apgcode.cmp(&b.apgcode))
+    });
+    tally
+}
+
+// Names of the library's still lifes, oscillators and spaceships by apgcode, under Life only.
+fn known_names(rule: &Rule) -> HashMap<String, String> {
+    if *rule != Rule::default() {
+        return HashMap::new();
+    }
+    LIBRARY
+        .iter()
+        .filter(|entry| {
+            matches!(
+                entry.kind,
+                Kind::StillLife | Kind::Oscillator | Kind::Spaceship
+            )
+        })
+        .filter_map(|entry| {
+            let pattern = entry.pattern();
+            let cells: Vec<Point> = (0..pattern.rows())
+                .flat_map(|r| (0..pattern.cols()).map(move |c| (r, c)))
+                .filter(|&(r, c)| pattern.cells[r][c] != 0)
+                .map(|(r, c)| (r as i64, c as i64))
+                .collect();
+            let evolution = Evolution::of(rule, &cells)?;
+            Some((evolution.apgcode(), entry.name.to_string()))
+        })
+        .collect()
+}
+
+// Live cells of the plane grouped by chains of cells at most `radius` apart.
+pub(crate) fn plane_clusters(cells: &HashSet<Point>, radius: i64) -> Vec<Vec<Point>> {
+    let mut seen: HashSet<Point> = HashSet::new();
+    let mut groups = Vec::new();
+    for &start in cells.iter() {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut group = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some((pr, pc)) = queue.pop_front() {
+            group.push((pr, pc));
+            for dr in -radius..=radius {
+                for dc in -radius..=radius {
+                    let next = (pr + dr, pc + dc);
+                    if cells.contains(&next) && seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+// Live cells grouped by chains of cells at most `radius` apart, wrapping around the board. Each
+// group is unwrapped onto the plane, starting from its first cell in row-major order.
+fn clusters(cells: &[Vec<Cell>], radius: i64) -> Vec<Vec<Point>> {
+    let (rows, cols) = (cells.len(), cells[0].len());
+    let mut seen = vec![vec![false; cols]; rows];
+    let mut groups = Vec::new();
+
+    for r in 0..rows {
+        for c in 0..cols {
+            if seen[r][c] || !cells[r][c].is_alive() {
+                continue;
+            }
+            seen[r][c] = true;
+            let mut group = Vec::new();
+            let mut queue = VecDeque::from([(r as i64, c as i64)]);
+            while let Some((pr, pc)) = queue.pop_front() {
+                group.push((pr, pc));
+                for dr in -radius..=radius {
+                    for dc in -radius..=radius {
+                        let (nr, nc) = (pr + dr, pc + dc);
+                        let (wr, wc) = (wrap(nr, rows), wrap(nc, cols));
+                        if !seen[wr][wc] && cells[wr][wc].is_alive() {
+                            seen[wr][wc] = true;
+                            queue.push_back((nr, nc));
+                        }
+                    }
+                }
+            }
+            groups.push(group);
+        }
+    }
+    groups
+}
+
+fn wrap(a: i64, len: usize) -> usize {
+    emod(a as i32, len as i32) as usize
+}
+
+// Top-left corner of an island's bounding box, back on the board.
+fn board_origin(island: &[Point], cells: &[Vec<Cell>]) -> (Rows, Cols) {
+    let (_, (r, c)) = normalized(island);
+    (wrap(r, cells.len()), wrap(c, cells[0].len()))
+}
+
+// Cells moved so the bounding box starts at (0, 0), sorted, and where it started.
+fn normalized(cells: &[Point]) -> (Vec<Point>, Point) {
+    let r0 = cells.iter().map(|p| p.0).min().unwrap_or(0);
+    let c0 = cells.iter().map(|p| p.1).min().unwrap_or(0);
+    let mut shape: Vec<Point> = cells.iter().map(|&(r, c)| (r - r0, c - c0)).collect();
+    shape.sort_unstable();
+    (shape, (r0, c0))
+}
+
+pub(crate) fn step(rule: &Rule, cells: &HashSet<Point>) -> HashSet<Point> {
+    let mut counts: HashMap<Point, usize> = cells.iter().map(|&p| (p, 0)).collect();
+    for &(r, c) in cells.iter() {
+        for dr in -1..=1 {
+            for dc in -1..=1 {
+                if dr != 0 || dc != 0 {
+                    *counts.entry((r + dr, c + dc)).or_default() += 1;
+                }
+            }
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|(point, n)| {
+            let cell = if cells.contains(point) {
+                Cell::ALIVE
+            } else {
+                Cell::DEAD
+            };
+            rule.next(cell, *n).is_alive()
+        })
+        .map(|(point, _)| point)
+        .collect()
+}
+
+// The eight rotations and reflections of a shape, each normalized.
+fn orientations(cells: &[Point]) -> Vec<Vec<Point>> {
+    let maps: [fn(Point) -> Point; 8] = [
+        |(r, c)| (r, c),
+        |(r, c)| (r, -c),
+        |(r, c)| (-r, c),
+        |(r, c)| (-r, -c),
+        |(r, c)| (c, r),
+        |(r, c)| (c, -r),
+        |(r, c)| (-c, r),
+        |(r, c)| (-c, -r),
+    ];
+    maps.iter()
+        .map(|map| normalized(&cells.iter().map(|&p| map(p)).collect::<Vec<_>>()).0)
+        .collect()
+}
+
+// Extended Wechsler format: strips of five rows, each column a base-32 digit with the top row as
+// the lowest bit; runs of blank columns shortened to `0`, `w`, `x` or `y` and a count, strips
+// separated by `z`.
+fn wechsler(shape: &[Point]) -> String {
+    let rows = shape.iter().map(|p| p.0 + 1).max().unwrap_or(0);
+    let cols = shape.iter().map(|p| p.1 + 1).max().unwrap_or(0);
+    let live: HashSet<Point> = shape.iter().copied().collect();
+
+    let mut out = String::new();
+    for strip in 0..(rows + 4) / 5 {
+        if strip > 0 {
+            out.push('z');
+        }
+        let mut blanks = 0;
+        for c in 0..cols {
+            let digit = (0..5)
+                .filter(|&k| live.contains(&(strip * 5 + k, c)))
+                .fold(0, |acc, k| acc | 1 << k);
+            if digit == 0 {
+                blanks += 1;
+                continue;
+            }
+            while blanks > 0 {
+                match blanks {
+                    1 => out.push('0'),
+                    2 => out.push('w'),
+                    3 => out.push('x'),
+                    n => {
+                        let run = n.min(39);
+                        out.push('y');
+                        out.push(WECHSLER_DIGITS[run - 4] as char);
+                        blanks -= run;
+                        continue;
+                    }
+                }
+                blanks = 0;
+            }
+            out.push(WECHSLER_DIGITS[digit] as char);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::rle;
+
+    // The census of a pattern alone in the middle of a board with room to spare.
+    fn census(pattern: &str) -> Census {
+        let pattern = rle::parse(pattern).unwrap();
+        let mut cells = vec![vec![Cell::DEAD; pattern.cols() + 8]; pattern.rows() + 8];
+        pattern.stamp(&mut cells, 4, 4).unwrap();
+        Census::take(&Rule::default(), &cells, 0).unwrap()
+    }
+
+    #[test]
+    fn library_objects_have_their_catagolue_apgcodes() {
+        let expected = [
+            ("Block", "xs4_33", Class::StillLife),
+            ("Beehive", "xs6_696", Class::StillLife),
+            ("Blinker", "xp2_7", Class::Oscillator),
+            (
+                "Pulsar",
+                "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401",
+                Class::Oscillator,
+            ),
+            ("Pentadecathlon", "xp15_4r4z4r4", Class::Oscillator),
+            ("Glider", "xq4_153", Class::Spaceship),
+            ("LWSS", "xq4_6frc", Class::Spaceship),
+            ("MWSS", "xq4_27dee6", Class::Spaceship),
+            ("HWSS", "xq4_27deee6", Class::Spaceship),
+        ];
+        for (name, apgcode, class) in expected {
+            let entry = LIBRARY.iter().find(|entry| entry.name == name).unwrap();
+            let census = census(&rle::write(&entry.pattern()));
+            assert_eq!(census.objects.len(), 1, "{name}");
+            let object = &census.objects[0];
+            assert_eq!(object.apgcode.as_deref(), Some(apgcode), "{name}");
+            assert_eq!(object.name.as_deref(), Some(name));
+            assert_eq!(object.class, class);
+        }
+    }
+
+    #[test]
+    fn apgcodes_do_not_depend_on_orientation_or_phase() {
+        // A glider going up and left, one generation on.
+        let census = census("x = 3, y = 3\no$obo$2o!");
+        assert_eq!(census.objects[0].apgcode.as_deref(), Some("xq4_153"));
+        assert_eq!(
+            census.objects[0]
+                .displacement
+                .map(|(x, y)| x.abs() + y.abs()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn tally_counts_objects_by_kind() {
+        let census = census("x = 13, y = 4\n2o3b2o3b3o$2o3b2o$$3o!");
+        assert_eq!(census.population, 14);
+        let counts: Vec<(&str, usize)> = census
+            .tally
+            .iter()
+            .map(|t| (t.apgcode.as_deref().unwrap(), t.count))
+            .collect();
+        assert_eq!(counts, [("xp2_7", 2), ("xs4_33", 2)]);
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::life::{Cell, Engine, UpdateScheme};
+
+type Rows = usize;
+type Cols = usize;
+
+/// Generations a [`CycleDetector`] remembers by default, which bounds the longest period it finds.
+pub const DEFAULT_HISTORY: usize = 512;
+
+fn cycle_error(err: &str) -> String {
+    format!("[CYCLE ERROR]: {err}.")
+}
+
+/// How a board settled, as found by a [`CycleDetector`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// Every cell died at generation `since`.
+    Empty { since: u64 },
+    /// The board stopped changing at generation `since`.
+    Static { since: u64 },
+    /// From generation `since` on, the board repeats every `period` generations, moved by
+    /// `shift` (rows, cols) each time.
+    Cycle {
+        period: u64,
+        since: u64,
+        shift: (i64, i64),
+    },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Empty { since } => write!(f, "Board died out at generation {since}"),
+            Outcome::Static { since } => write!(f, "Board is static since generation {since}"),
+            Outcome::Cycle {
+                period,
+                since,
+                shift,
+            } => {
+                write!(
+                    f,
+                    "Board cycles with period {period} since generation {since}"
+                )?;
+                match shift {
+                    (0, 0) => Ok(()),
+                    (dr, dc) => write!(f, ", moving ({dc}, {dr}) cells per period"),
+                }
+            }
+        }
+    }
+}
+
+/// What a run does once its board has settled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CycleAction {
+    /// Only say how the board settled.
+    #[default]
+    Report,
+    /// Stop running.
+    Pause,
+    /// Fill the board with a fresh soup and carry on.
+    Reseed,
+}
+
+impl CycleAction {
+    pub fn parse(action: &str) -> Result<Self, String> {
+        match action.trim().to_ascii_lowercase().as_str() {
+            "report" => Ok(CycleAction::Report),
+            "pause" => Ok(CycleAction::Pause),
+            "reseed" => Ok(CycleAction::Reseed),
+            _ => Err(cycle_error(&format!(
+                "unknown action '{action}', expected report, pause or reseed"
+            ))),
+        }
+    }
+}
+
+// A remembered generation: its live cells cropped to their bounding box, which starts at `origin`.
+struct Seen {
+    generation: u64,
+    hash: u64,
+    origin: (Rows, Cols),
+    shape: Vec<Vec<u8>>,
+}
+
+/// Finds when a board dies out, stops changing or starts repeating, possibly moved across the
+/// board as spaceships do.
+///
+/// Each generation is cropped to its live cells and hashed; the detector keeps the last
+/// `capacity` of them and looks for the most recent one with the same shape. Boards wrap around,
+/// so a spaceship crossing an edge is recognised once it is clear of it.
+pub struct CycleDetector {
+    capacity: usize,
+    history: VecDeque<Seen>,
+    found: Option<Outcome>,
+}
+
+impl Default for CycleDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY)
+    }
+}
+
+impl CycleDetector {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            history: VecDeque::new(),
+            found: None,
+        }
+    }
+
+    /// How the board settled, once that has been found.
+    pub fn found(&self) -> Option<Outcome> {
+        self.found
+    }
+
+    /// Whether no generation has been recorded since the detector was created or reset.
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    /// Forgets the history, for a board that was edited.
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.found = None;
+    }
+
+    /// Records generation `generation` of a board run by `engine`. Returns how the board settled
+    /// the first time that is found; runs with random draws in them are never judged. A board has
+    /// died out the generation it is empty, unless the rule brings empty space to life.
+    pub fn observe(
+        &mut self,
+        engine: &mut Engine,
+        generation: u64,
+        cells: &[Vec<Cell>],
+    ) -> Option<Outcome> {
+        if self.found.is_some() || !engine.is_deterministic() {
+            return None;
+        }
+
+        let (origin, shape) = crop(cells);
+        if shape.is_empty() && engine.keeps_empty() {
+            self.found = Some(Outcome::Empty { since: generation });
+            return self.found;
+        }
+        let mut hasher = DefaultHasher::new();
+        shape.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let (rows, cols) = (cells.len(), cells[0].len());
+        let found = self
+            .history
+            .iter()
+            .rev()
+            .filter(|seen| seen.hash == hash && seen.shape == shape)
+            .map(|seen| {
+                let shift = (
+                    wrapped_shift(seen.origin.0, origin.0, rows),
+                    wrapped_shift(seen.origin.1, origin.1, cols),
+                );
+                (seen, shift)
+            })
+            // The checkerboard scheme updates the two colours in turn, so a shape only repeats
+            // on squares of the same colour.
+            .find(|(_, (dr, dc))| {
+                engine.scheme() != UpdateScheme::Checkerboard || (dr + dc).rem_euclid(2) == 0
+            })
+            .map(|(seen, shift)| {
+                let (period, since) = (generation - seen.generation, seen.generation);
+                match (period, shift) {
+                    (1, (0, 0)) => Outcome::Static { since },
+                    _ => Outcome::Cycle {
+                        period,
+                        since,
+                        shift,
+                    },
+                }
+            });
+
+        self.history.push_back(Seen {
+            generation,
+            hash,
+            origin,
+            shape,
+        });
+        while self.history.len() > self.capacity {
+            self.history.pop_front();
+        }
+        self.found = found;
+        found
+    }
+}
+
+// The live cells of a board cropped to their bounding box, and where that box starts.
+fn crop(cells: &[Vec<Cell>]) -> ((Rows, Cols), Vec<Vec<u8>>) {
+    let live = |row: &Vec<Cell>| row.iter().any(Cell::is_alive);
+    let (Some(r0), Some(r1)) = (cells.iter().position(live), cells.iter().rposition(live)) else {
+        return ((0, 0), Vec::new());
+    };
+    let rows = &cells[r0..=r1];
+    let c0 = rows
+        .iter()
+        .filter_map(|row| row.iter().position(Cell::is_alive))
+        .min()
+        .unwrap_or(0);
+    let c1 = rows
+        .iter()
+        .filter_map(|row| row.iter().rposition(Cell::is_alive))
+        .max()
+        .unwrap_or(0);
+
+    let shape = rows
+        .iter()
+        .map(|row| row[c0..=c1].iter().map(|cell| cell.0).collect())
+        .collect();
+    ((r0, c0), shape)
+}
+
+// Displacement from `from` to `to` around a wrapped axis of `len` cells, as the shortest move.
+fn wrapped_shift(from: usize, to: usize, len: usize) -> i64 {
+    let len = len as i64;
+    let d = (to as i64 - from as i64).rem_euclid(len);
+    if d > len / 2 {
+        d - len
+    } else {
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::Rule;
+
+    fn board(rows: &[&str]) -> Vec<Vec<Cell>> {
+        rows.iter()
+            .map(|row| row.chars().map(|ch| Cell((ch == 'o') as u8)).collect())
+            .collect()
+    }
+
+    // Steps `cells` under `engine` until the detector finds how it settled, and returns that.
+    fn settle(engine: &mut Engine, mut cells: Vec<Vec<Cell>>, steps: u64) -> Option<Outcome> {
+        let mut detector = CycleDetector::default();
+        for generation in 0..=steps {
+            if let Some(outcome) = detector.observe(engine, generation, &cells) {
+                return Some(outcome);
+            }
+            engine.step(&mut cells);
+        }
+        None
+    }
+
+    fn life() -> Engine {
+        Engine::new(Rule::default(), UpdateScheme::Synchronous, 0)
+    }
+
+    #[test]
+    fn a_board_dies_out_the_generation_it_empties() {
+        let lone = board(&["...", ".o.", "..."]);
+        assert_eq!(
+            settle(&mut life(), lone, 8),
+            Some(Outcome::Empty { since: 1 })
+        );
+        let empty = board(&["...", "...", "..."]);
+        assert_eq!(
+            settle(&mut life(), empty, 8),
+            Some(Outcome::Empty { since: 0 })
+        );
+    }
+
+    #[test]
+    fn an_empty_board_under_b0_is_not_dead() {
+        // Under B0/S the empty board fills up, then empties again, and so on.
+        let mut engine = Engine::new(Rule::parse("B0/S").unwrap(), UpdateScheme::Synchronous, 0);
+        let empty = board(&["....", "....", "....", "...."]);
+        let cycle = Outcome::Cycle {
+            period: 2,
+            since: 0,
+            shift: (0, 0),
+        };
+        assert_eq!(settle(&mut engine, empty, 8), Some(cycle));
+    }
+
+    #[test]
+    fn still_lifes_are_static_and_oscillators_cycle() {
+        let block = board(&["....", ".oo.", ".oo.", "...."]);
+        assert_eq!(
+            settle(&mut life(), block, 8),
+            Some(Outcome::Static { since: 0 })
+        );
+
+        // A lone cell dies and leaves the block standing, so the board is static from then.
+        let block = board(&["o.....", "......", "......", "...oo.", "...oo.", "......"]);
+        assert_eq!(
+            settle(&mut life(), block, 8),
+            Some(Outcome::Static { since: 1 })
+        );
+
+        let blinker = board(&[".....", ".....", ".ooo.", ".....", "....."]);
+        let cycle = Outcome::Cycle {
+            period: 2,
+            since: 0,
+            shift: (0, 0),
+        };
+        assert_eq!(settle(&mut life(), blinker, 8), Some(cycle));
+    }
+
+    #[test]
+    fn spaceships_cycle_with_their_shift() {
+        let mut glider = vec![vec![Cell::DEAD; 12]; 12];
+        for (r, c) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            glider[r][c] = Cell::ALIVE;
+        }
+        let cycle = Outcome::Cycle {
+            period: 4,
+            since: 0,
+            shift: (1, 1),
+        };
+        assert_eq!(settle(&mut life(), glider.clone(), 8), Some(cycle));
+
+        // Across the edges too: while the glider straddles them (generations 4 and 8) its shape is
+        // cut in two, so it is first matched three periods on, moved the short way around.
+        let mut wrapped = vec![vec![Cell::DEAD; 12]; 12];
+        for (r, row) in glider.iter().enumerate() {
+            for (c, &cell) in row.iter().enumerate() {
+                wrapped[(r + 9) % 12][(c + 9) % 12] = cell;
+            }
+        }
+        let cycle = Outcome::Cycle {
+            period: 12,
+            since: 0,
+            shift: (3, 3),
+        };
+        assert_eq!(settle(&mut life(), wrapped, 40), Some(cycle));
+    }
+
+    #[test]
+    fn checkerboard_only_matches_shifts_of_even_parity() {
+        let at = |col: usize| {
+            let mut cells = vec![vec![Cell::DEAD; 8]; 4];
+            cells[1][col] = Cell::ALIVE;
+            cells[2][col] = Cell::ALIVE;
+            cells
+        };
+
+        let mut engine = life();
+        let mut detector = CycleDetector::default();
+        assert_eq!(detector.observe(&mut engine, 0, &at(1)), None);
+        let cycle = Outcome::Cycle {
+            period: 1,
+            since: 0,
+            shift: (0, 1),
+        };
+        assert_eq!(detector.observe(&mut engine, 1, &at(2)), Some(cycle));
+
+        let mut engine = Engine::new(Rule::default(), UpdateScheme::Checkerboard, 0);
+        let mut detector = CycleDetector::default();
+        assert_eq!(detector.observe(&mut engine, 0, &at(1)), None);
+        assert_eq!(detector.observe(&mut engine, 1, &at(2)), None);
+        let cycle = Outcome::Cycle {
+            period: 2,
+            since: 0,
+            shift: (0, 2),
+        };
+        assert_eq!(detector.observe(&mut engine, 2, &at(3)), Some(cycle));
+    }
+
+    #[test]
+    fn only_the_last_capacity_generations_are_remembered() {
+        let shapes = [
+            board(&["....", ".oo.", ".oo.", "...."]),
+            board(&["....", "ooo.", "....", "...."]),
+            board(&[".o..", "..o.", "ooo.", "...."]),
+        ];
+        for (capacity, expected) in [
+            (2, None),
+            (
+                3,
+                Some(Outcome::Cycle {
+                    period: 3,
+                    since: 0,
+                    shift: (0, 0),
+                }),
+            ),
+        ] {
+            let mut engine = life();
+            let mut detector = CycleDetector::new(capacity);
+            for (generation, cells) in shapes.iter().cycle().take(4).enumerate() {
+                let found = detector.observe(&mut engine, generation as u64, cells);
+                assert_eq!(found, if generation == 3 { expected } else { None });
+            }
+        }
+    }
+
+    #[test]
+    fn runs_with_random_draws_are_not_judged_and_reset_forgets() {
+        let block = board(&["....", ".oo.", ".oo.", "...."]);
+        let mut engine = Engine::new(Rule::default(), UpdateScheme::Alpha(0.5), 0);
+        assert_eq!(settle(&mut engine, block.clone(), 8), None);
+
+        let mut engine = life();
+        let mut detector = CycleDetector::default();
+        detector.observe(&mut engine, 0, &block);
+        assert_eq!(
+            detector.observe(&mut engine, 1, &block),
+            Some(Outcome::Static { since: 0 })
+        );
+        // Once found, the outcome is reported once and kept.
+        assert_eq!(detector.observe(&mut engine, 2, &block), None);
+        assert_eq!(detector.found(), Some(Outcome::Static { since: 0 }));
+
+        detector.reset();
+        assert!(detector.is_empty() && detector.found().is_none());
+        assert_eq!(detector.observe(&mut engine, 5, &block), None);
+    }
+}
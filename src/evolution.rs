@@ -0,0 +1,846 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::Path;
+
+use rand::Rng as _;
+use serde::{Deserialize, Serialize};
+
+use crate::life::Rule;
+use crate::rng::{self, Rng};
+use crate::smoothlife::sweep::{self, Axis, Space};
+use crate::smoothlife::Params;
+
+/// Best genomes of a whole run kept in a checkpoint and its summary.
+pub const HALL_OF_FAME: usize = 10;
+
+pub fn evolution_error(err: &str) -> String {
+    format!("[EVOLUTION ERROR]: {err}.")
+}
+
+/// What a genome may be and how it varies: the genomes a run draws, mutates and crosses.
+pub trait GenomeSpace {
+    type Genome: Clone;
+
+    /// A random genome.
+    fn random(&self, rng: &mut Rng) -> Self::Genome;
+    /// A copy of `genome` with each gene changed with probability `rate`, and at least one.
+    fn mutate(&self, genome: &Self::Genome, rate: f64, rng: &mut Rng) -> Self::Genome;
+    /// A child taking each gene from either parent.
+    fn crossover(&self, a: &Self::Genome, b: &Self::Genome, rng: &mut Rng) -> Self::Genome;
+    /// The genome as text, which also tells genomes apart.
+    fn encode(&self, genome: &Self::Genome) -> String;
+    fn decode(&self, text: &str) -> Result<Self::Genome, String>;
+    /// The space itself as text, so that a checkpoint is only resumed in the same one.
+    fn describe(&self) -> String;
+}
+
+/// Life-like rules without B0: birth on 1 to 8 neighbours and survival on 0 to 8.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LifeLike;
+
+impl LifeLike {
+    // Genes 0 to 7 are births on 1 to 8 neighbours, 8 to 16 survivals on 0 to 8.
+    const GENES: usize = 17;
+
+    fn genes(rule: &Rule) -> [bool; Self::GENES] {
+        let (birth, survival) = (rule.birth(), rule.survival());
+        std::array::from_fn(|i| match i {
+            0..=7 => birth[i + 1],
+            _ => survival[i - 8],
+        })
+    }
+
+    fn rule(genes: &[bool; Self::GENES]) -> Rule {
+        let birth = std::array::from_fn(|n| n > 0 && genes[n - 1]);
+        let survival = std::array::from_fn(|n| genes[n + 8]);
+        Rule::from_tables(birth, survival)
+    }
+}
+
+impl GenomeSpace for LifeLike {
+    type Genome = Rule;
+
+    fn random(&self, rng: &mut Rng) -> Rule {
+        Self::rule(&std::array::from_fn(|_| rng.gen_bool(0.3)))
+    }
+
+    fn mutate(&self, rule: &Rule, rate: f64, rng: &mut Rng) -> Rule {
+        let mut genes = Self::genes(rule);
+        let forced = rng.gen_range(0..Self::GENES);
+        for (i, gene) in genes.iter_mut().enumerate() {
+            if i == forced || rng.gen_bool(rate) {
+                *gene = !*gene;
+            }
+        }
+        Self::rule(&genes)
+    }
+
+    fn crossover(&self, a: &Rule, b: &Rule, rng: &mut Rng) -> Rule {
+        let (a, b) = (Self::genes(a), Self::genes(b));
+        Self::rule(&std::array::from_fn(|i| {
+            if rng.gen_bool(0.5) {
+                a[i]
+            } else {
+                b[i]
+            }
+        }))
+    }
+
+    fn encode(&self, rule: &Rule) -> String {
+        rule.to_string()
+    }
+
+    fn decode(&self, text: &str) -> Result<Rule, String> {
+        let rule = Rule::parse(text)?;
+        if rule.birth()[0] {
+            return Err(evolution_error(&format!("{text} has B0")));
+        }
+        Ok(rule)
+    }
+
+    fn describe(&self) -> String {
+        "life-like".to_string()
+    }
+}
+
+/// SmoothLife parameters, with those of the axes evolving within their ranges.
+impl GenomeSpace for Space {
+    type Genome = Params;
+
+    fn random(&self, rng: &mut Rng) -> Params {
+        let mut params = self.sample(1, rng)[0];
+        for axis in self.axes() {
+            let value = quantize(sweep::get(&params, axis.name));
+            sweep::set(&mut params, axis.name, value);
+        }
+        params
+    }
+
+    // Mutations move a value by up to a fifth of its range.
+    fn mutate(&self, params: &Params, rate: f64, rng: &mut Rng) -> Params {
+        let mut params = *params;
+        let forced = rng.gen_range(0..self.axes().len().max(1));
+        for (i, axis) in self.axes().iter().enumerate() {
+            if i == forced || rng.gen_bool(rate) {
+                let (low, high) = (axis.from.min(axis.to), axis.from.max(axis.to));
+                let value =
+                    sweep::get(&params, axis.name) + rng.gen_range(-0.2..=0.2) * (high - low);
+                sweep::set(&mut params, axis.name, quantize(value.clamp(low, high)));
+            }
+        }
+        params
+    }
+
+    fn crossover(&self, a: &Params, b: &Params, rng: &mut Rng) -> Params {
+        let mut child = *a;
+        for axis in self.axes().iter().filter(|_| rng.gen_bool(0.5)) {
+            sweep::set(&mut child, axis.name, sweep::get(b, axis.name));
+        }
+        child
+    }
+
+    fn encode(&self, params: &Params) -> String {
+        let genes: Vec<String> = self
+            .axes()
+            .iter()
+            .map(|axis| format!("{}={}", axis.name, sweep::get(params, axis.name)))
+            .collect();
+        genes.join(" ")
+    }
+
+    fn decode(&self, text: &str) -> Result<Params, String> {
+        let mut params = self.base();
+        for gene in text.split_whitespace() {
+            let axis = Axis::parse(gene)?;
+            sweep::set(&mut params, axis.name, axis.from);
+        }
+        Ok(params)
+    }
+
+    fn describe(&self) -> String {
+        let axes: Vec<String> = self
+            .axes()
+            .iter()
+            .map(|axis| format!("{}={}:{}", axis.name, axis.from, axis.to))
+            .collect();
+        axes.join(" ")
+    }
+}
+
+// Evolved parameters keep four decimals, so that they read well and come back the same from a
+// checkpoint.
+fn quantize(value: f32) -> f32 {
+    (value * 1e4).round() / 1e4
+}
+
+/// A measure of how a run went, between 0 and 1, that a run can be selected for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Fitness {
+    /// The population (or mass) settles down rather than swinging or dying out.
+    Stability,
+    /// Many distinct objects are left.
+    Diversity,
+    /// Something moves across the board.
+    Motion,
+    /// The final board is disordered, from the entropy of its local patterns.
+    Entropy,
+}
+
+impl Fitness {
+    pub const ALL: [Fitness; 4] = [
+        Fitness::Stability,
+        Fitness::Diversity,
+        Fitness::Motion,
+        Fitness::Entropy,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fitness::Stability => "stability",
+            Fitness::Diversity => "diversity",
+            Fitness::Motion => "motion",
+            Fitness::Entropy => "entropy",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Fitness::ALL
+            .into_iter()
+            .find(|fitness| fitness.name() == name.trim().to_ascii_lowercase())
+            .ok_or_else(|| {
+                let names: Vec<&str> = Fitness::ALL.iter().map(Fitness::name).collect();
+                evolution_error(&format!(
+                    "unknown fitness '{name}', expected one of {}",
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Every [`Fitness`] measured on a run, or averaged over several.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Traits {
+    pub stability: f64,
+    pub diversity: f64,
+    pub motion: f64,
+    pub entropy: f64,
+}
+
+impl Traits {
+    pub fn get(&self, fitness: Fitness) -> f64 {
+        match fitness {
+            Fitness::Stability => self.stability,
+            Fitness::Diversity => self.diversity,
+            Fitness::Motion => self.motion,
+            Fitness::Entropy => self.entropy,
+        }
+    }
+
+    pub fn mean(runs: &[Traits]) -> Traits {
+        let n = runs.len().max(1) as f64;
+        let sum = |f: fn(&Traits) -> f64| runs.iter().map(f).sum::<f64>() / n;
+        Traits {
+            stability: sum(|t| t.stability),
+            diversity: sum(|t| t.diversity),
+            motion: sum(|t| t.motion),
+            entropy: sum(|t| t.entropy),
+        }
+    }
+}
+
+/// Shannon entropy of a histogram, divided by its most, `log2(counts.len())`.
+pub fn entropy(counts: &[usize]) -> f64 {
+    let total = counts.iter().sum::<usize>() as f64;
+    if total == 0.0 || counts.len() < 2 {
+        return 0.0;
+    }
+    let bits: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+    bits / (counts.len() as f64).log2()
+}
+
+/// `count` squashed into [0, 1), reaching one half at `half`.
+pub fn saturate(count: f64, half: f64) -> f64 {
+    count / (count + half)
+}
+
+/// Weighted fitnesses that a run is scored on, their weighted mean.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Objective {
+    terms: Vec<(Fitness, f64)>,
+}
+
+impl Objective {
+    /// Parses fitness names with optional weights, such as `diversity,motion=2`.
+    pub fn parse(objective: &str) -> Result<Self, String> {
+        let mut terms = Vec::new();
+        for term in objective.split(',').filter(|t| !t.trim().is_empty()) {
+            let (name, weight) = match term.split_once('=') {
+                Some((name, weight)) => (
+                    name,
+                    weight
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|w| w.is_finite() && *w > 0.0)
+                        .ok_or_else(|| {
+                            evolution_error(&format!("'{weight}' is not a positive weight"))
+                        })?,
+                ),
+                None => (term, 1.0),
+            };
+            let fitness = Fitness::parse(name)?;
+            if terms.iter().any(|&(f, _)| f == fitness) {
+                return Err(evolution_error(&format!(
+                    "{} is given twice",
+                    fitness.name()
+                )));
+            }
+            terms.push((fitness, weight));
+        }
+        if terms.is_empty() {
+            return Err(evolution_error("no fitness given"));
+        }
+        Ok(Self { terms })
+    }
+
+    pub fn score(&self, traits: &Traits) -> f64 {
+        let total: f64 = self.terms.iter().map(|&(_, w)| w).sum();
+        self.terms
+            .iter()
+            .map(|&(fitness, weight)| weight * traits.get(fitness))
+            .sum::<f64>()
+            / total
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|&(fitness, weight)| {
+                if weight == 1.0 {
+                    fitness.name().to_string()
+                } else {
+                    format!("{}={weight}", fitness.name())
+                }
+            })
+            .collect();
+        write!(f, "{}", terms.join(","))
+    }
+}
+
+impl TryFrom<String> for Objective {
+    type Error = String;
+
+    fn try_from(objective: String) -> Result<Self, String> {
+        Objective::parse(&objective)
+    }
+}
+
+impl From<Objective> for String {
+    fn from(objective: Objective) -> String {
+        objective.to_string()
+    }
+}
+
+/// How a run evolves; fixed once it starts, and saved with its checkpoints.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Settings {
+    pub seed: u64,
+    pub population: usize,
+    /// Best genomes carried over unchanged to the next generation.
+    pub elite: usize,
+    /// Genomes drawn for each parent, of which the fittest wins.
+    pub tournament: usize,
+    /// Chance of each gene changing in a child.
+    pub mutation: f64,
+    /// Runs, from different soups, each genome is scored over.
+    pub trials: usize,
+    pub objective: Objective,
+    /// [`GenomeSpace::describe`] of the genomes evolved.
+    pub space: String,
+    /// What each trial is run with, as described by the caller, such as the board and soup size.
+    pub conditions: String,
+}
+
+/// A genome as scored in one generation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scored {
+    pub genome: String,
+    pub generation: u64,
+    pub fitness: f64,
+    pub traits: Traits,
+}
+
+/// How one generation went.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub generation: u64,
+    pub best: Scored,
+    pub mean_fitness: f64,
+    /// Different genomes in the generation.
+    pub distinct: usize,
+}
+
+/// All there is to a run between two generations, which resumes it exactly.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub settings: Settings,
+    pub generation: u64,
+    pub population: Vec<String>,
+    pub history: Vec<Record>,
+    pub hall_of_fame: Vec<Scored>,
+}
+
+impl Checkpoint {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| evolution_error(&format!("cannot read {}: {err}", path.display())))?;
+        serde_json::from_str(&text).map_err(|err| {
+            evolution_error(&format!("{} is not a checkpoint: {err}", path.display()))
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self).expect("a checkpoint always serializes");
+        fs::write(path, json + "\n")
+            .map_err(|err| evolution_error(&format!("cannot write {}: {err}", path.display())))
+    }
+}
+
+/// A genetic search over a [`GenomeSpace`].
+///
+/// Each generation, every distinct genome is scored by the caller on the same soups, with tournament
+/// selection, uniform crossover and mutation breeding the next one. Everything random is drawn
+/// from the seed and the generation, so a run is reproduced by its seed, and resumed exactly from a
+/// checkpoint.
+pub struct Evolution<S: GenomeSpace> {
+    space: S,
+    settings: Settings,
+    generation: u64,
+    population: Vec<S::Genome>,
+    history: Vec<Record>,
+    hall_of_fame: Vec<Scored>,
+}
+
+impl<S: GenomeSpace> Evolution<S> {
+    /// Starts a run from `first` genomes, filled up with random ones.
+    pub fn new(space: S, mut settings: Settings, first: Vec<S::Genome>) -> Result<Self, String> {
+        if settings.population < 2 || settings.elite >= settings.population {
+            return Err(evolution_error(
+                "a population needs at least two genomes and room for more than the elite",
+            ));
+        }
+        if settings.trials == 0 || settings.tournament == 0 {
+            return Err(evolution_error(
+                "genomes need at least one trial and tournaments at least one entrant",
+            ));
+        }
+        if !(0.0..=1.0).contains(&settings.mutation) {
+            return Err(evolution_error(&format!(
+                "mutation rate {} is not in [0, 1]",
+                settings.mutation
+            )));
+        }
+        settings.space = space.describe();
+        let mut rng = Self::rng(&settings, 0);
+        let mut population = first;
+        population.truncate(settings.population);
+        while population.len() < settings.population {
+            population.push(space.random(&mut rng));
+        }
+        Ok(Self {
+            space,
+            settings,
+            generation: 0,
+            population,
+            history: Vec::new(),
+            hall_of_fame: Vec::new(),
+        })
+    }
+
+    /// Picks a run up where `checkpoint` left it; its conditions must be `conditions`.
+    pub fn resume(space: S, checkpoint: Checkpoint, conditions: &str) -> Result<Self, String> {
+        let settings = checkpoint.settings;
+        if settings.space != space.describe() {
+            return Err(evolution_error(&format!(
+                "the checkpoint evolves {}, not {}",
+                settings.space,
+                space.describe()
+            )));
+        }
+        if settings.conditions != conditions {
+            return Err(evolution_error(&format!(
+                "the checkpoint was run with {}, not {conditions}",
+                settings.conditions
+            )));
+        }
+        let population = checkpoint
+            .population
+            .iter()
+            .map(|genome| space.decode(genome))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            space,
+            settings,
+            generation: checkpoint.generation,
+            population,
+            history: checkpoint.history,
+            hall_of_fame: checkpoint.hall_of_fame,
+        })
+    }
+
+    pub fn space(&self) -> &S {
+        &self.space
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The generation about to be scored.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn history(&self) -> &[Record] {
+        &self.history
+    }
+
+    /// The best genomes so far, best first, each once.
+    pub fn hall_of_fame(&self) -> &[Scored] {
+        &self.hall_of_fame
+    }
+
+    /// Seeds of the soups every genome of this generation is tried on; they change every
+    /// generation, so that nothing is bred for one soup.
+    pub fn soup_seeds(&self) -> Vec<u64> {
+        let trials = self.settings.trials as u64;
+        (0..trials)
+            .map(|k| {
+                self.settings
+                    .seed
+                    .wrapping_add(self.generation * trials + k)
+            })
+            .collect()
+    }
+
+    /// The distinct genomes of this generation, to be scored in this order.
+    pub fn genomes(&self) -> Vec<S::Genome> {
+        let mut seen = HashMap::new();
+        self.population
+            .iter()
+            .filter(|genome| {
+                let len = seen.len();
+                *seen.entry(self.space.encode(genome)).or_insert(len) == len
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Scores this generation from the traits of [`Self::genomes`], and breeds the next one.
+    pub fn advance(&mut self, traits: &[Traits]) -> &Record {
+        let genomes = self.genomes();
+        assert_eq!(genomes.len(), traits.len(), "every genome needs its traits");
+        let mut scored: Vec<(S::Genome, Scored)> = genomes
+            .into_iter()
+            .zip(traits.iter())
+            .map(|(genome, &traits)| {
+                let scored = Scored {
+                    genome: self.space.encode(&genome),
+                    generation: self.generation,
+                    fitness: self.settings.objective.score(&traits),
+                    traits,
+                };
+                (genome, scored)
+            })
+            .collect();
+        scored.sort_by(|(_, a), (_, b)| {
+            b.fitness
+                .total_cmp(&a.fitness)
+                .then_with(|| a.genome.cmp(&b.genome))
+        });
+        let fitness: HashMap<String, f64> = scored
+            .iter()
+            .map(|(_, s)| (s.genome.clone(), s.fitness))
+            .collect();
+
+        for (_, s) in scored.iter() {
+            match self.hall_of_fame.iter_mut().find(|h| h.genome == s.genome) {
+                Some(h) if h.fitness < s.fitness => *h = s.clone(),
+                Some(_) => {}
+                None => self.hall_of_fame.push(s.clone()),
+            }
+        }
+        self.hall_of_fame.sort_by(|a, b| {
+            b.fitness
+                .total_cmp(&a.fitness)
+                .then_with(|| a.genome.cmp(&b.genome))
+        });
+        self.hall_of_fame.truncate(HALL_OF_FAME);
+
+        let mean_fitness = self
+            .population
+            .iter()
+            .map(|genome| fitness[&self.space.encode(genome)])
+            .sum::<f64>()
+            / self.population.len() as f64;
+        self.history.push(Record {
+            generation: self.generation,
+            best: scored[0].1.clone(),
+            mean_fitness,
+            distinct: scored.len(),
+        });
+
+        self.generation += 1;
+        let mut rng = Self::rng(&self.settings, self.generation);
+        let mut next: Vec<S::Genome> = scored
+            .iter()
+            .take(self.settings.elite)
+            .map(|(genome, _)| genome.clone())
+            .collect();
+        while next.len() < self.settings.population {
+            let a = self.tournament(&scored, &mut rng);
+            let b = self.tournament(&scored, &mut rng);
+            let child = self.space.crossover(a, b, &mut rng);
+            next.push(self.space.mutate(&child, self.settings.mutation, &mut rng));
+        }
+        self.population = next;
+        self.history.last().unwrap()
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            settings: self.settings.clone(),
+            generation: self.generation,
+            population: self
+                .population
+                .iter()
+                .map(|genome| self.space.encode(genome))
+                .collect(),
+            history: self.history.clone(),
+            hall_of_fame: self.hall_of_fame.clone(),
+        }
+    }
+
+    /// The best genomes of the run so far, one row per genome.
+    pub fn table(&self) -> String {
+        let width = self
+            .hall_of_fame
+            .iter()
+            .map(|s| s.genome.len())
+            .max()
+            .unwrap_or(0)
+            .max("genome".len());
+        let mut out = format!(
+            "Best of {} generations for {} (seed {}):\n",
+            self.history.len(),
+            self.settings.objective,
+            self.settings.seed
+        );
+        write!(
+            out,
+            "{:>7}  {:<width$}  {:>10}",
+            "fitness", "genome", "generation"
+        )
+        .unwrap();
+        for fitness in Fitness::ALL {
+            write!(out, "  {:>9}", fitness.name()).unwrap();
+        }
+        out.push('\n');
+        for s in self.hall_of_fame.iter() {
+            write!(
+                out,
+                "{:>7.3}  {:<width$}  {:>10}",
+                s.fitness, s.genome, s.generation
+            )
+            .unwrap();
+            for fitness in Fitness::ALL {
+                write!(out, "  {:>9.3}", s.traits.get(fitness)).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // The fittest of `tournament` genomes drawn from the scored ones, which are sorted.
+    fn tournament<'a>(&self, scored: &'a [(S::Genome, Scored)], rng: &mut Rng) -> &'a S::Genome {
+        let best = (0..self.settings.tournament)
+            .map(|_| rng.gen_range(0..scored.len()))
+            .min()
+            .unwrap();
+        &scored[best].0
+    }
+
+    fn rng(settings: &Settings, generation: u64) -> Rng {
+        rng::from_seed_stream(
+            settings.seed.wrapping_add(generation),
+            rng::EVOLUTION_STREAM,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smoothlife::Params;
+
+    fn settings(seed: u64) -> Settings {
+        Settings {
+            seed,
+            population: 12,
+            elite: 2,
+            tournament: 3,
+            mutation: 0.1,
+            trials: 2,
+            objective: Objective::parse("diversity,motion=2").unwrap(),
+            space: String::new(),
+            conditions: "test soups".to_string(),
+        }
+    }
+
+    // Traits that only depend on the genome, as if every soup were run.
+    fn traits<S: GenomeSpace>(evolution: &Evolution<S>) -> Vec<Traits> {
+        evolution
+            .genomes()
+            .iter()
+            .map(|genome| {
+                let text = evolution.space().encode(genome);
+                let sum = text.bytes().map(u64::from).sum::<u64>();
+                Traits {
+                    stability: 0.0,
+                    diversity: (sum % 97) as f64 / 97.0,
+                    motion: (text.len() % 5) as f64 / 5.0,
+                    entropy: 0.0,
+                }
+            })
+            .collect()
+    }
+
+    fn checkpoint_json<S: GenomeSpace>(evolution: &Evolution<S>) -> String {
+        serde_json::to_string(&evolution.checkpoint()).unwrap()
+    }
+
+    #[test]
+    fn settings_are_checked() {
+        let bad = [
+            Settings {
+                population: 1,
+                ..settings(0)
+            },
+            Settings {
+                elite: 12,
+                ..settings(0)
+            },
+            Settings {
+                trials: 0,
+                ..settings(0)
+            },
+            Settings {
+                tournament: 0,
+                ..settings(0)
+            },
+            Settings {
+                mutation: 1.5,
+                ..settings(0)
+            },
+        ];
+        for settings in bad {
+            assert!(Evolution::new(LifeLike, settings, Vec::new()).is_err());
+        }
+
+        let evolution = Evolution::new(LifeLike, settings(0), Vec::new()).unwrap();
+        assert_eq!(evolution.settings().space, "life-like");
+        assert_eq!(evolution.checkpoint().population.len(), 12);
+    }
+
+    #[test]
+    fn genomes_are_distinct_in_the_order_first_met() {
+        let rules: Vec<Rule> = ["B36/S23", "B3/S23", "B36/S23", "B2/S", "B3/S23"]
+            .iter()
+            .map(|rule| Rule::parse(rule).unwrap())
+            .collect();
+        let settings = Settings {
+            population: 5,
+            ..settings(0)
+        };
+        let evolution = Evolution::new(LifeLike, settings, rules).unwrap();
+        let genomes: Vec<String> = evolution
+            .genomes()
+            .iter()
+            .map(|rule| rule.to_string())
+            .collect();
+        assert_eq!(genomes, ["B36/S23", "B3/S23", "B2/S"]);
+    }
+
+    #[test]
+    fn runs_are_reproduced_by_their_seed_and_resumed_exactly() {
+        let run = |seed: u64, generations: u64| {
+            let mut evolution = Evolution::new(LifeLike, settings(seed), Vec::new()).unwrap();
+            while evolution.generation() < generations {
+                let traits = traits(&evolution);
+                evolution.advance(&traits);
+            }
+            evolution
+        };
+
+        let expected = checkpoint_json(&run(5, 6));
+        assert_eq!(checkpoint_json(&run(5, 6)), expected);
+        assert_ne!(checkpoint_json(&run(6, 6)), expected);
+
+        let halfway = run(5, 3).checkpoint();
+        let text = serde_json::to_string(&halfway).unwrap();
+        let checkpoint: Checkpoint = serde_json::from_str(&text).unwrap();
+        assert!(Evolution::resume(LifeLike, checkpoint.clone(), "other soups").is_err());
+        let mut resumed = Evolution::resume(LifeLike, checkpoint, "test soups").unwrap();
+        while resumed.generation() < 6 {
+            let traits = traits(&resumed);
+            resumed.advance(&traits);
+        }
+        assert_eq!(checkpoint_json(&resumed), expected);
+
+        let history = resumed.history();
+        assert_eq!(history.len(), 6);
+        let best = resumed.hall_of_fame()[0].fitness;
+        assert!(history.iter().all(|record| record.best.fitness <= best));
+    }
+
+    #[test]
+    fn genomes_encode_and_decode_back() {
+        let mut rng = rng::from_seed(3);
+        for _ in 0..50 {
+            let rule = LifeLike.random(&mut rng);
+            let rule = LifeLike.mutate(&rule, 0.2, &mut rng);
+            assert_eq!(LifeLike.decode(&LifeLike.encode(&rule)).unwrap(), rule);
+            assert!(!rule.birth()[0]);
+        }
+        assert!(LifeLike.decode("B03/S23").is_err());
+
+        let axes = ["b1=0.2:0.35", "d2=0.35:0.55", "alpha-m=0.1:0.2"]
+            .iter()
+            .map(|axis| Axis::parse(axis).unwrap())
+            .collect();
+        let space = Space::new(Params::default(), axes).unwrap();
+        for _ in 0..50 {
+            let params = space.random(&mut rng);
+            let params = space.mutate(&params, 0.5, &mut rng);
+            assert_eq!(space.decode(&space.encode(&params)).unwrap(), params);
+            assert_eq!(
+                (params.ra, params.dt),
+                (Params::default().ra, Params::default().dt)
+            );
+        }
+    }
+}
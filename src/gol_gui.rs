@@ -1,8 +1,32 @@
+use std::path::Path;
+
+use cellular_automata::census::{census_error, Census};
+use cellular_automata::cycle::{CycleAction, CycleDetector, Outcome};
+use cellular_automata::golly::GollyRule;
+use cellular_automata::history::{self, History, LifeSample};
+use cellular_automata::life::{
+    rule_error, split_bounded_grid, Automaton, Cell, Engine, Rule, UpdateScheme,
+};
+use cellular_automata::pattern::library::Pick;
+use cellular_automata::pattern::{self, Pattern};
+use cellular_automata::render::gif::GifRecorder;
+use cellular_automata::render::sequence::FrameSequence;
+use cellular_automata::render::stream::{FrameStream, StreamFormat};
+use cellular_automata::render::{
+    self, image_error, Capture, CaptureOptions, Captures, Frame, Palette,
+};
+use cellular_automata::rng::{self, Rng};
+use cellular_automata::sand::{Material, SandBoard};
+use cellular_automata::script::ScriptRule;
+use cellular_automata::soup::{self, Soup, Symmetry};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::{Canvas, RenderTarget};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Canvas, RenderTarget};
+use sdl2::surface::Surface;
 use sdl2::video::Window;
 
 const WINDOW_HEIGHT: u32 = 800;
@@ -13,6 +37,30 @@ const RENDER_TIMEOUT: f32 = 0.2f32;
 
 const ROWS: usize = 100;
 const COLS: usize = 100;
+const BRUSH_RADIUS: usize = 2;
+const DEFAULT_SAVE_FILE: &str = "board.rle";
+const DEFAULT_PNG_FILE: &str = "board.png";
+const DEFAULT_CELL_SIZE: u32 = 8;
+const DEFAULT_THRESHOLD: f32 = 0.5;
+const DEFAULT_GIF_FILE: &str = "board.gif";
+const DEFAULT_FRAME_DELAY: u32 = 100;
+const PREVIEW_COLOR: u32 = 0x00C8C8FF;
+const CHART_BACKGROUND: u32 = 0x000000B0;
+const CHART_COLOR: u32 = 0xFFC832FF;
+const DEFAULT_CENSUS_FILE: &str = "census.json";
+const MAX_SETTLE_GENERATIONS: u64 = 100_000;
+
+const APP_NAME: &str = "GoLrs";
+const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
+const BIN_NAME: Option<&str> = option_env!("CARGO_PKG_NAME");
+const DESCRIPTION: Option<&str> = option_env!("CARGO_PKG_DESCRIPTION");
+const AUTHORS: Option<&str> = option_env!("CARGO_PKG_AUTHORS");
+
+const HELP_TEMPLATE: &str = "\
+GoLrs ({version}) - {about-with-newline}
+{usage-heading} {usage}
+{all-args}
+{author-section}";
 
 #[macro_export]
 macro_rules! RGBA_HEX {
@@ -33,124 +81,386 @@ const MIN_COLS: u16 = 10;
 type Rows = usize;
 type Cols = usize;
 
-#[derive(Clone, PartialEq)]
-enum Cell {
-    Dead,
-    Alive,
-}
-
-impl Cell {
-    fn as_color_hex(&self) -> u32 {
-        match self {
-            Cell::Alive => 0xFFFFFFFF,
-            Cell::Dead => 0x000000FF,
-        }
-    }
-
-    fn toggle(&mut self) {
-        match self {
-            Cell::Alive => *self = Cell::Dead,
-            Cell::Dead => *self = Cell::Alive,
-        }
-    }
-}
-
 pub struct Board {
     board: Vec<Vec<Cell>>,
     cursor: (Cols, Rows),
+    engine: Engine,
+    soup: Soup,
+    soup_size: Option<(Rows, Cols)>,
+    rng: Rng,
+    generation: u64,
+    cycles: CycleDetector,
+    history: History<LifeSample>,
 }
 
 impl Board {
-    pub fn new(rows: Rows, cols: Cols) -> Self {
+    pub fn new(rows: Rows, cols: Cols, engine: Engine, rng: Rng) -> Self {
         let rows = rows.clamp(MIN_ROWS as usize, MAX_ROWS as usize);
         let cols = cols.clamp(MIN_COLS as usize, MAX_COLS as usize);
 
         Self {
-            board: vec![vec![Cell::Dead; cols]; rows],
+            board: vec![vec![Cell::DEAD; cols]; rows],
             cursor: (0, 0),
+            engine,
+            soup: Soup::default(),
+            soup_size: None,
+            rng,
+            generation: 0,
+            cycles: CycleDetector::default(),
+            history: History::default(),
         }
     }
 
-    pub fn next_gen(&mut self) {
-        let mut new_board = self.board.clone();
+    pub fn set_soup(&mut self, soup: Soup, size: Option<(Rows, Cols)>) {
+        self.soup = soup;
+        self.soup_size = size;
+    }
 
-        for (ir, row) in new_board.iter_mut().enumerate() {
-            for (ic, item) in row.iter_mut().enumerate() {
-                let n = self.count_n(ir, ic);
-                match self.board[ir][ic] {
-                    Cell::Dead => *item = if n == 3 { Cell::Alive } else { Cell::Dead },
-                    Cell::Alive => {
-                        *item = if (2..=3).contains(&n) {
-                            Cell::Alive
-                        } else {
-                            Cell::Dead
-                        }
-                    }
-                }
+    /// Steps the board; returns how it settled, the generation that is first found.
+    pub fn next_gen(&mut self) -> Option<Outcome> {
+        // The board a run starts from may already be part of the cycle, and of the history.
+        if self.cycles.is_empty() {
+            self.cycles
+                .observe(&mut self.engine, self.generation, &self.board);
+        }
+        if self.history.is_empty() {
+            self.history.push(self.sample(0, 0));
+        }
+        let previous = self.board.clone();
+        self.engine.step(&mut self.board);
+        self.generation += 1;
+
+        let (mut births, mut deaths) = (0, 0);
+        for (before, after) in previous.iter().flatten().zip(self.board.iter().flatten()) {
+            match (before.is_alive(), after.is_alive()) {
+                (false, true) => births += 1,
+                (true, false) => deaths += 1,
+                _ => {}
             }
         }
+        self.history.push(self.sample(births, deaths));
+        self.cycles
+            .observe(&mut self.engine, self.generation, &self.board)
+    }
+
+    fn sample(&self, births: usize, deaths: usize) -> LifeSample {
+        LifeSample {
+            generation: self.generation,
+            population: self.board.iter().flatten().filter(|c| c.is_alive()).count(),
+            births,
+            deaths,
+        }
+    }
 
-        self.board = new_board;
+    pub fn stamp(&mut self, pattern: &Pattern, row: Rows, col: Cols) -> Result<(), String> {
+        self.cycles.reset();
+        pattern.stamp(&mut self.board, row, col)
+    }
+
+    /// Thresholds an image into the board, scaled to fit right of and below `(row, col)`.
+    pub fn import_at(
+        &mut self,
+        path: &str,
+        row: Rows,
+        col: Cols,
+        threshold: f32,
+    ) -> Result<(), String> {
+        let (rows, cols) = (self.board.len(), self.board[0].len());
+        let pattern = render::import_pattern(path, rows - row, cols - col, threshold)?;
+        self.stamp(&pattern, row, col)
+    }
+
+    pub fn to_pattern(&self) -> Pattern {
+        Pattern {
+            rule: Some(self.engine.automaton().to_string()),
+            ..Pattern::from_cells(&self.board)
+        }
+    }
+
+    pub fn to_frame(&self, cell_size: usize, palette: Option<&Palette>) -> Frame {
+        Frame::from_cells(&self.board, self.engine.automaton(), cell_size, palette)
+    }
+
+    /// Starts recording the board to a GIF, beginning with the current generation.
+    pub fn record(
+        &self,
+        path: &str,
+        cell_size: usize,
+        palette: Option<&Palette>,
+        options: CaptureOptions,
+    ) -> Result<GifRecorder, String> {
+        let frame = self.to_frame(cell_size, palette);
+        let colours = render::state_colours(self.engine.automaton(), palette);
+        let mut recorder = GifRecorder::create(path, frame.width, frame.height, &colours, options)?;
+        recorder.offer(&frame)?;
+        Ok(recorder)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.board.iter().flatten().all(|&cell| cell == Cell::DEAD)
+    }
+
+    /// Classifies the objects on the board, which only Life-like rules allow.
+    pub fn census(&self) -> Result<Census, String> {
+        match self.engine.automaton() {
+            Automaton::LifeLike(rule) => Census::take(rule, &self.board, self.generation),
+            _ => Err(census_error("a census needs a Life-like rule")),
+        }
+    }
+
+    /// Steps the board until it settles, for at most `limit` generations. Returns whether it did.
+    pub fn settle(&mut self, limit: u64) -> bool {
+        while self.cycles.found().is_none() && self.generation < limit {
+            self.next_gen();
+        }
+        self.cycles.found().is_some()
     }
 
     pub fn toggle_cur_cell(&mut self) {
+        self.cycles.reset();
         let (c, r) = self.cursor;
-        self.board[r][c].toggle();
+        self.board[r][c].cycle(self.engine.automaton().n_states());
     }
 
     pub fn clear(&mut self) {
-        self.board = Self::new(self.board.len(), self.board[0].len()).board;
+        self.cycles.reset();
+        for row in self.board.iter_mut() {
+            row.fill(Cell::DEAD);
+        }
     }
 
     pub fn draw<T: RenderTarget>(&self, c: &mut Canvas<T>, width: u32, height: u32) {
-        let cell_h = height as i32 / self.board.len() as i32;
-        let cell_w = width as i32 / self.board[0].len() as i32;
+        let automaton = self.engine.automaton();
+        draw_grid(c, &self.board, width, height, |cell| {
+            automaton.color_hex(*cell)
+        });
+    }
 
-        for (ir, row) in self.board.iter().enumerate() {
-            for (ic, item) in row.iter().enumerate() {
-                let x = ic as i32 * cell_w;
-                let y = ir as i32 * cell_h;
+    pub fn randomize(&mut self) {
+        self.cycles.reset();
+        let (rows, cols) = (self.board.len(), self.board[0].len());
+        let (h, w) = self.soup_size.unwrap_or((rows, cols));
+        let (h, w) = (h.min(rows), w.min(cols));
+        let (r0, c0) = ((rows - h) / 2, (cols - w) / 2);
+        let soup = self.soup.generate(h, w, &mut self.rng);
 
-                let rect = Rect::new(x, y, cell_w as u32, cell_h as u32);
-                c.set_draw_color(RGBA_HEX!(item.as_color_hex()));
-                c.fill_rect(rect).unwrap();
+        for (r, row) in soup.iter().enumerate() {
+            for (c, &alive) in row.iter().enumerate() {
+                self.board[r0 + r][c0 + c] = if alive { Cell::ALIVE } else { Cell::DEAD };
             }
         }
     }
+}
 
-    pub fn randomize(&mut self) {
-        for row in self.board.iter_mut() {
-            for item in row.iter_mut() {
-                *item = if rand::random() {
-                    Cell::Dead
-                } else {
-                    Cell::Alive
-                }
+fn draw_grid<T, C, F>(c: &mut Canvas<T>, grid: &[Vec<C>], width: u32, height: u32, color: F)
+where
+    T: RenderTarget,
+    F: Fn(&C) -> u32,
+{
+    let cell_h = height as i32 / grid.len() as i32;
+    let cell_w = width as i32 / grid[0].len() as i32;
+
+    for (ir, row) in grid.iter().enumerate() {
+        for (ic, item) in row.iter().enumerate() {
+            let x = ic as i32 * cell_w;
+            let y = ir as i32 * cell_h;
+
+            let rect = Rect::new(x, y, cell_w as u32, cell_h as u32);
+            c.set_draw_color(RGBA_HEX!(color(item)));
+            c.fill_rect(rect).unwrap();
+        }
+    }
+}
+
+// Outlines the footprint of a pattern stamped at `(row, col)` and fills its live cells, clipped
+// to the board.
+fn draw_preview<T: RenderTarget>(
+    c: &mut Canvas<T>,
+    preview: &Pattern,
+    (row, col): (Rows, Cols),
+    (rows, cols): (Rows, Cols),
+    width: u32,
+    height: u32,
+) {
+    let cell_h = height as i32 / rows as i32;
+    let cell_w = width as i32 / cols as i32;
+    c.set_draw_color(RGBA_HEX!(PREVIEW_COLOR));
+
+    for (ir, states) in preview.cells.iter().enumerate() {
+        for (ic, &state) in states.iter().enumerate() {
+            let (pr, pc) = (row + ir, col + ic);
+            if state != 0 && pr < rows && pc < cols {
+                let (x, y) = (pc as i32 * cell_w, pr as i32 * cell_h);
+                c.fill_rect(Rect::new(x, y, cell_w as u32, cell_h as u32))
+                    .unwrap();
             }
         }
     }
+    let footprint = Rect::new(
+        col as i32 * cell_w,
+        row as i32 * cell_h,
+        (preview.cols().min(cols - col) as i32 * cell_w) as u32,
+        (preview.rows().min(rows - row) as i32 * cell_h) as u32,
+    );
+    c.draw_rect(footprint).unwrap();
+}
 
-    fn count_n(&self, row: Rows, col: Cols) -> usize {
-        let mut n: usize = 0;
+// Plots the population of the last generations across the bottom quarter of the window, two
+// pixels per generation, scaled between the lowest and highest population shown.
+fn draw_history<T: RenderTarget>(
+    c: &mut Canvas<T>,
+    history: &History<LifeSample>,
+    width: u32,
+    height: u32,
+) {
+    let chart_h = (height / 4).max(1) as i32;
+    let top = height as i32 - chart_h;
+    c.set_blend_mode(BlendMode::Blend);
+    c.set_draw_color(RGBA_HEX!(CHART_BACKGROUND));
+    c.fill_rect(Rect::new(0, top, width, chart_h as u32))
+        .unwrap();
+    c.set_blend_mode(BlendMode::None);
 
-        for dr in 0..=2 {
-            for dc in 0..=2 {
-                if dr != 1 || dc != 1 {
-                    let r = emod((row + dr) as i32 - 1, self.board.len() as i32);
-                    let c = emod((col + dc) as i32 - 1, self.board[0].len() as i32);
-                    if self.board[r as usize][c as usize] == Cell::Alive {
-                        n += 1;
-                    }
-                }
+    let recent = history.recent(width as usize / 2);
+    let (low, high) = history::bounds(recent);
+    let points: Vec<Point> = recent
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let t = match high - low {
+                range if range > 0.0 => (sample.population as f64 - low) / range,
+                _ => 0.5,
+            };
+            let y = top + chart_h - 1 - (t * (chart_h - 1) as f64) as i32;
+            Point::new(i as i32 * 2, y)
+        })
+        .collect();
+    c.set_draw_color(RGBA_HEX!(CHART_COLOR));
+    c.draw_lines(points.as_slice()).unwrap();
+}
+
+fn cell_at(
+    x: i32,
+    y: i32,
+    rows: Rows,
+    cols: Cols,
+    width: u32,
+    height: u32,
+) -> Option<(Rows, Cols)> {
+    let cell_h = (height as usize / rows) as i32;
+    let cell_w = (width as usize / cols) as i32;
+    if x < 0 || y < 0 || cell_h == 0 || cell_w == 0 {
+        return None;
+    }
+
+    let (r, c) = ((y / cell_h) as usize, (x / cell_w) as usize);
+    (r < rows && c < cols).then_some((r, c))
+}
+
+fn sand_frame(sandbox: &SandBoard, cell_size: usize) -> Frame {
+    Frame::from_grid(sandbox.cells(), cell_size, |m| {
+        render::rgb(m.as_color_hex())
+    })
+}
+
+fn record_sandbox(
+    sandbox: &SandBoard,
+    path: &str,
+    cell_size: usize,
+    options: CaptureOptions,
+) -> Result<GifRecorder, String> {
+    let frame = sand_frame(sandbox, cell_size);
+    let colours: Vec<[u8; 3]> = Material::ALL
+        .iter()
+        .map(|m| render::rgb(m.as_color_hex()))
+        .collect();
+    let mut recorder = GifRecorder::create(path, frame.width, frame.height, &colours, options)?;
+    recorder.offer(&frame)?;
+    Ok(recorder)
+}
+
+fn material_for_key(key: Keycode) -> Option<Material> {
+    match key {
+        Keycode::Num0 | Keycode::Backspace => Some(Material::Empty),
+        Keycode::Num1 => Some(Material::Sand),
+        Keycode::Num2 => Some(Material::Water),
+        Keycode::Num3 => Some(Material::Wall),
+        Keycode::Num4 => Some(Material::Fire),
+        Keycode::Num5 => Some(Material::Smoke),
+        Keycode::Num6 => Some(Material::Plant),
+        _ => None,
+    }
+}
+
+// Runs the board for `options.frames` frames, `options.skip` generations apart, into the
+// captures and a PNG sequence drawn the way the window draws it. Returns what was written.
+fn run_headless(
+    board: &mut Board,
+    captures: &mut Captures,
+    out_path: Option<&String>,
+    cell_size: usize,
+    palette: Option<&Palette>,
+    options: CaptureOptions,
+) -> Result<Vec<String>, String> {
+    let mut sequence = out_path.map(|dir| FrameSequence::create(dir)).transpose()?;
+    let skip = options.skip.max(1);
+    let last = options.frames.unwrap_or(1).saturating_sub(1) * skip;
+
+    let mut reports = Vec::new();
+    for generation in 0..=last {
+        if generation > 0 {
+            board.next_gen();
+            reports.extend(captures.offer(|| board.to_frame(cell_size, palette)));
+        }
+        if let Some(sequence) = &mut sequence {
+            if generation.is_multiple_of(skip) {
+                sequence.write(&draw_offscreen(board, cell_size)?)?;
             }
         }
+    }
+    // A single frame leaves the captures open.
+    reports.extend(captures.stop());
 
-        n
+    let mut reports = reports.into_iter().collect::<Result<Vec<_>, _>>()?;
+    if let Some(sequence) = &sequence {
+        reports.push(format!(
+            "Wrote {} frames to {}",
+            sequence.written(),
+            sequence.describe()
+        ));
     }
+    Ok(reports)
+}
+
+// Draws the board on a software canvas, `cell_size` pixels per cell, with the window's code.
+fn draw_offscreen(board: &Board, cell_size: usize) -> Result<Frame, String> {
+    let (rows, cols) = (board.board.len(), board.board[0].len());
+    let (width, height) = ((cols * cell_size) as u32, (rows * cell_size) as u32);
+    let surface = Surface::new(width, height, PixelFormatEnum::RGB24).map_err(sdl_error)?;
+    let mut canvas = surface.into_canvas().map_err(sdl_error)?;
+
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+    board.draw(&mut canvas, width, height);
+    canvas.present();
+
+    let pixels = canvas
+        .read_pixels(None, PixelFormatEnum::RGB24)
+        .map_err(sdl_error)?;
+    Ok(Frame {
+        width: width as usize,
+        height: height as usize,
+        pixels,
+    })
 }
 
-fn emod(a: i32, b: i32) -> i32 {
-    (a % b + b) % b
+fn print_reports(reports: impl IntoIterator<Item = Result<String, String>>) {
+    for report in reports {
+        match report {
+            Ok(report) => println!("{report}"),
+            Err(err) => eprintln!("{err}"),
+        }
+    }
 }
 
 fn sdl_error(err: String) -> String {
@@ -158,7 +468,7 @@ fn sdl_error(err: String) -> String {
 }
 
 fn sdl_create_window(sdl_ctx: &sdl2::Sdl) -> Result<Window, String> {
-    let video_subsys = sdl_ctx.video().map_err(|err| sdl_error(err))?;
+    let video_subsys = sdl_ctx.video().map_err(sdl_error)?;
 
     video_subsys
         .window("GoLrs", WINDOW_WIDHT, WINDOW_HEIGHT)
@@ -184,8 +494,262 @@ fn sdl_create_canvas(window: Window) -> Result<Canvas<Window>, String> {
     Ok(canvas)
 }
 
+fn set_sand_title(
+    canvas: &mut Canvas<Window>,
+    seed: u64,
+    material: Material,
+) -> Result<(), String> {
+    canvas
+        .window_mut()
+        .set_title(&format!("GoLrs - Sand (seed {seed}) - {}", material.name()))
+        .map_err(|err| sdl_error(err.to_string()))
+}
+
+fn set_board_title(
+    canvas: &mut Canvas<Window>,
+    seed: u64,
+    pick: Option<&Pick>,
+) -> Result<(), String> {
+    let title = match pick {
+        Some(pick) => format!("GoLrs (seed {seed}) - {}", pick.describe()),
+        None => format!("GoLrs (seed {seed})"),
+    };
+    canvas
+        .window_mut()
+        .set_title(&title)
+        .map_err(|err| sdl_error(err.to_string()))
+}
+
+fn create_soup(matches: &ArgMatches) -> Soup {
+    Soup {
+        density: *matches.get_one::<f64>("density").unwrap_or(&0.5),
+        symmetry: *matches
+            .get_one::<Symmetry>("symmetry")
+            .unwrap_or(&Symmetry::C1),
+        noise_scale: matches.get_one::<f32>("noise").copied(),
+    }
+}
+
+fn create_capture_options(matches: &ArgMatches) -> CaptureOptions {
+    let mut options = CaptureOptions {
+        delay_ms: *matches
+            .get_one::<u32>("frame-delay")
+            .unwrap_or(&DEFAULT_FRAME_DELAY),
+        skip: *matches.get_one::<u32>("frame-skip").unwrap_or(&1) as usize,
+        frames: matches.get_one::<u32>("frames").map(|&n| n as usize),
+    };
+    if let Some(&seconds) = matches.get_one::<f64>("duration") {
+        options.frames = Some(options.frames_in(seconds));
+    }
+    options
+}
+
+fn create_engine(
+    matches: &ArgMatches,
+    seed: u64,
+    pattern: Option<(&Pattern, &Path)>,
+) -> Result<Engine, String> {
+    let automaton = match (
+        matches.get_one::<String>("rule-script"),
+        matches.get_one::<String>("rule-file"),
+        matches.get_one::<Rule>("rule"),
+        pattern.and_then(|(p, path)| Some((p.rule.as_ref()?, path))),
+    ) {
+        (Some(path), _, _, _) => ScriptRule::load(path)?.into(),
+        (None, Some(path), _, _) => GollyRule::load(path)?.into(),
+        (None, None, Some(rule), _) => rule.clone().into(),
+        (None, None, None, Some((rule, path))) => {
+            if let (_, Some(grid)) = split_bounded_grid(rule) {
+                eprintln!(
+                    "Ignoring the bounded grid {grid} of rule {rule}: the board wraps around"
+                );
+            }
+            Automaton::resolve(rule, path.parent().unwrap_or(Path::new(".")))?
+        }
+        (None, None, None, None) => Rule::default().into(),
+    };
+    let p_birth = *matches.get_one::<f64>("p-birth").unwrap_or(&1.0);
+    let p_death = *matches.get_one::<f64>("p-death").unwrap_or(&1.0);
+    let scheme = matches
+        .get_one::<UpdateScheme>("update")
+        .unwrap_or(&UpdateScheme::Synchronous);
+
+    let automaton = match automaton {
+        Automaton::LifeLike(rule) => rule.with_probabilities(p_birth, p_death)?.into(),
+        other if p_birth == 1.0 && p_death == 1.0 => other,
+        _ => {
+            return Err(rule_error(
+                "--p-birth and --p-death only apply to Life-like rules",
+            ))
+        }
+    };
+    Ok(Engine::new(automaton, *scheme, seed))
+}
+
 fn main() -> Result<(), String> {
-    let sdl_context = sdl2::init().map_err(|err| sdl_error(err))?;
+    let matches = get_args();
+    let seed = rng::resolve_seed(matches.get_one::<u64>("seed").copied());
+    let load_path = matches.get_one::<String>("load");
+    let save_path = matches
+        .get_one::<String>("save")
+        .map_or(DEFAULT_SAVE_FILE, String::as_str);
+    let loaded = load_path.map(pattern::load).transpose()?;
+    let import_path = matches.get_one::<String>("import");
+    let threshold = *matches
+        .get_one::<f32>("threshold")
+        .unwrap_or(&DEFAULT_THRESHOLD);
+    let png_path = matches
+        .get_one::<String>("png")
+        .map_or(DEFAULT_PNG_FILE, String::as_str);
+    let cell_size = *matches
+        .get_one::<u32>("cell-size")
+        .unwrap_or(&DEFAULT_CELL_SIZE) as usize;
+    let palette = matches.get_one::<Palette>("palette");
+    let record_path = matches.get_one::<String>("record");
+    let stream_path = matches.get_one::<String>("stream");
+    let stream_format = *matches
+        .get_one::<StreamFormat>("stream-format")
+        .unwrap_or(&StreamFormat::Y4m);
+    let capture_options = create_capture_options(&matches);
+    let census_path = matches.get_one::<String>("census");
+    let history_path = matches.get_one::<String>("history");
+    let on_cycle = *matches
+        .get_one::<CycleAction>("on-cycle")
+        .unwrap_or(&CycleAction::Report);
+
+    let rng = rng::from_seed_stream(seed, rng::SOUP_STREAM);
+    let engine = create_engine(
+        &matches,
+        seed,
+        loaded.as_ref().zip(load_path.map(Path::new)),
+    )?;
+    let mut board = Board::new(ROWS, COLS, engine, rng);
+    board.set_soup(
+        create_soup(&matches),
+        matches.get_one::<(Rows, Cols)>("soup-size").copied(),
+    );
+    if let Some(pattern) = &loaded {
+        let (r, c) = pattern.centred_in(ROWS, COLS);
+        board.stamp(pattern, r, c)?;
+    }
+    if let Some(path) = import_path {
+        let pattern = render::import_pattern(path, ROWS, COLS, threshold)?;
+        let (r, c) = pattern.centred_in(ROWS, COLS);
+        board.stamp(&pattern, r, c)?;
+    }
+    let mut sandbox = matches
+        .get_flag("sand")
+        .then(|| SandBoard::new(ROWS, COLS, seed));
+
+    let headless = matches.get_flag("headless");
+    let out_path = matches.get_one::<String>("out");
+    if stream_path.is_some_and(|target| target == "-") {
+        if !headless {
+            return Err(image_error(
+                "only --headless runs can stream to stdout, where messages go otherwise",
+            ));
+        }
+        if out_path.is_some_and(|dir| dir == "-") {
+            return Err(image_error(
+                "--stream and --out cannot both write to stdout",
+            ));
+        }
+    }
+    if census_path.is_some_and(|target| target == "-") {
+        if !headless {
+            return Err(census_error(
+                "only --headless runs can write the census to stdout, where messages go otherwise",
+            ));
+        }
+        if stream_path.is_some_and(|target| target == "-") || out_path.is_some_and(|dir| dir == "-")
+        {
+            return Err(census_error(
+                "the census and the frames cannot both go to stdout",
+            ));
+        }
+    }
+    if headless
+        && (record_path.is_some()
+            || stream_path.is_some()
+            || out_path.is_some()
+            || history_path.is_some())
+        && !matches.contains_id("length")
+    {
+        return Err(image_error(
+            "rendering headless needs --frames or --duration",
+        ));
+    }
+    // With nothing loaded a headless run has nothing to watch, so it starts from a soup.
+    if headless && board.is_empty() {
+        board.randomize();
+    }
+
+    let mut captures = Captures {
+        gif: match (record_path, &sandbox) {
+            (Some(path), Some(sandbox)) => {
+                Some(record_sandbox(sandbox, path, cell_size, capture_options)?)
+            }
+            (Some(path), None) => Some(board.record(path, cell_size, palette, capture_options)?),
+            (None, _) => None,
+        },
+        stream: stream_path
+            .map(|target| FrameStream::open(target, stream_format, capture_options))
+            .transpose()?,
+    };
+    // The recording starts with the current generation, and so does the stream.
+    if let Some(stream) = &mut captures.stream {
+        let frame = match &sandbox {
+            Some(sandbox) => sand_frame(sandbox, cell_size),
+            None => board.to_frame(cell_size, palette),
+        };
+        stream.offer(&frame)?;
+    }
+
+    if headless {
+        // Frames sent to stdout leave no room for messages there.
+        let quiet = captures.is_stdout()
+            || out_path.is_some_and(|dir| dir == "-")
+            || census_path.is_some_and(|target| target == "-");
+        let mut reports = run_headless(
+            &mut board,
+            &mut captures,
+            out_path,
+            cell_size,
+            palette,
+            capture_options,
+        )?;
+        // A census waits for the board to settle, however long the frames ran.
+        if let Some(path) = census_path {
+            if !board.settle(MAX_SETTLE_GENERATIONS) {
+                reports.push(format!(
+                    "Board did not settle within {MAX_SETTLE_GENERATIONS} generations"
+                ));
+            }
+            let census = board.census()?;
+            reports.push(census.table().trim_end().to_string());
+            match path.as_str() {
+                "-" => println!("{}", census.to_json()),
+                path => {
+                    census.save(path)?;
+                    reports.push(format!("Census written to {path}"));
+                }
+            }
+        }
+        if let Some(path) = history_path {
+            board.history.save(path)?;
+            reports.push(format!("History written to {path}"));
+        }
+        for line in reports.iter().chain([&format!("Seed: {seed}")]) {
+            if quiet {
+                eprintln!("{line}");
+            } else {
+                println!("{line}");
+            }
+        }
+        return Ok(());
+    }
+
+    let sdl_context = sdl2::init().map_err(sdl_error)?;
     let window = sdl_create_window(&sdl_context)?;
     let mut canvas = sdl_create_canvas(window)?;
 
@@ -193,11 +757,21 @@ fn main() -> Result<(), String> {
     canvas.clear();
     canvas.present();
 
-    let mut board = Board::new(ROWS, COLS);
+    let mut material = Material::Sand;
     let mut pause = false;
+    let mut pick = Pick::default();
+    let mut picking = false;
+    let mut charting = false;
+    let mut mouse = (0, 0);
     let (mut width, mut height) = canvas.window().size();
     let mut r_timeout = RENDER_TIMEOUT;
 
+    if sandbox.is_some() {
+        set_sand_title(&mut canvas, seed, material)?;
+    } else {
+        set_board_title(&mut canvas, seed, None)?;
+    }
+
     let mut event_pump = sdl_context.event_pump().unwrap();
     'running: loop {
         for event in event_pump.poll_iter() {
@@ -218,31 +792,499 @@ fn main() -> Result<(), String> {
                 Event::KeyDown {
                     keycode: Some(Keycode::R),
                     ..
-                } => board.randomize(),
+                } if sandbox.is_none() => board.randomize(),
                 Event::KeyDown {
                     keycode: Some(Keycode::C),
                     ..
-                } => board.clear(),
+                } => match &mut sandbox {
+                    Some(sandbox) => sandbox.clear(),
+                    None => board.clear(),
+                },
                 Event::KeyDown {
                     keycode: Some(Keycode::Space),
                     ..
                 } => pause = !pause,
+                Event::MouseMotion { x, y, .. } => mouse = (x, y),
+                Event::KeyDown {
+                    keycode: Some(Keycode::H),
+                    ..
+                } if sandbox.is_none() => charting = !charting,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } if sandbox.is_none() => {
+                    picking = !picking;
+                    set_board_title(&mut canvas, seed, picking.then_some(&pick))?;
+                }
+                Event::KeyDown {
+                    keycode:
+                        Some(
+                            key @ (Keycode::LeftBracket
+                            | Keycode::RightBracket
+                            | Keycode::T
+                            | Keycode::F),
+                        ),
+                    ..
+                } if picking => {
+                    match key {
+                        Keycode::RightBracket => pick.next(),
+                        Keycode::LeftBracket => pick.prev(),
+                        Keycode::T => pick.rotate(),
+                        _ => pick.flip(),
+                    }
+                    set_board_title(&mut canvas, seed, Some(&pick))?;
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if picking => {
+                    if let Some((r, c)) = cell_at(x, y, ROWS, COLS, width, height) {
+                        if let Err(err) = board.stamp(&pick.pattern(), r, c) {
+                            eprintln!("{err}");
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::L),
+                    ..
+                } if pause && sandbox.is_none() => {
+                    let Some(path) = load_path else {
+                        eprintln!("No pattern file given with --load");
+                        continue;
+                    };
+                    let (x, y) = mouse;
+                    let result = pattern::load(path).and_then(|p| {
+                        let (r, c) = cell_at(x, y, ROWS, COLS, width, height)
+                            .unwrap_or_else(|| p.centred_in(ROWS, COLS));
+                        board.stamp(&p, r, c)
+                    });
+                    if let Err(err) = result {
+                        eprintln!("{err}");
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    ..
+                } if pause && sandbox.is_none() => {
+                    match pattern::save(save_path, &board.to_pattern()) {
+                        Ok(()) => println!("Saved {save_path}"),
+                        Err(err) => eprintln!("{err}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::I),
+                    ..
+                } if pause && sandbox.is_none() => {
+                    let Some(path) = import_path else {
+                        eprintln!("No image given with --import");
+                        continue;
+                    };
+                    let (x, y) = mouse;
+                    let (r, c) = cell_at(x, y, ROWS, COLS, width, height).unwrap_or((0, 0));
+                    match board.import_at(path, r, c, threshold) {
+                        Ok(()) => println!("Imported {path}"),
+                        Err(err) => eprintln!("{err}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    ..
+                } => {
+                    let frame = match &sandbox {
+                        Some(sandbox) => sand_frame(sandbox, cell_size),
+                        None => board.to_frame(cell_size, palette),
+                    };
+                    match frame.save_png(png_path) {
+                        Ok(()) => println!("Exported {png_path}"),
+                        Err(err) => eprintln!("{err}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::K),
+                    ..
+                } if sandbox.is_none() => {
+                    let path = census_path.map_or(DEFAULT_CENSUS_FILE, String::as_str);
+                    match board.census() {
+                        Ok(census) => {
+                            print!("{}", census.table());
+                            match census.save(path) {
+                                Ok(()) => println!("Census written to {path}"),
+                                Err(err) => eprintln!("{err}"),
+                            }
+                        }
+                        Err(err) => eprintln!("{err}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::G),
+                    ..
+                } => {
+                    if let Some(report) = render::stop(&mut captures.gif) {
+                        print_reports([report]);
+                        continue;
+                    }
+                    let path = record_path.map_or(DEFAULT_GIF_FILE, String::as_str);
+                    let started = match &sandbox {
+                        Some(sandbox) => record_sandbox(sandbox, path, cell_size, capture_options),
+                        None => board.record(path, cell_size, palette, capture_options),
+                    };
+                    match started {
+                        Ok(started) => {
+                            captures.gif = Some(started);
+                            println!("Recording to {path}");
+                        }
+                        Err(err) => eprintln!("{err}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } if sandbox.is_some() => {
+                    if let Some(m) = material_for_key(key) {
+                        material = m;
+                        set_sand_title(&mut canvas, seed, material)?;
+                    }
+                }
                 _ => {}
             }
         }
 
-        if !pause {
+        if let Some(sandbox) = &mut sandbox {
+            let mouse = event_pump.mouse_state();
+            let brush = if mouse.is_mouse_button_pressed(MouseButton::Left) {
+                Some(material)
+            } else if mouse.is_mouse_button_pressed(MouseButton::Right) {
+                Some(Material::Empty)
+            } else {
+                None
+            };
+
+            if let Some(brush) = brush {
+                let (rows, cols) = (sandbox.rows(), sandbox.cols());
+                if let Some((r, c)) = cell_at(mouse.x(), mouse.y(), rows, cols, width, height) {
+                    sandbox.paint(r, c, BRUSH_RADIUS, brush);
+                }
+            }
+
+            if !pause {
+                sandbox.step();
+                print_reports(captures.offer(|| sand_frame(sandbox, cell_size)));
+            }
+        } else if !pause {
             r_timeout -= DELTA_TIME;
             if r_timeout <= 0.0 {
                 r_timeout = RENDER_TIMEOUT;
-                board.next_gen();
+                if let Some(outcome) = board.next_gen() {
+                    match on_cycle {
+                        CycleAction::Report => println!("{outcome}"),
+                        CycleAction::Pause => {
+                            println!("{outcome}, paused");
+                            pause = true;
+                        }
+                        CycleAction::Reseed => {
+                            board.randomize();
+                            println!("{outcome}, reseeded");
+                        }
+                    }
+                }
+                print_reports(captures.offer(|| board.to_frame(cell_size, palette)));
             }
         }
 
         canvas.clear();
-        board.draw(&mut canvas, width, height);
+        match &sandbox {
+            Some(sandbox) => draw_grid(
+                &mut canvas,
+                sandbox.cells(),
+                width,
+                height,
+                Material::as_color_hex,
+            ),
+            None => board.draw(&mut canvas, width, height),
+        }
+        if charting && sandbox.is_none() {
+            draw_history(&mut canvas, &board.history, width, height);
+        }
+        if picking {
+            let (x, y) = mouse;
+            if let Some(at) = cell_at(x, y, ROWS, COLS, width, height) {
+                draw_preview(
+                    &mut canvas,
+                    &pick.pattern(),
+                    at,
+                    (ROWS, COLS),
+                    width,
+                    height,
+                );
+            }
+        }
         canvas.present();
     }
 
+    print_reports(captures.stop());
+    if let Some(err) = board.engine.automaton().error() {
+        eprintln!("{err}");
+    }
+    if let Some(path) = history_path.filter(|_| sandbox.is_none()) {
+        board.history.save(path)?;
+        println!("History written to {path}");
+    }
     Ok(())
 }
+
+fn get_args() -> ArgMatches {
+    Command::new(APP_NAME)
+        .display_name(BIN_NAME.unwrap_or("Unknown"))
+        .author(AUTHORS.unwrap_or("Unknown"))
+        .about(DESCRIPTION.unwrap_or("Unknown"))
+        .version(VERSION.unwrap_or("Unknown"))
+        .help_template(HELP_TEMPLATE)
+        .arg(
+            Arg::new("sand")
+                .long("sand")
+                .action(ArgAction::SetTrue)
+                .help("Run the falling-sand sandbox instead of Game of Life"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Seed for the random number generator")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("rule")
+                .long("rule")
+                .value_name("rule")
+                .action(ArgAction::Set)
+                .help("Life-like rule in B/S notation [default: B3/S23]")
+                .value_parser(Rule::parse),
+        )
+        .arg(
+            Arg::new("rule-file")
+                .long("rule-file")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .conflicts_with("rule")
+                .help("Golly .rule file (@TABLE or @TREE) to run instead of a Life-like rule"),
+        )
+        .arg(
+            Arg::new("rule-script")
+                .long("rule-script")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .conflicts_with_all(["rule", "rule-file"])
+                .help("Rhai script defining fn next(state, neighbours) to run as the rule"),
+        )
+        .arg(
+            Arg::new("p-birth")
+                .long("p-birth")
+                .value_name("p")
+                .action(ArgAction::Set)
+                .help("Probability that a birth happens [default: 1.0]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("p-death")
+                .long("p-death")
+                .value_name("p")
+                .action(ArgAction::Set)
+                .help("Probability that a death happens [default: 1.0]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("update")
+                .long("update")
+                .value_name("scheme")
+                .action(ArgAction::Set)
+                .help("Update scheme: sync, sequential, alpha:<p> or checkerboard [default: sync]")
+                .value_parser(UpdateScheme::parse),
+        )
+        .arg(
+            Arg::new("density")
+                .long("density")
+                .value_name("p")
+                .action(ArgAction::Set)
+                .help("Density of random soups [default: 0.5]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("symmetry")
+                .long("symmetry")
+                .value_name("sym")
+                .action(ArgAction::Set)
+                .help("Symmetry of random soups: C1, D2V, D2H, C2, C4, D4 or D8 [default: C1]")
+                .value_parser(Symmetry::parse),
+        )
+        .arg(
+            Arg::new("noise")
+                .long("noise")
+                .value_name("scale")
+                .action(ArgAction::Set)
+                .help("Threshold value noise of the given scale instead of white noise")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("soup-size")
+                .long("soup-size")
+                .value_name("rowsxcols")
+                .action(ArgAction::Set)
+                .help("Confine random soups to a centred rectangle, e.g. 16x16")
+                .value_parser(soup::parse_size),
+        )
+        .arg(
+            Arg::new("on-cycle")
+                .long("on-cycle")
+                .value_name("action")
+                .action(ArgAction::Set)
+                .help("What to do once the board dies out, stops or repeats: report, pause or reseed [default: report]")
+                .value_parser(CycleAction::parse),
+        )
+        .arg(
+            Arg::new("census")
+                .long("census")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the census is written to as JSON, or - for stdout with --headless [default: census.json]"),
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("CSV file the population, births and deaths of every generation are written to on exit"),
+        )
+        .arg(
+            Arg::new("load")
+                .short('l')
+                .long("load")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Load a pattern file (RLE, .cells, Life 1.05/1.06, .mc) into the board"),
+        )
+        .arg(
+            Arg::new("save")
+                .long("save")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the board is saved to [default: board.rle]"),
+        )
+        .arg(
+            Arg::new("import")
+                .long("import")
+                .value_name("image")
+                .action(ArgAction::Set)
+                .help("Threshold a PNG or PPM image into the board"),
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .value_name("t")
+                .action(ArgAction::Set)
+                .help("Luminance above which imported pixels become live cells [default: 0.5]")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("png")
+                .long("png")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the board is exported to as PNG [default: board.png]"),
+        )
+        .arg(
+            Arg::new("cell-size")
+                .long("cell-size")
+                .value_name("px")
+                .action(ArgAction::Set)
+                .help("Pixels per cell in exported images [default: 8]")
+                .value_parser(value_parser!(u32).range(1..=64)),
+        )
+        .arg(
+            Arg::new("palette")
+                .long("palette")
+                .value_name("colours")
+                .action(ArgAction::Set)
+                .help("Palette of exported images: gray, inverted, heat, ocean or hex colours such as 000000,ffffff [default: rule colours]")
+                .value_parser(Palette::parse),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Record the run into an animated GIF"),
+        )
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Stop recording after this many frames")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .value_name("seconds")
+                .action(ArgAction::Set)
+                .help("Stop recording once the GIF lasts this long")
+                .value_parser(render::parse_duration),
+        )
+        .group(ArgGroup::new("length").args(["frames", "duration"]))
+        .arg(
+            Arg::new("frame-delay")
+                .long("frame-delay")
+                .value_name("ms")
+                .action(ArgAction::Set)
+                .help("Delay between GIF frames, in milliseconds [default: 100]")
+                .value_parser(value_parser!(u32).range(10..=655350)),
+        )
+        .arg(
+            Arg::new("frame-skip")
+                .long("frame-skip")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Record only every num-th generation or sandbox step [default: 1]")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("out")
+                .long("out")
+                .value_name("dir")
+                .action(ArgAction::Set)
+                .requires("headless")
+                .help("Directory headless frames are written to as numbered PNGs, or - for stdout"),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Stream every generation or sandbox step as raw video into a file, a named pipe or - for stdout"),
+        )
+        .arg(
+            Arg::new("stream-format")
+                .long("stream-format")
+                .value_name("format")
+                .action(ArgAction::Set)
+                .requires("stream")
+                .help("Raw video format of --stream: y4m or ppm [default: y4m]")
+                .value_parser(StreamFormat::parse),
+        )
+        .group(
+            ArgGroup::new("output")
+                .args(["record", "stream", "out", "census", "history"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .action(ArgAction::SetTrue)
+                .requires("output")
+                .conflicts_with("sand")
+                .help("Render, log history or take a census without opening a window, then exit"),
+        )
+        .get_matches()
+}
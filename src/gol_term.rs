@@ -1,9 +1,35 @@
-use std::io::{stdin, stdout, Write};
+use std::io::{stderr, stdin, stdout, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use cellular_automata::census::{census_error, Census, Class};
+use cellular_automata::cycle::{CycleAction, CycleDetector, Outcome};
+use cellular_automata::evolution::{
+    self, Checkpoint, Evolution, GenomeSpace, LifeLike, Objective, Traits,
+};
+use cellular_automata::golly::GollyRule;
+use cellular_automata::history::{self, History, LifeSample};
+use cellular_automata::life::{
+    emod, rule_error, split_bounded_grid, Automaton, Cell, Engine, Rule, UpdateScheme,
+};
+use cellular_automata::pattern::library::Pick;
+use cellular_automata::pattern::{self, Pattern};
+use cellular_automata::periodic;
+use cellular_automata::predecessor::{self, predecessor_error, Found, Options};
+use cellular_automata::render::gif::GifRecorder;
+use cellular_automata::render::stream::{FrameStream, StreamFormat};
+use cellular_automata::render::{
+    self, image_error, Capture, CaptureOptions, Captures, Frame, Palette,
+};
+use cellular_automata::rng::{self, Rng};
+use cellular_automata::script::ScriptRule;
+use cellular_automata::search::{self, Format, SearchReport, Settings};
+use cellular_automata::session::{self, RuleSource, Session};
+use cellular_automata::soup::{self, Soup, Symmetry};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
 
 use termion::event::Key;
 use termion::input::TermRead;
@@ -28,8 +54,65 @@ const MIN_ROWS: u16 = 10;
 const MIN_COLS: u16 = 10;
 const DEFAULT_ROWS: u16 = 20;
 const DEFAULT_COLS: u16 = 20;
+const DEFAULT_SAVE_FILE: &str = "board.rle";
+const DEFAULT_SESSION_FILE: &str = "gol_term.session";
+const DEFAULT_PNG_FILE: &str = "board.png";
+const DEFAULT_CELL_SIZE: u32 = 8;
+const DEFAULT_THRESHOLD: f32 = 0.5;
+const DEFAULT_GIF_FILE: &str = "board.gif";
+const DEFAULT_FRAME_DELAY: u32 = 100;
+const DEFAULT_CENSUS_FILE: &str = "census.json";
+const DEFAULT_HISTORY_FILE: &str = "history.csv";
+const MAX_SETTLE_GENERATIONS: u64 = 100_000;
+const SEARCH_SOUP_SIZE: (Rows, Cols) = (16, 16);
+const SEARCH_SOUPS: usize = 1000;
+const SEARCH_MAX_GENERATIONS: u64 = 20_000;
+const SEARCH_METHUSELAH: u64 = 1000;
+const PERIODIC_SIZE: (Rows, Cols) = (8, 8);
+const EVOLVE_GENERATIONS: u64 = 20;
+const EVOLVE_POPULATION: usize = 20;
+const EVOLVE_ELITE: usize = 2;
+const EVOLVE_TOURNAMENT: usize = 3;
+const EVOLVE_MUTATION: f64 = 0.05;
+const EVOLVE_TRIALS: usize = 4;
+const EVOLVE_MAX_GENERATIONS: u64 = 1000;
+const EVOLVE_FITNESS: &str = "diversity";
+// Kinds of objects at which the diversity of a soup reaches a half.
+const DIVERSITY_HALF: f64 = 5.0;
+// Edit mode waits for the solver, so it gives up sooner than the subcommand.
+const EDIT_MAX_CONFLICTS: u64 = 100_000;
 
 const HIGHLIGHT_PAIR: (&dyn color::Color, &dyn color::Color) = (&color::Black, &color::White);
+const SELECTION_COLOR: &dyn color::Color = &color::Yellow;
+const PREVIEW_COLOR: &dyn color::Color = &color::Cyan;
+const PICK_KEYS: &str = "[/] pattern, t turn, f flip, SPACE stamp, p close";
+const HELP_COLOR_PAIR: (&dyn color::Color, &dyn color::Color) = (&color::White, &color::Blue);
+
+const HELP: [(&str, &str); 23] = [
+    ("ENTER", "Toggle Edit/Run mode"),
+    ("?", "Show/hide this help"),
+    ("P", "Export board to --png file"),
+    ("G", "Start/stop recording to --record GIF"),
+    ("K", "Take a census, show it and save it to --census file"),
+    ("H", "Save population history to --history file"),
+    ("CTRL+s", "Save session to --session file"),
+    ("CTRL+o", "Restore session from --session file"),
+    ("q, CTRL+c", "Quit"),
+    ("", "Edit mode"),
+    ("w/a/s/d, arrows", "Move cursor"),
+    ("SPACE", "Toggle cell"),
+    ("c", "Clear board"),
+    ("r", "Randomize board or selection"),
+    ("v", "Start/stop selection"),
+    ("b", "Find a predecessor of the selection"),
+    ("L", "Load --load pattern at cursor"),
+    ("S", "Save board to --save file"),
+    ("I", "Import --import image at cursor"),
+    ("p", "Open/close the pattern picker"),
+    ("", "Pattern picker"),
+    ("[, ]", "Previous/next pattern"),
+    ("t, f, SPACE", "Turn, mirror, stamp at cursor"),
+];
 
 type Rows = usize;
 type Cols = usize;
@@ -40,72 +123,258 @@ enum RenderMode {
     Unicode,
 }
 
-#[derive(Clone, PartialEq)]
-enum Cell {
-    Dead,
-    Alive,
+impl RenderMode {
+    fn name(&self) -> &str {
+        match self {
+            RenderMode::Ascii => "ascii",
+            RenderMode::Unicode => "unicode",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ascii" => Some(RenderMode::Ascii),
+            "unicode" => Some(RenderMode::Unicode),
+            _ => None,
+        }
+    }
+}
+
+trait Glyph {
+    fn as_str(&self, mode: RenderMode) -> &str;
 }
 
-impl Cell {
+impl Glyph for Cell {
     fn as_str(&self, mode: RenderMode) -> &str {
         match mode {
-            RenderMode::Ascii => match self {
-                Cell::Alive => "@",
-                Cell::Dead => "-",
+            RenderMode::Ascii => match self.is_alive() {
+                true => "@",
+                false => "-",
             },
-            RenderMode::Unicode => match self {
-                Cell::Alive => "▢",
-                Cell::Dead => "■",
+            RenderMode::Unicode => match self.is_alive() {
+                true => "▢",
+                false => "■",
             },
         }
     }
-
-    fn toggle(&mut self) {
-        match self {
-            Cell::Alive => *self = Cell::Dead,
-            Cell::Dead => *self = Cell::Alive,
-        }
-    }
 }
 
 pub struct Board {
     board: Vec<Vec<Cell>>,
     render_mode: RenderMode,
     cursor: (Cols, Rows),
+    selection: Option<(Cols, Rows)>,
+    pick: Pick,
+    picking: bool,
+    engine: Engine,
+    soup: Soup,
+    soup_size: Option<(Rows, Cols)>,
+    rng: Rng,
+    message: Option<String>,
+    show_help: bool,
+    census: Option<String>,
+    seed: u64,
+    generation: u64,
+    births: usize,
+    deaths: usize,
+    last_step: Option<Instant>,
+    speed: Option<f64>,
+    cycles: CycleDetector,
+    history: History<LifeSample>,
 }
 
 impl Board {
-    pub fn new(rows: Rows, cols: Cols) -> Self {
+    pub fn new(rows: Rows, cols: Cols, engine: Engine, seed: u64) -> Self {
         let rows = rows.clamp(MIN_ROWS as usize, MAX_ROWS as usize);
         let cols = cols.clamp(MIN_COLS as usize, MAX_COLS as usize);
 
         Self {
-            board: vec![vec![Cell::Dead; cols]; rows],
+            board: vec![vec![Cell::DEAD; cols]; rows],
             render_mode: RenderMode::Ascii,
             cursor: (0, 0),
+            selection: None,
+            pick: Pick::default(),
+            picking: false,
+            engine,
+            soup: Soup::default(),
+            soup_size: None,
+            rng: rng::from_seed_stream(seed, rng::SOUP_STREAM),
+            message: None,
+            show_help: false,
+            census: None,
+            seed,
+            generation: 0,
+            births: 0,
+            deaths: 0,
+            last_step: None,
+            speed: None,
+            cycles: CycleDetector::default(),
+            history: History::default(),
         }
     }
 
-    pub fn next_gen(&mut self) {
-        let mut new_board = self.board.clone();
+    /// Rebuilds a board, and the mode it was in, from a saved session.
+    fn restore(session: &Session) -> Result<(Self, Mode), String> {
+        let (rows, cols) = (session.cells.len(), session.cells[0].len());
+        if !(MIN_ROWS as usize..=MAX_ROWS as usize).contains(&rows)
+            || !(MIN_COLS as usize..=MAX_COLS as usize).contains(&cols)
+        {
+            return Err(session::session_error(&format!(
+                "board of {rows}x{cols} is outside the supported size"
+            )));
+        }
+        let mode = Mode::parse(&session.mode)
+            .ok_or_else(|| session::session_error(&format!("unknown mode '{}'", session.mode)))?;
+        let render_mode = RenderMode::parse(&session.render_mode).ok_or_else(|| {
+            session::session_error(&format!("unknown render mode '{}'", session.render_mode))
+        })?;
 
-        for (ir, row) in new_board.iter_mut().enumerate() {
-            for (ic, item) in row.iter_mut().enumerate() {
-                let n = self.count_n(ir, ic);
-                match self.board[ir][ic] {
-                    Cell::Dead => *item = if n == 3 { Cell::Alive } else { Cell::Dead },
-                    Cell::Alive => {
-                        *item = if (2..=3).contains(&n) {
-                            Cell::Alive
-                        } else {
-                            Cell::Dead
-                        }
-                    }
-                }
+        let board = Self {
+            board: session.cells.clone(),
+            render_mode,
+            cursor: session.cursor,
+            selection: session.selection,
+            pick: Pick::default(),
+            picking: false,
+            engine: session.engine()?,
+            soup: Soup::default(),
+            soup_size: None,
+            rng: session.soup_rng(),
+            message: None,
+            show_help: false,
+            census: None,
+            seed: session.seed,
+            generation: session.generation,
+            births: 0,
+            deaths: 0,
+            last_step: None,
+            speed: None,
+            cycles: CycleDetector::default(),
+            history: History::default(),
+        };
+        Ok((board, mode))
+    }
+
+    fn to_session(&self, mode: &Mode) -> Result<Session, String> {
+        let automaton = self.engine.automaton();
+        let (p_birth, p_death) = match automaton {
+            Automaton::LifeLike(rule) => (rule.p_birth(), rule.p_death()),
+            _ => (1.0, 1.0),
+        };
+
+        Ok(Session {
+            cells: self.board.clone(),
+            rule: RuleSource::of(automaton)?,
+            p_birth,
+            p_death,
+            scheme: self.engine.scheme(),
+            seed: self.seed,
+            soup_rng: self.rng.get_word_pos(),
+            engine_rng: self.engine.rng_position(),
+            generation: self.generation,
+            cursor: self.cursor,
+            selection: self.selection,
+            mode: mode.name().to_string(),
+            render_mode: self.render_mode.name().to_string(),
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_soup(&mut self, soup: Soup, size: Option<(Rows, Cols)>) {
+        self.soup = soup;
+        self.soup_size = size;
+    }
+
+    /// Steps the board; returns how it settled, the generation that is first found.
+    pub fn next_gen(&mut self) -> Option<Outcome> {
+        // The board a run starts from may already be part of the cycle, and of the history.
+        if self.cycles.is_empty() {
+            self.cycles
+                .observe(&mut self.engine, self.generation, &self.board);
+        }
+        if self.history.is_empty() {
+            self.history.push(self.sample());
+        }
+        let previous = self.board.clone();
+        self.engine.step(&mut self.board);
+        self.generation += 1;
+
+        (self.births, self.deaths) = (0, 0);
+        for (before, after) in previous.iter().flatten().zip(self.board.iter().flatten()) {
+            match (before.is_alive(), after.is_alive()) {
+                (false, true) => self.births += 1,
+                (true, false) => self.deaths += 1,
+                _ => {}
             }
         }
+        self.history.push(self.sample());
+
+        // Generations per second, smoothed over the last few steps.
+        let now = Instant::now();
+        if let Some(last) = self.last_step {
+            let rate = 1.0 / now.duration_since(last).as_secs_f64().max(1e-6);
+            self.speed = Some(self.speed.map_or(rate, |speed| 0.8 * speed + 0.2 * rate));
+        }
+        self.last_step = Some(now);
+        if let Some(err) = self.engine.automaton().error() {
+            self.message = Some(err.to_string());
+        }
+        self.cycles
+            .observe(&mut self.engine, self.generation, &self.board)
+    }
 
-        self.board = new_board;
+    /// Stops timing generations, so a pause does not count against the run speed.
+    pub fn pause(&mut self) {
+        self.last_step = None;
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    /// Classifies the objects on the board, which only Life-like rules allow.
+    pub fn census(&self) -> Result<Census, String> {
+        match self.engine.automaton() {
+            Automaton::LifeLike(rule) => Census::take(rule, &self.board, self.generation),
+            _ => Err(census_error("a census needs a Life-like rule")),
+        }
+    }
+
+    /// Takes a census, shows its table and saves it to `path`; hides the table if it is shown.
+    pub fn toggle_census(&mut self, path: &str) {
+        if self.census.take().is_some() {
+            return;
+        }
+        let message = match self.census() {
+            Ok(census) => {
+                self.census = Some(census.table());
+                census
+                    .save(path)
+                    .map_or_else(|err| err, |_| format!("Census written to {path}"))
+            }
+            Err(err) => err,
+        };
+        self.set_message(message);
+    }
+
+    fn sample(&self) -> LifeSample {
+        LifeSample {
+            generation: self.generation,
+            population: self.population(),
+            births: self.births,
+            deaths: self.deaths,
+        }
+    }
+
+    pub fn save_history(&self, path: &str) -> Result<(), String> {
+        self.history.save(path)
+    }
+
+    pub fn population(&self) -> usize {
+        self.board.iter().flatten().filter(|c| c.is_alive()).count()
     }
 
     pub fn to_ascii_mode(&mut self) {
@@ -132,44 +401,325 @@ impl Board {
         self.cursor.1 = (self.cursor.1 + 1).clamp(0, self.board.len() - 1);
     }
 
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    pub fn stamp(&mut self, pattern: &Pattern, row: Rows, col: Cols) -> Result<(), String> {
+        self.cycles.reset();
+        pattern.stamp(&mut self.board, row, col)
+    }
+
+    pub fn stamp_at_cursor(&mut self, pattern: &Pattern) -> Result<(), String> {
+        let (c, r) = self.cursor;
+        self.stamp(pattern, r, c)
+    }
+
+    pub fn is_picking(&self) -> bool {
+        self.picking
+    }
+
+    /// Opens or closes the pattern picker, which previews the picked pattern at the cursor.
+    pub fn toggle_picker(&mut self) {
+        self.picking = !self.picking;
+        self.message = self.picking.then(|| self.describe_pick());
+    }
+
+    /// Changes the picked pattern or its orientation, as `change` does to it.
+    pub fn change_pick(&mut self, change: fn(&mut Pick)) {
+        change(&mut self.pick);
+        self.message = Some(self.describe_pick());
+    }
+
+    pub fn stamp_pick(&mut self) -> Result<(), String> {
+        self.stamp_at_cursor(&self.pick.pattern())
+    }
+
+    fn describe_pick(&self) -> String {
+        format!("{} - {PICK_KEYS}", self.pick.describe())
+    }
+
+    // State the preview puts at a cell, if it covers that cell.
+    fn preview_at(&self, preview: &Pattern, row: Rows, col: Cols) -> Option<Cell> {
+        let (c, r) = self.cursor;
+        let state = preview
+            .cells
+            .get(row.checked_sub(r)?)?
+            .get(col.checked_sub(c)?)?;
+        Some(Cell(*state))
+    }
+
+    /// Thresholds an image into the board, scaled to fit right of and below the cursor.
+    pub fn import_at_cursor(&mut self, path: &str, threshold: f32) -> Result<(), String> {
+        let (c, r) = self.cursor;
+        let (rows, cols) = (self.board.len(), self.board[0].len());
+        let pattern = render::import_pattern(path, rows - r, cols - c, threshold)?;
+        self.stamp(&pattern, r, c)
+    }
+
+    pub fn to_pattern(&self) -> Pattern {
+        Pattern {
+            rule: Some(self.engine.automaton().to_string()),
+            ..Pattern::from_cells(&self.board)
+        }
+    }
+
+    pub fn to_frame(&self, cell_size: usize, palette: Option<&Palette>) -> Frame {
+        Frame::from_cells(&self.board, self.engine.automaton(), cell_size, palette)
+    }
+
+    /// Starts recording the board to a GIF, beginning with the current generation.
+    pub fn record(
+        &self,
+        path: &str,
+        cell_size: usize,
+        palette: Option<&Palette>,
+        options: CaptureOptions,
+    ) -> Result<GifRecorder, String> {
+        let frame = self.to_frame(cell_size, palette);
+        let colours = render::state_colours(self.engine.automaton(), palette);
+        let mut recorder = GifRecorder::create(path, frame.width, frame.height, &colours, options)?;
+        recorder.offer(&frame)?;
+        Ok(recorder)
+    }
+
+    pub fn toggle_selection(&mut self) {
+        self.selection = match self.selection {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+    }
+
+    /// Looks for a predecessor of the selection and writes it over the selection, grown by a cell
+    /// on every side, so that the next generation brings the selection back.
+    pub fn find_predecessor(&mut self) {
+        let message = match self.selection_rect() {
+            Some(((c0, r0), (c1, r1))) => self.predecessor_of((r0, c0), (r1, c1)),
+            None => "Select the cells to find a predecessor of with v first".to_string(),
+        };
+        self.set_message(message);
+    }
+
+    fn predecessor_of(&mut self, (r0, c0): (Rows, Cols), (r1, c1): (Rows, Cols)) -> String {
+        let Automaton::LifeLike(rule) = self.engine.automaton() else {
+            return predecessor_error("predecessors need a Life-like rule");
+        };
+        let options = Options {
+            max_conflicts: Some(EDIT_MAX_CONFLICTS),
+            ..Options::default()
+        };
+        let (rows, cols) = (self.board.len(), self.board[0].len());
+        let margin = options.margin;
+        if r1 - r0 + 1 + 2 * margin > rows || c1 - c0 + 1 + 2 * margin > cols {
+            return predecessor_error(&format!(
+                "the selection needs {margin} free cell around it on the board"
+            ));
+        }
+        let target: Vec<Vec<Cell>> = self.board[r0..=r1]
+            .iter()
+            .map(|row| row[c0..=c1].to_vec())
+            .collect();
+        let search = match predecessor::search(rule, &target, options) {
+            Ok(search) => search,
+            Err(err) => return err,
+        };
+
+        match search.found {
+            Found::Pattern(cells) => {
+                self.cycles.reset();
+                for (r, row) in cells.iter().enumerate() {
+                    for (c, &cell) in row.iter().enumerate() {
+                        let br = emod((r0 + r) as i32 - margin as i32, rows as i32) as usize;
+                        let bc = emod((c0 + c) as i32 - margin as i32, cols as i32) as usize;
+                        self.board[br][bc] = cell;
+                    }
+                }
+                "Predecessor found; the next generation is the selection".to_string()
+            }
+            Found::Impossible => {
+                "The selection is an orphan, so this board is a Garden of Eden".to_string()
+            }
+            Found::GaveUp => format!("Gave up after {} conflicts", search.conflicts),
+        }
+    }
+
     pub fn toggle_cur_cell(&mut self) {
+        self.cycles.reset();
         let (c, r) = self.cursor;
-        self.board[r][c].toggle();
+        self.board[r][c].cycle(self.engine.automaton().n_states());
     }
 
-    pub fn render<W: Write>(&self, s: &mut W) {
+    fn render<W: Write>(&self, s: &mut W, mode: &Mode) {
         write!(s, "{}{}", cursor::Goto(1, 1), clear::AfterCursor).unwrap();
+        let multi_state = self.engine.automaton().n_states() > 2;
+        let preview = self.picking.then(|| self.pick.pattern());
 
         for (ir, row) in self.board.iter().enumerate() {
             write!(s, "{}", cursor::Goto(1, (ir + 1) as u16)).unwrap();
 
             for (ic, item) in row.iter().enumerate() {
                 write!(s, "{}", if ic == 0 { " " } else { "" }).unwrap();
-                write!(s, "{}", item.as_str(self.render_mode)).unwrap();
+                if let Some(cell) = preview.as_ref().and_then(|p| self.preview_at(p, ir, ic)) {
+                    write!(s, "{}", color::Fg(PREVIEW_COLOR)).unwrap();
+                    write!(s, "{}", cell.as_str(self.render_mode)).unwrap();
+                    write!(s, "{}", style::Reset).unwrap();
+                } else if self.is_selected(ir, ic) {
+                    write!(s, "{}", color::Fg(SELECTION_COLOR)).unwrap();
+                    write!(s, "{}", item.as_str(self.render_mode)).unwrap();
+                    write!(s, "{}", style::Reset).unwrap();
+                } else if multi_state && item.is_alive() {
+                    let hex = self.engine.automaton().color_hex(*item);
+                    let rgb = color::Rgb((hex >> 24) as u8, (hex >> 16) as u8, (hex >> 8) as u8);
+                    write!(s, "{}", color::Fg(rgb)).unwrap();
+                    write!(s, "{}", item.as_str(self.render_mode)).unwrap();
+                    write!(s, "{}", style::Reset).unwrap();
+                } else {
+                    write!(s, "{}", item.as_str(self.render_mode)).unwrap();
+                }
                 write!(s, "{}", if ic < row.len() - 1 { " " } else { "" }).unwrap();
             }
-            writeln!(s, "").unwrap();
+            writeln!(s).unwrap();
+        }
+
+        write!(
+            s,
+            "{}{}{}{}",
+            cursor::Goto(1, (self.board.len() + 2) as u16),
+            style::Invert,
+            self.status(mode),
+            style::Reset
+        )
+        .unwrap();
+        write!(
+            s,
+            "{}{}",
+            cursor::Goto(1, (self.board.len() + 3) as u16),
+            self.history_line()
+        )
+        .unwrap();
+        if let Some(message) = &self.message {
+            write!(
+                s,
+                "{}{}",
+                cursor::Goto(1, (self.board.len() + 4) as u16),
+                message
+            )
+            .unwrap();
         }
 
         self.highlight_cursor(s);
+        if let Some(table) = &self.census {
+            let lines: Vec<String> = table.lines().map(|line| format!(" {line}")).collect();
+            render_overlay(s, &lines);
+        }
+        if self.show_help {
+            self.render_help(s);
+        }
+    }
+
+    // Such as `Gen 42 | Pop 37 (+5 -3) | B3/S23 | 20x20 | Run 9.8 gen/s | P2 | ? help`.
+    fn status(&self, mode: &Mode) -> String {
+        let speed = match (mode, self.speed) {
+            (Mode::Run, Some(speed)) => format!(" {speed:.1} gen/s"),
+            _ => String::new(),
+        };
+        let settled = match self.cycles.found() {
+            Some(Outcome::Empty { .. }) => " | Empty".to_string(),
+            Some(Outcome::Static { .. }) => " | Static".to_string(),
+            Some(Outcome::Cycle {
+                period,
+                shift: (0, 0),
+                ..
+            }) => format!(" | P{period}"),
+            Some(Outcome::Cycle { period, .. }) => format!(" | P{period} moving"),
+            None => String::new(),
+        };
+        format!(
+            " Gen {} | Pop {} (+{} -{}) | {} | {}x{} | {}{speed}{settled} | ? help ",
+            self.generation,
+            self.population(),
+            self.births,
+            self.deaths,
+            self.engine.automaton(),
+            self.board[0].len(),
+            self.board.len(),
+            mode.title(),
+        )
+    }
+
+    // The population of the last generations as a sparkline the width of the board, with the
+    // lowest and highest population it spans.
+    fn history_line(&self) -> String {
+        let width = self.board[0].len() * 2 - 1;
+        let spark = self.history.sparkline(width);
+        if spark.is_empty() {
+            return String::new();
+        }
+        let (low, high) = history::bounds(self.history.recent(width));
+        format!("{spark} {low}..{high}")
+    }
+
+    fn render_help<W: Write>(&self, s: &mut W) {
+        let width = HELP.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        let lines: Vec<String> = HELP
+            .iter()
+            .map(|(key, action)| match key {
+                &"" => format!(" {action}:"),
+                key => format!("   {key:<width$}  {action}"),
+            })
+            .collect();
+        render_overlay(s, &lines);
     }
 
     pub fn clear(&mut self) {
-        self.board = Self::new(self.board.len(), self.board[0].len()).board;
+        self.cycles.reset();
+        for row in self.board.iter_mut() {
+            row.fill(Cell::DEAD);
+        }
     }
 
     pub fn randomize(&mut self) {
-        for row in self.board.iter_mut() {
-            for item in row.iter_mut() {
-                *item = if rand::random() {
-                    Cell::Dead
-                } else {
-                    Cell::Alive
-                }
+        self.cycles.reset();
+        let (r0, c0, h, w) = self.soup_area();
+        let soup = self.soup.generate(h, w, &mut self.rng);
+
+        for (r, row) in soup.iter().enumerate() {
+            for (c, &alive) in row.iter().enumerate() {
+                self.board[r0 + r][c0 + c] = if alive { Cell::ALIVE } else { Cell::DEAD };
             }
         }
     }
 
+    // The selection if there is one, else the centred soup rectangle, else the whole board.
+    fn soup_area(&self) -> (Rows, Cols, Rows, Cols) {
+        let (rows, cols) = (self.board.len(), self.board[0].len());
+
+        if let Some(((c0, r0), (c1, r1))) = self.selection_rect() {
+            return (r0, c0, r1 - r0 + 1, c1 - c0 + 1);
+        }
+
+        match self.soup_size {
+            Some((h, w)) => {
+                let (h, w) = (h.min(rows), w.min(cols));
+                ((rows - h) / 2, (cols - w) / 2, h, w)
+            }
+            None => (0, 0, rows, cols),
+        }
+    }
+
+    fn selection_rect(&self) -> Option<((Cols, Rows), (Cols, Rows))> {
+        let (ac, ar) = self.selection?;
+        let (c, r) = self.cursor;
+        Some(((ac.min(c), ar.min(r)), (ac.max(c), ar.max(r))))
+    }
+
+    fn is_selected(&self, row: Rows, col: Cols) -> bool {
+        match self.selection_rect() {
+            Some(((c0, r0), (c1, r1))) => (r0..=r1).contains(&row) && (c0..=c1).contains(&col),
+            None => false,
+        }
+    }
+
     fn highlight_cursor<W: Write>(&self, s: &mut W) {
         if self.render_mode == RenderMode::Ascii {
             let (c, r) = self.cursor;
@@ -194,37 +744,37 @@ impl Board {
             write!(s, "{}", style::Reset).unwrap();
         }
     }
-
-    fn count_n(&self, row: Rows, col: Cols) -> usize {
-        let mut n: usize = 0;
-
-        for dr in 0..=2 {
-            for dc in 0..=2 {
-                if dr != 1 || dc != 1 {
-                    let r = emod((row + dr) as i32 - 1, self.board.len() as i32);
-                    let c = emod((col + dc) as i32 - 1, self.board[0].len() as i32);
-                    if self.board[r as usize][c as usize] == Cell::Alive {
-                        n += 1;
-                    }
-                }
-            }
-        }
-
-        n
-    }
-}
-
-fn emod(a: i32, b: i32) -> i32 {
-    (a % b + b) % b
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum Mode {
     Run,
     Edit,
 }
 
 impl Mode {
+    fn title(&self) -> &str {
+        match self {
+            Mode::Run => "Run",
+            Mode::Edit => "Edit",
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Mode::Run => "run",
+            Mode::Edit => "edit",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "run" => Some(Mode::Run),
+            "edit" => Some(Mode::Edit),
+            _ => None,
+        }
+    }
+
     fn toggle(&mut self) {
         match self {
             Mode::Run => *self = Mode::Edit,
@@ -233,10 +783,168 @@ impl Mode {
     }
 }
 
-fn main() {
+fn main() -> Result<(), String> {
     let matches = get_args();
+    match matches.subcommand() {
+        Some(("search", matches)) => return search(matches),
+        Some(("predecessor", matches)) => return find_predecessor(matches),
+        Some(("periodic", matches)) => return find_periodic(matches),
+        Some(("evolve", matches)) => return evolve(matches),
+        _ => {}
+    }
     let cols = matches.get_one::<u16>("columns").unwrap_or(&DEFAULT_COLS);
     let rows = matches.get_one::<u16>("rows").unwrap_or(&DEFAULT_ROWS);
+    let seed = rng::resolve_seed(matches.get_one::<u64>("seed").copied());
+    let load_path = matches.get_one::<String>("load");
+    let save_path = matches
+        .get_one::<String>("save")
+        .map_or(DEFAULT_SAVE_FILE, String::as_str);
+    let session_path = matches.get_one::<String>("session");
+    let import_path = matches.get_one::<String>("import");
+    let threshold = *matches
+        .get_one::<f32>("threshold")
+        .unwrap_or(&DEFAULT_THRESHOLD);
+    let png_path = matches
+        .get_one::<String>("png")
+        .map_or(DEFAULT_PNG_FILE, String::as_str);
+    let cell_size = *matches
+        .get_one::<u32>("cell-size")
+        .unwrap_or(&DEFAULT_CELL_SIZE) as usize;
+    let palette = matches.get_one::<Palette>("palette");
+    let record_path = matches.get_one::<String>("record");
+    let stream_path = matches.get_one::<String>("stream");
+    let stream_format = *matches
+        .get_one::<StreamFormat>("stream-format")
+        .unwrap_or(&StreamFormat::Y4m);
+    let capture_options = create_capture_options(&matches);
+    let soup = create_soup(&matches);
+    let soup_size = matches.get_one::<(Rows, Cols)>("soup-size").copied();
+    let census_path = matches.get_one::<String>("census");
+    let history_path = matches.get_one::<String>("history");
+    let on_cycle = *matches
+        .get_one::<CycleAction>("on-cycle")
+        .unwrap_or(&CycleAction::Report);
+
+    // An existing `--session` file takes the place of every other startup option.
+    let (mut board, mut mode) = match session_path.filter(|path| Path::new(path).exists()) {
+        Some(path) => Board::restore(&Session::load(path)?)?,
+        None => {
+            let loaded = load_path.map(pattern::load).transpose()?;
+            let engine = create_engine(
+                &matches,
+                seed,
+                loaded.as_ref().zip(load_path.map(Path::new)),
+            )?;
+            let mut board = Board::new(*rows as usize, *cols as usize, engine, seed);
+            if let Some(pattern) = &loaded {
+                let (r, c) = pattern.centred_in(*rows as usize, *cols as usize);
+                board.stamp(pattern, r, c)?;
+            }
+            if let Some(path) = import_path {
+                let (rows, cols) = (board.board.len(), board.board[0].len());
+                let pattern = render::import_pattern(path, rows, cols, threshold)?;
+                let (r, c) = pattern.centred_in(rows, cols);
+                board.stamp(&pattern, r, c)?;
+            }
+            (board, Mode::Edit)
+        }
+    };
+    board.set_soup(soup.clone(), soup_size);
+
+    let headless = matches.get_flag("headless");
+    if stream_path.is_some_and(|target| target == "-") && !headless {
+        return Err(image_error(
+            "only --headless runs can stream to stdout, where the board is drawn otherwise",
+        ));
+    }
+    if census_path.is_some_and(|target| target == "-") {
+        if !headless {
+            return Err(census_error(
+                "only --headless runs can write the census to stdout, where the board is drawn otherwise",
+            ));
+        }
+        if stream_path.is_some_and(|target| target == "-") {
+            return Err(census_error(
+                "the census and the stream cannot both go to stdout",
+            ));
+        }
+    }
+    if headless
+        && (record_path.is_some() || stream_path.is_some() || history_path.is_some())
+        && !matches.contains_id("length")
+    {
+        return Err(image_error(
+            "recording, streaming or logging history headless needs --frames or --duration",
+        ));
+    }
+    // With nothing loaded a headless run has nothing to watch, so it starts from a soup.
+    if headless && board.board.iter().flatten().all(|&cell| cell == Cell::DEAD) {
+        board.randomize();
+    }
+    let mut captures = Captures {
+        gif: record_path
+            .map(|path| board.record(path, cell_size, palette, capture_options))
+            .transpose()?,
+        stream: stream_path
+            .map(|target| FrameStream::open(target, stream_format, capture_options))
+            .transpose()?,
+    };
+    // The recording starts with the current generation, and so does the stream.
+    if let Some(stream) = &mut captures.stream {
+        stream.offer(&board.to_frame(cell_size, palette))?;
+    }
+    if headless {
+        // Frames sent to stdout leave no room for messages there.
+        let quiet = captures.is_stdout() || census_path.is_some_and(|target| target == "-");
+        let say = |line: &str| {
+            if quiet {
+                eprintln!("{line}")
+            } else {
+                println!("{line}")
+            }
+        };
+        let mut reports = Vec::new();
+        // A census waits for the board to settle, however long the captures run.
+        let mut settled = census_path.is_none() || board.cycles.found().is_some();
+        // A history alone runs as long as a recording of the same length would.
+        let logged = match history_path {
+            Some(_) => capture_options.frames.unwrap_or(0) * capture_options.skip,
+            None => 0,
+        } as u64;
+        let start = board.generation;
+        while captures.is_active()
+            || board.generation - start < logged
+            || (!settled && board.generation < MAX_SETTLE_GENERATIONS)
+        {
+            settled |= board.next_gen().is_some();
+            reports.extend(captures.offer(|| board.to_frame(cell_size, palette)));
+        }
+        for report in reports {
+            say(&report?);
+        }
+        if let Some(path) = census_path {
+            if !settled {
+                say(&format!(
+                    "Board did not settle within {MAX_SETTLE_GENERATIONS} generations"
+                ));
+            }
+            let census = board.census()?;
+            say(census.table().trim_end());
+            match path.as_str() {
+                "-" => println!("{}", census.to_json()),
+                path => {
+                    census.save(path)?;
+                    say(&format!("Census written to {path}"));
+                }
+            }
+        }
+        if let Some(path) = history_path {
+            board.save_history(path)?;
+            say(&format!("History written to {path}"));
+        }
+        say(&format!("Seed: {}", board.seed()));
+        return Ok(());
+    }
 
     let mut stdout = stdout().into_raw_mode().unwrap();
     write!(stdout, "{}", termion::cursor::Hide).unwrap();
@@ -252,27 +960,94 @@ fn main() {
     });
 
     let mut quit = false;
-    let mut mode = Mode::Edit;
-    let mut board = Board::new(*rows as usize, *cols as usize);
+    let session_file = session_path.map_or(DEFAULT_SESSION_FILE, String::as_str);
 
     while !quit {
         match mode {
             Mode::Run => {
                 board.to_unicode_mode();
-                board.next_gen();
+                if let Some(outcome) = board.next_gen() {
+                    match on_cycle {
+                        CycleAction::Report => board.set_message(outcome.to_string()),
+                        CycleAction::Pause => {
+                            board.set_message(format!("{outcome}, paused"));
+                            mode = Mode::Edit;
+                        }
+                        CycleAction::Reseed => {
+                            board.randomize();
+                            board.set_message(format!("{outcome}, reseeded"));
+                        }
+                    }
+                }
+                for report in captures.offer(|| board.to_frame(cell_size, palette)) {
+                    board.set_message(report.unwrap_or_else(|err| err));
+                }
             }
             Mode::Edit => {
                 board.to_ascii_mode();
+                board.pause();
             }
         }
 
-        board.render(&mut stdout);
+        board.render(&mut stdout, &mode);
         stdout.flush().unwrap();
 
         if let Ok(key) = rx.recv_timeout(timeout) {
             match key {
                 Key::Ctrl('c') | Key::Char('q') => quit = true,
                 Key::Char('\n') => mode.toggle(),
+                Key::Char('?') => board.toggle_help(),
+                Key::Char('H') => {
+                    let path = history_path.map_or(DEFAULT_HISTORY_FILE, String::as_str);
+                    let message = board
+                        .save_history(path)
+                        .map_or_else(|err| err, |_| format!("History written to {path}"));
+                    board.set_message(message);
+                }
+                Key::Char('K') => {
+                    board.toggle_census(census_path.map_or(DEFAULT_CENSUS_FILE, String::as_str))
+                }
+                Key::Ctrl('s') => {
+                    let message = board
+                        .to_session(&mode)
+                        .and_then(|session| session.save(session_file))
+                        .map_or_else(|err| err, |_| format!("Saved session to {session_file}"));
+                    board.set_message(message);
+                }
+                Key::Ctrl('o') => {
+                    match Session::load(session_file).and_then(|s| Board::restore(&s)) {
+                        Ok((restored, restored_mode)) => {
+                            board = restored;
+                            board.set_soup(soup.clone(), soup_size);
+                            board.set_message(format!("Restored session from {session_file}"));
+                            mode = restored_mode;
+                        }
+                        Err(err) => board.set_message(err),
+                    }
+                }
+                Key::Char('P') => {
+                    let message = board
+                        .to_frame(cell_size, palette)
+                        .save_png(png_path)
+                        .map_or_else(|err| err, |_| format!("Exported {png_path}"));
+                    board.set_message(message);
+                }
+                Key::Char('G') => {
+                    let message = match render::stop(&mut captures.gif) {
+                        Some(report) => report.unwrap_or_else(|err| err),
+                        None => {
+                            let path = record_path.map_or(DEFAULT_GIF_FILE, String::as_str);
+                            match board.record(path, cell_size, palette, capture_options) {
+                                Ok(started) => {
+                                    captures.gif = Some(started);
+                                    format!("Recording to {path}")
+                                }
+                                Err(err) => err,
+                            }
+                        }
+                    };
+                    board.set_message(message);
+                }
                 key => {
                     if mode == Mode::Edit {
                         match key {
@@ -282,7 +1057,43 @@ fn main() {
                             Key::Char('s') | Key::Down => board.move_cursor_down(),
                             Key::Char('a') | Key::Left => board.move_cursor_left(),
                             Key::Char('d') | Key::Right => board.move_cursor_right(),
+                            Key::Char('p') => board.toggle_picker(),
+                            Key::Esc if board.is_picking() => board.toggle_picker(),
+                            Key::Char(']') if board.is_picking() => board.change_pick(Pick::next),
+                            Key::Char('[') if board.is_picking() => board.change_pick(Pick::prev),
+                            Key::Char('t') if board.is_picking() => board.change_pick(Pick::rotate),
+                            Key::Char('f') if board.is_picking() => board.change_pick(Pick::flip),
+                            Key::Char(' ') if board.is_picking() => {
+                                if let Err(err) = board.stamp_pick() {
+                                    board.set_message(err);
+                                }
+                            }
                             Key::Char(' ') => board.toggle_cur_cell(),
+                            Key::Char('v') => board.toggle_selection(),
+                            Key::Char('b') => board.find_predecessor(),
+                            Key::Char('L') => {
+                                let message = match load_path {
+                                    Some(path) => pattern::load(path)
+                                        .and_then(|p| board.stamp_at_cursor(&p))
+                                        .map_or_else(|err| err, |_| format!("Loaded {path}")),
+                                    None => "No pattern file given with --load".to_string(),
+                                };
+                                board.set_message(message);
+                            }
+                            Key::Char('S') => {
+                                let message = pattern::save(save_path, &board.to_pattern())
+                                    .map_or_else(|err| err, |_| format!("Saved {save_path}"));
+                                board.set_message(message);
+                            }
+                            Key::Char('I') => {
+                                let message = match import_path {
+                                    Some(path) => board
+                                        .import_at_cursor(path, threshold)
+                                        .map_or_else(|err| err, |_| format!("Imported {path}")),
+                                    None => "No image given with --import".to_string(),
+                                };
+                                board.set_message(message);
+                            }
                             _ => {}
                         }
                     }
@@ -299,6 +1110,588 @@ fn main() {
         cursor::Show
     )
     .unwrap();
+    drop(stdout);
+
+    for report in captures.stop() {
+        println!("{}", report.unwrap_or_else(|err| err));
+    }
+    println!("Seed: {}", board.seed());
+    if let Some(path) = history_path {
+        board.save_history(path)?;
+        println!("History written to {path}");
+    }
+    if let Some(path) = session_path {
+        board.to_session(&mode)?.save(path)?;
+        println!("Saved session to {path}");
+    }
+    Ok(())
+}
+
+// Runs `--soups` random soups on all cores until they settle, takes a census of each and writes
+// what they left behind.
+fn search(matches: &ArgMatches) -> Result<(), String> {
+    let rule = matches.get_one::<Rule>("rule").cloned().unwrap_or_default();
+    let first_seed = rng::resolve_seed(matches.get_one::<u64>("seed").copied());
+    let soup = create_soup(matches);
+    let soup_size = *matches
+        .get_one::<(Rows, Cols)>("soup-size")
+        .unwrap_or(&SEARCH_SOUP_SIZE);
+    let soups = *matches.get_one::<usize>("soups").unwrap_or(&SEARCH_SOUPS);
+    let max_generations = *matches
+        .get_one::<u64>("max-generations")
+        .unwrap_or(&SEARCH_MAX_GENERATIONS);
+    let rare = *matches.get_one::<usize>("rare").unwrap_or(&1);
+    let methuselah = *matches
+        .get_one::<u64>("methuselah")
+        .unwrap_or(&SEARCH_METHUSELAH);
+    let threads = matches
+        .get_one::<u32>("threads")
+        .map(|&n| n as usize)
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .min(soups);
+    let out_path = matches.get_one::<String>("out");
+    let format = match (matches.get_one::<Format>("format"), out_path) {
+        (Some(&format), _) => format,
+        (None, Some(path)) => Format::of_path(path),
+        (None, None) => Format::Json,
+    };
+
+    // Checks the rule once, before any thread starts.
+    Census::take(&rule, &[vec![Cell::DEAD]], 0)?;
+
+    let settings = Settings::new(
+        rule.to_string(),
+        soup_size,
+        &soup,
+        first_seed,
+        soups,
+        max_generations,
+    );
+    let mut report = SearchReport::new(settings);
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let progress = stderr().is_terminal();
+
+    thread::scope(|scope| -> Result<(), String> {
+        for _ in 0..threads {
+            let tx = tx.clone();
+            let (rule, soup, next) = (&rule, &soup, &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= soups {
+                    break;
+                }
+                let seed = first_seed.wrapping_add(i as u64);
+                let mut rng = rng::from_seed_stream(seed, rng::SOUP_STREAM);
+                let cells = soup.generate(soup_size.0, soup_size.1, &mut rng);
+                if tx
+                    .send((seed, search::run_soup(rule, &cells, max_generations)))
+                    .is_err()
+                {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (done, (seed, run)) in rx.iter().enumerate() {
+            let run = run?;
+            report.add(seed, run.lifespan, &run.census);
+            if progress {
+                eprint!("\rSearched {}/{soups} soups", done + 1);
+            }
+        }
+        if progress {
+            eprintln!();
+        }
+        Ok(())
+    })?;
+    report.finish(rare, methuselah);
+
+    match out_path.map(String::as_str) {
+        Some("-") => {
+            eprint!("{}", report.table());
+            print!("{}", report.write(format));
+        }
+        Some(path) => {
+            print!("{}", report.table());
+            report.save(path, format)?;
+            println!("Results written to {path}");
+        }
+        None => print!("{}", report.table()),
+    }
+    Ok(())
+}
+
+// How every rule of a genetic search is tried: soups on the unbounded plane, run until they
+// settle.
+struct EvolveTrial {
+    soup: Soup,
+    soup_size: (Rows, Cols),
+    max_generations: u64,
+}
+
+impl EvolveTrial {
+    fn describe(&self) -> String {
+        let noise = match self.soup.noise_scale {
+            Some(scale) => format!(", noise {scale}"),
+            None => String::new(),
+        };
+        format!(
+            "{}x{} {} soups of density {}{noise} on the plane, up to {} generations",
+            self.soup_size.0,
+            self.soup_size.1,
+            self.soup.symmetry,
+            self.soup.density,
+            self.max_generations
+        )
+    }
+
+    // Runs the soup seeded `seed` under `rule` and measures it: how steady its population ends up,
+    // how many kinds of objects and whether spaceships it leaves, flown away or not, and the
+    // entropy of the 2x2 blocks of what is left in its bounding box.
+    fn run(&self, rule: &Rule, seed: u64) -> Result<Traits, String> {
+        let mut rng = rng::from_seed_stream(seed, rng::SOUP_STREAM);
+        let soup = self
+            .soup
+            .generate(self.soup_size.0, self.soup_size.1, &mut rng);
+        let run = search::run_soup(rule, &soup, self.max_generations)?;
+        let census = run.census;
+        if census.population == 0 {
+            return Ok(Traits::default());
+        }
+
+        let stability = if run.lifespan.is_some() {
+            1.0
+        } else {
+            let populations = &run.populations;
+            let tail = &populations[populations.len() / 2..];
+            let mean = tail.iter().sum::<usize>() as f64 / tail.len() as f64;
+            let variance =
+                tail.iter().map(|&p| (p as f64 - mean).powi(2)).sum::<f64>() / tail.len() as f64;
+            1.0 - (variance.sqrt() / mean).min(1.0)
+        };
+        let kinds = census.tally.iter().filter(|t| t.apgcode.is_some()).count();
+        let moving = census.tally.iter().any(|t| t.class == Class::Spaceship);
+
+        let cells = &run.cells;
+        let mut blocks = [0; 16];
+        for r in 0..cells.len().saturating_sub(1) {
+            for c in 0..cells[r].len() - 1 {
+                let block = [(0, 0), (0, 1), (1, 0), (1, 1)]
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &(dr, dc))| cells[r + dr][c + dc].is_alive())
+                    .fold(0, |block, (i, _)| block | 1 << i);
+                blocks[block] += 1;
+            }
+        }
+
+        Ok(Traits {
+            stability,
+            diversity: evolution::saturate(kinds as f64, DIVERSITY_HALF),
+            motion: if moving { 1.0 } else { 0.0 },
+            entropy: evolution::entropy(&blocks),
+        })
+    }
+}
+
+// Evolves Life-like rules for the fitness asked for, checkpointing every generation.
+fn evolve(matches: &ArgMatches) -> Result<(), String> {
+    let trial = EvolveTrial {
+        soup: create_soup(matches),
+        soup_size: *matches
+            .get_one::<(Rows, Cols)>("soup-size")
+            .unwrap_or(&SEARCH_SOUP_SIZE),
+        max_generations: *matches
+            .get_one::<u64>("max-generations")
+            .unwrap_or(&EVOLVE_MAX_GENERATIONS),
+    };
+    let generations = *matches
+        .get_one::<u64>("generations")
+        .unwrap_or(&EVOLVE_GENERATIONS);
+    let resume_path = matches.get_one::<String>("resume");
+    let checkpoint_path = matches.get_one::<String>("checkpoint").or(resume_path);
+    let conditions = trial.describe();
+
+    let mut evolution = match resume_path {
+        Some(path) => Evolution::resume(LifeLike, Checkpoint::load(path)?, &conditions)?,
+        None => {
+            let settings = evolution::Settings {
+                seed: rng::resolve_seed(matches.get_one::<u64>("seed").copied()),
+                population: *matches
+                    .get_one::<usize>("population")
+                    .unwrap_or(&EVOLVE_POPULATION),
+                elite: *matches.get_one::<usize>("elite").unwrap_or(&EVOLVE_ELITE),
+                tournament: *matches
+                    .get_one::<usize>("tournament")
+                    .unwrap_or(&EVOLVE_TOURNAMENT),
+                mutation: *matches
+                    .get_one::<f64>("mutation")
+                    .unwrap_or(&EVOLVE_MUTATION),
+                trials: *matches.get_one::<usize>("trials").unwrap_or(&EVOLVE_TRIALS),
+                objective: match matches.get_one::<Objective>("fitness") {
+                    Some(objective) => objective.clone(),
+                    None => Objective::parse(EVOLVE_FITNESS)?,
+                },
+                space: String::new(),
+                conditions,
+            };
+            // A rule given with --rule is the first of the first generation.
+            let first = match matches.get_one::<Rule>("rule") {
+                Some(rule) => vec![LifeLike.decode(&rule.to_string())?],
+                None => Vec::new(),
+            };
+            Evolution::new(LifeLike, settings, first)?
+        }
+    };
+    let threads = matches
+        .get_one::<u32>("threads")
+        .map(|&n| n as usize)
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+    let settings = evolution.settings().clone();
+    println!(
+        "Evolving {} rules for {} over {} soups each, seed {}: {}",
+        settings.population,
+        settings.objective,
+        settings.trials,
+        settings.seed,
+        settings.conditions
+    );
+
+    while evolution.generation() < generations {
+        let rules = evolution.genomes();
+        let seeds = evolution.soup_seeds();
+        let total = rules.len() * seeds.len();
+        let mut runs = vec![Traits::default(); total];
+        let next = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+
+        thread::scope(|scope| -> Result<(), String> {
+            for _ in 0..threads.min(total) {
+                let tx = tx.clone();
+                let (rules, seeds, trial, next) = (&rules, &seeds, &trial, &next);
+                scope.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= total {
+                        break;
+                    }
+                    let run = trial.run(&rules[i / seeds.len()], seeds[i % seeds.len()]);
+                    if tx.send((i, run)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+            for (i, run) in rx.iter() {
+                runs[i] = run?;
+            }
+            Ok(())
+        })?;
+
+        let traits: Vec<Traits> = runs.chunks(seeds.len()).map(Traits::mean).collect();
+        let record = evolution.advance(&traits);
+        println!(
+            "Generation {}: best {:.3} {}, mean {:.3}, {} distinct rules",
+            record.generation,
+            record.best.fitness,
+            record.best.genome,
+            record.mean_fitness,
+            record.distinct
+        );
+        if let Some(path) = checkpoint_path {
+            evolution.checkpoint().save(path)?;
+        }
+    }
+
+    print!("{}", evolution.table());
+    if let Some(path) = checkpoint_path {
+        println!("Checkpoint written to {path}");
+    }
+    Ok(())
+}
+
+// Looks for a predecessor of a pattern file, or proves there is none, and writes it out.
+fn find_predecessor(matches: &ArgMatches) -> Result<(), String> {
+    let path = matches.get_one::<String>("target").unwrap();
+    let target = pattern::load(path)?;
+    let rule = match (matches.get_one::<Rule>("rule"), &target.rule) {
+        (Some(rule), _) => rule.clone(),
+        (None, Some(rule)) => {
+            let (name, grid) = split_bounded_grid(rule);
+            if let Some(grid) = grid {
+                eprintln!("Ignoring the bounded grid {grid} of rule {rule}");
+            }
+            Rule::parse(name).map_err(|_| {
+                predecessor_error(&format!("predecessors need a Life-like rule, not {rule}"))
+            })?
+        }
+        (None, None) => Rule::default(),
+    };
+    let options = Options {
+        margin: *matches
+            .get_one::<u32>("margin")
+            .unwrap_or(&(predecessor::DEFAULT_MARGIN as u32)) as usize,
+        exact: matches.get_flag("exact"),
+        max_conflicts: Some(
+            *matches
+                .get_one::<u64>("max-conflicts")
+                .unwrap_or(&predecessor::DEFAULT_MAX_CONFLICTS),
+        ),
+    };
+    let out_path = matches.get_one::<String>("out");
+
+    let cells: Vec<Vec<Cell>> = target
+        .cells
+        .iter()
+        .map(|row| row.iter().map(|&state| Cell(state.min(1))).collect())
+        .collect();
+    let population = cells.iter().flatten().filter(|c| c.is_alive()).count();
+    // Messages make way for the pattern on stdout.
+    let say = |line: &str| match out_path {
+        Some(_) => println!("{line}"),
+        None => eprintln!("{line}"),
+    };
+    say(&format!(
+        "Target: {}x{} (cols x rows) with {population} cells under {rule}",
+        target.cols(),
+        target.rows()
+    ));
+
+    let search = predecessor::search(&rule, &cells, options)?;
+    say(&format!(
+        "Solver: {} variables, {} clauses, {} conflicts",
+        search.variables, search.clauses, search.conflicts
+    ));
+    match search.found {
+        Found::Pattern(cells) => {
+            let pattern = Pattern {
+                rule: Some(rule.to_string()),
+                comments: vec![format!(
+                    "Predecessor of {path}, grown by {} cells on every side",
+                    options.margin
+                )],
+                cells: cells
+                    .iter()
+                    .map(|row| row.iter().map(|c| c.0).collect())
+                    .collect(),
+                ..Pattern::default()
+            };
+            say(&format!(
+                "Found a predecessor of {}x{} with {} cells",
+                pattern.cols(),
+                pattern.rows(),
+                pattern.population()
+            ));
+            match out_path {
+                Some(out) => {
+                    pattern::save(out, &pattern)?;
+                    say(&format!("Predecessor written to {out}"));
+                }
+                None => print!("{}", pattern::Format::Rle.write(&pattern)),
+            }
+        }
+        Found::Impossible if options.proves_orphan() => say(
+            "No predecessor exists: the target is an orphan, so every board holding it is a Garden of Eden",
+        ),
+        Found::Impossible => say(&format!(
+            "No predecessor fits within {} cells of the target",
+            options.margin
+        )),
+        Found::GaveUp => say(&format!(
+            "Gave up after {} conflicts; raise --max-conflicts to search longer",
+            search.conflicts
+        )),
+    }
+    Ok(())
+}
+
+// Looks for an oscillator or a spaceship in a box, or proves there is none, and writes it out.
+fn find_periodic(matches: &ArgMatches) -> Result<(), String> {
+    let rule = matches.get_one::<Rule>("rule").cloned().unwrap_or_default();
+    let (rows, cols) = *matches.get_one("size").unwrap_or(&PERIODIC_SIZE);
+    let options = periodic::Options {
+        period: *matches.get_one::<u32>("period").unwrap() as usize,
+        shift: *matches.get_one("shift").unwrap_or(&(0, 0)),
+        rows,
+        cols,
+        symmetry: *matches
+            .get_one::<Symmetry>("symmetry")
+            .unwrap_or(&Symmetry::C1),
+        max_conflicts: Some(
+            *matches
+                .get_one::<u64>("max-conflicts")
+                .unwrap_or(&predecessor::DEFAULT_MAX_CONFLICTS),
+        ),
+    };
+    let out_path = matches.get_one::<String>("out");
+
+    // Messages make way for the pattern on stdout.
+    let say = |line: &str| match out_path {
+        Some(_) => println!("{line}"),
+        None => eprintln!("{line}"),
+    };
+    say(&format!(
+        "Looking for a {options} with {} symmetry in a {rows}x{cols} (rows x cols) box under {rule}",
+        options.symmetry
+    ));
+
+    let search = periodic::search(&rule, options)?;
+    say(&format!(
+        "Solver: {} variables, {} clauses, {} conflicts",
+        search.variables, search.clauses, search.conflicts
+    ));
+    match search.found {
+        Found::Pattern(cells) => {
+            let mut pattern = Pattern::from_cells(&cells).cropped();
+            pattern.rule = Some(rule.to_string());
+            pattern.comments = vec![format!("A {options}")];
+            say(&format!(
+                "Found a {options} of {}x{} with {} cells",
+                pattern.cols(),
+                pattern.rows(),
+                pattern.population()
+            ));
+            match out_path {
+                Some(out) => {
+                    pattern::save(out, &pattern)?;
+                    say(&format!("Pattern written to {out}"));
+                }
+                None => print!("{}", pattern::Format::Rle.write(&pattern)),
+            }
+        }
+        Found::Impossible => say(&format!("No {options} fits in a {rows}x{cols} box")),
+        Found::GaveUp => say(&format!(
+            "Gave up after {} conflicts; raise --max-conflicts to search longer",
+            search.conflicts
+        )),
+    }
+    Ok(())
+}
+
+// Draws lines of text in a box over the top left of the board.
+fn render_overlay<W: Write>(s: &mut W, lines: &[String]) {
+    let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) + 1;
+
+    write!(
+        s,
+        "{}{}",
+        color::Fg(HELP_COLOR_PAIR.0),
+        color::Bg(HELP_COLOR_PAIR.1)
+    )
+    .unwrap();
+    for (i, line) in lines.iter().enumerate() {
+        write!(s, "{}{line:<columns$}", cursor::Goto(3, (i + 2) as u16)).unwrap();
+    }
+    write!(s, "{}", style::Reset).unwrap();
+}
+
+fn create_soup(matches: &ArgMatches) -> Soup {
+    Soup {
+        density: *matches.get_one::<f64>("density").unwrap_or(&0.5),
+        symmetry: *matches
+            .get_one::<Symmetry>("symmetry")
+            .unwrap_or(&Symmetry::C1),
+        noise_scale: matches.get_one::<f32>("noise").copied(),
+    }
+}
+
+fn create_capture_options(matches: &ArgMatches) -> CaptureOptions {
+    let mut options = CaptureOptions {
+        delay_ms: *matches
+            .get_one::<u32>("frame-delay")
+            .unwrap_or(&DEFAULT_FRAME_DELAY),
+        skip: *matches.get_one::<u32>("frame-skip").unwrap_or(&1) as usize,
+        frames: matches.get_one::<u32>("frames").map(|&n| n as usize),
+    };
+    if let Some(&seconds) = matches.get_one::<f64>("duration") {
+        options.frames = Some(options.frames_in(seconds));
+    }
+    options
+}
+
+fn create_engine(
+    matches: &ArgMatches,
+    seed: u64,
+    pattern: Option<(&Pattern, &Path)>,
+) -> Result<Engine, String> {
+    let automaton = match (
+        matches.get_one::<String>("rule-script"),
+        matches.get_one::<String>("rule-file"),
+        matches.get_one::<Rule>("rule"),
+        pattern.and_then(|(p, path)| Some((p.rule.as_ref()?, path))),
+    ) {
+        (Some(path), _, _, _) => ScriptRule::load(path)?.into(),
+        (None, Some(path), _, _) => GollyRule::load(path)?.into(),
+        (None, None, Some(rule), _) => rule.clone().into(),
+        (None, None, None, Some((rule, path))) => {
+            if let (_, Some(grid)) = split_bounded_grid(rule) {
+                eprintln!(
+                    "Ignoring the bounded grid {grid} of rule {rule}: the board wraps around"
+                );
+            }
+            Automaton::resolve(rule, path.parent().unwrap_or(Path::new(".")))?
+        }
+        (None, None, None, None) => Rule::default().into(),
+    };
+    let p_birth = *matches.get_one::<f64>("p-birth").unwrap_or(&1.0);
+    let p_death = *matches.get_one::<f64>("p-death").unwrap_or(&1.0);
+    let scheme = matches
+        .get_one::<UpdateScheme>("update")
+        .unwrap_or(&UpdateScheme::Synchronous);
+
+    let automaton = match automaton {
+        Automaton::LifeLike(rule) => rule.with_probabilities(p_birth, p_death)?.into(),
+        other if p_birth == 1.0 && p_death == 1.0 => other,
+        _ => {
+            return Err(rule_error(
+                "--p-birth and --p-death only apply to Life-like rules",
+            ))
+        }
+    };
+    Ok(Engine::new(automaton, *scheme, seed))
+}
+
+// The Life-like rule, for the board and every subcommand, each with its own default.
+fn rule_arg(help: &'static str) -> Arg {
+    Arg::new("rule")
+        .long("rule")
+        .value_name("rule")
+        .action(ArgAction::Set)
+        .help(help)
+        .value_parser(Rule::parse)
+}
+
+// How random soups are drawn, for the board and the subcommands that run soups.
+fn soup_args() -> [Arg; 4] {
+    [
+        Arg::new("density")
+            .long("density")
+            .value_name("p")
+            .action(ArgAction::Set)
+            .help("Density of random soups [default: 0.5]")
+            .value_parser(value_parser!(f64)),
+        Arg::new("symmetry")
+            .long("symmetry")
+            .value_name("sym")
+            .action(ArgAction::Set)
+            .help("Symmetry of random soups: C1, D2V, D2H, C2, C4, D4 or D8 [default: C1]")
+            .value_parser(Symmetry::parse),
+        Arg::new("noise")
+            .long("noise")
+            .value_name("scale")
+            .action(ArgAction::Set)
+            .help("Threshold value noise of the given scale instead of white noise")
+            .value_parser(value_parser!(f32)),
+        Arg::new("soup-size")
+            .long("soup-size")
+            .value_name("rowsxcols")
+            .action(ArgAction::Set)
+            .help("Confine random soups to a centred rectangle, e.g. 16x16")
+            .value_parser(soup::parse_size),
+    ]
 }
 
 fn get_args() -> ArgMatches {
@@ -326,5 +1719,453 @@ fn get_args() -> ArgMatches {
                 .help("Number of rows in the board")
                 .value_parser(value_parser!(u16).range((MIN_ROWS as i64)..=(MAX_ROWS as i64))),
         )
+        .arg(rule_arg("Life-like rule in B/S notation [default: B3/S23]"))
+        .arg(
+            Arg::new("rule-file")
+                .long("rule-file")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .conflicts_with("rule")
+                .help("Golly .rule file (@TABLE or @TREE) to run instead of a Life-like rule"),
+        )
+        .arg(
+            Arg::new("rule-script")
+                .long("rule-script")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .conflicts_with_all(["rule", "rule-file"])
+                .help("Rhai script defining fn next(state, neighbours) to run as the rule"),
+        )
+        .arg(
+            Arg::new("p-birth")
+                .long("p-birth")
+                .value_name("p")
+                .action(ArgAction::Set)
+                .help("Probability that a birth happens [default: 1.0]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("p-death")
+                .long("p-death")
+                .value_name("p")
+                .action(ArgAction::Set)
+                .help("Probability that a death happens [default: 1.0]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("update")
+                .long("update")
+                .value_name("scheme")
+                .action(ArgAction::Set)
+                .help("Update scheme: sync, sequential, alpha:<p> or checkerboard [default: sync]")
+                .value_parser(UpdateScheme::parse),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .global(true)
+                .help("Seed for the random number generator")
+                .value_parser(value_parser!(u64)),
+        )
+        .args(soup_args())
+        .arg(
+            Arg::new("on-cycle")
+                .long("on-cycle")
+                .value_name("action")
+                .action(ArgAction::Set)
+                .help("What to do once the board dies out, stops or repeats: report, pause or reseed [default: report]")
+                .value_parser(CycleAction::parse),
+        )
+        .arg(
+            Arg::new("census")
+                .long("census")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the census is written to as JSON, or - for stdout with --headless [default: census.json]"),
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("CSV file the population, births and deaths of every generation are written to on exit"),
+        )
+        .arg(
+            Arg::new("load")
+                .short('l')
+                .long("load")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Load a pattern file (RLE, .cells, Life 1.05/1.06, .mc) into the board"),
+        )
+        .arg(
+            Arg::new("session")
+                .long("session")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Resume the session saved in file, if it exists, and save it there on quit"),
+        )
+        .arg(
+            Arg::new("save")
+                .long("save")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the board is saved to [default: board.rle]"),
+        )
+        .arg(
+            Arg::new("import")
+                .long("import")
+                .value_name("image")
+                .action(ArgAction::Set)
+                .help("Threshold a PNG or PPM image into the board"),
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .value_name("t")
+                .action(ArgAction::Set)
+                .help("Luminance above which imported pixels become live cells [default: 0.5]")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("png")
+                .long("png")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the board is exported to as PNG [default: board.png]"),
+        )
+        .arg(
+            Arg::new("cell-size")
+                .long("cell-size")
+                .value_name("px")
+                .action(ArgAction::Set)
+                .help("Pixels per cell in exported images [default: 8]")
+                .value_parser(value_parser!(u32).range(1..=64)),
+        )
+        .arg(
+            Arg::new("palette")
+                .long("palette")
+                .value_name("colours")
+                .action(ArgAction::Set)
+                .help("Palette of exported images: gray, inverted, heat, ocean or hex colours such as 000000,ffffff [default: rule colours]")
+                .value_parser(Palette::parse),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Record every generation the board runs into an animated GIF"),
+        )
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Stop recording after this many frames")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .value_name("seconds")
+                .action(ArgAction::Set)
+                .help("Stop recording once the GIF lasts this long")
+                .value_parser(render::parse_duration),
+        )
+        .group(ArgGroup::new("length").args(["frames", "duration"]))
+        .arg(
+            Arg::new("frame-delay")
+                .long("frame-delay")
+                .value_name("ms")
+                .action(ArgAction::Set)
+                .help("Delay between GIF frames, in milliseconds [default: 100]")
+                .value_parser(value_parser!(u32).range(10..=655350)),
+        )
+        .arg(
+            Arg::new("frame-skip")
+                .long("frame-skip")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Record only every num-th generation [default: 1]")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Stream every generation as raw video into a file, a named pipe or - for stdout"),
+        )
+        .arg(
+            Arg::new("stream-format")
+                .long("stream-format")
+                .value_name("format")
+                .action(ArgAction::Set)
+                .requires("stream")
+                .help("Raw video format of --stream: y4m or ppm [default: y4m]")
+                .value_parser(StreamFormat::parse),
+        )
+        .group(
+            ArgGroup::new("output")
+                .args(["record", "stream", "census", "history"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .action(ArgAction::SetTrue)
+                .requires("output")
+                .help("Record, stream, log history or take a census without opening the board, then exit"),
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Run random soups until they settle and count the objects they leave, on all cores")
+                .arg(rule_arg("Life-like rule in B/S notation [default: B3/S23]"))
+                .args(soup_args())
+                .arg(
+                    Arg::new("soups")
+                        .long("soups")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Number of soups to run [default: 1000]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Threads to run soups on [default: one per core]")
+                        .value_parser(value_parser!(u32).range(1..)),
+                )
+                .arg(
+                    Arg::new("max-generations")
+                        .long("max-generations")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Give up on soups that have not settled after this many generations [default: 20000]")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("rare")
+                        .long("rare")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Flag objects left by at most this many soups as rare [default: 1]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("methuselah")
+                        .long("methuselah")
+                        .value_name("gens")
+                        .action(ArgAction::Set)
+                        .help("Flag soups that take at least this many generations to settle [default: 1000]")
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("File the results are written to, or - for stdout"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("format")
+                        .action(ArgAction::Set)
+                        .help("Format of --out: json or csv [default: csv for .csv files, json otherwise]")
+                        .value_parser(Format::parse),
+                ),
+        )
+        .subcommand(
+            Command::new("predecessor")
+                .about("Find a pattern that evolves into a target pattern, or prove that none does, with a SAT solver")
+                .arg(rule_arg("Life-like rule in B/S notation [default: the target's rule, else B3/S23]"))
+                .arg(
+                    Arg::new("target")
+                        .value_name("file")
+                        .required(true)
+                        .help("Pattern file to find a predecessor of; its rule applies unless --rule is given"),
+                )
+                .arg(
+                    Arg::new("margin")
+                        .long("margin")
+                        .value_name("cells")
+                        .action(ArgAction::Set)
+                        .help("Cells the predecessor may reach beyond the target on every side [default: 1]")
+                        .value_parser(value_parser!(u32).range(..=64)),
+                )
+                .arg(
+                    Arg::new("exact")
+                        .long("exact")
+                        .action(ArgAction::SetTrue)
+                        .help("Require the next generation to hold the target alone, with nothing alive around it"),
+                )
+                .arg(
+                    Arg::new("max-conflicts")
+                        .long("max-conflicts")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Give up after the solver runs into this many conflicts [default: 1000000]")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("Pattern file the predecessor is written to [default: RLE on stdout]"),
+                ),
+        )
+        .subcommand(
+            Command::new("evolve")
+                .about("Evolve Life-like rules that score well on a fitness over random soups, with a genetic algorithm")
+                .arg(rule_arg("Life-like rule in B/S notation to put in the first generation [default: only random rules]"))
+                .args(soup_args())
+                .arg(
+                    Arg::new("fitness")
+                        .long("fitness")
+                        .value_name("fitness")
+                        .action(ArgAction::Set)
+                        .help("Fitnesses to score rules on, with optional weights: stability, diversity, motion, entropy, e.g. diversity,motion=2 [default: diversity]")
+                        .value_parser(Objective::parse),
+                )
+                .arg(
+                    Arg::new("generations")
+                        .long("generations")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Generation to evolve up to, counting those of a resumed run [default: 20]")
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("population")
+                        .long("population")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Rules in each generation [default: 20]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("elite")
+                        .long("elite")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Best rules kept unchanged in the next generation [default: 2]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("tournament")
+                        .long("tournament")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Rules drawn to pick each parent from, the fittest winning [default: 3]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("mutation")
+                        .long("mutation")
+                        .value_name("rate")
+                        .action(ArgAction::Set)
+                        .help("Chance of each birth or survival count flipping in a child, besides one that always does [default: 0.05]")
+                        .value_parser(value_parser!(f64)),
+                )
+                .arg(
+                    Arg::new("trials")
+                        .long("trials")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Soups each rule is scored over [default: 4]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("max-generations")
+                        .long("max-generations")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Generations a soup runs for at most before it is scored [default: 1000]")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Threads to run soups on [default: one per core]")
+                        .value_parser(value_parser!(u32).range(1..)),
+                )
+                .arg(
+                    Arg::new("checkpoint")
+                        .long("checkpoint")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("File the state of the run is written to after every generation [default: the --resume file]"),
+                )
+                .arg(
+                    Arg::new("resume")
+                        .long("resume")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("Checkpoint to carry on from, with its population and fitness settings; the soup options and --max-generations must be given again as they were"),
+                ),
+        )
+        .subcommand(
+            Command::new("periodic")
+                .about("Find an oscillator or a spaceship of a period and speed in a box, or prove that none fits, with a SAT solver")
+                .arg(rule_arg("Life-like rule in B/S notation [default: B3/S23]"))
+                .arg(
+                    Arg::new("symmetry")
+                        .long("symmetry")
+                        .value_name("sym")
+                        .action(ArgAction::Set)
+                        .help("Symmetry the pattern must have: C1, D2V, D2H, C2, C4, D4 or D8 [default: C1]")
+                        .value_parser(Symmetry::parse),
+                )
+                .arg(
+                    Arg::new("period")
+                        .long("period")
+                        .value_name("gens")
+                        .action(ArgAction::Set)
+                        .required(true)
+                        .help("Generations after which the pattern comes back")
+                        .value_parser(value_parser!(u32).range(1..=64)),
+                )
+                .arg(
+                    Arg::new("shift")
+                        .long("shift")
+                        .value_name("dx,dy")
+                        .action(ArgAction::Set)
+                        .allow_hyphen_values(true)
+                        .help("Columns right and rows down the pattern moves every period, 0,0 for an oscillator [default: 0,0]")
+                        .value_parser(periodic::Options::parse_shift),
+                )
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .value_name("rowsxcols")
+                        .action(ArgAction::Set)
+                        .help("Box every generation of the pattern must fit in [default: 8x8]")
+                        .value_parser(soup::parse_size),
+                )
+                .arg(
+                    Arg::new("max-conflicts")
+                        .long("max-conflicts")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Give up after the solver runs into this many conflicts [default: 1000000]")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("Pattern file the pattern is written to [default: RLE on stdout]"),
+                ),
+        )
         .get_matches()
 }
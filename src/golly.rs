@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::life::{emod, rule_error, Cell};
+
+pub mod table;
+pub mod tree;
+
+type Rows = usize;
+type Cols = usize;
+
+// Distinct neighbourhoods remembered per rule before the cache is flushed.
+const MAX_CACHED: usize = 1 << 20;
+
+/// The states around a cell: `around` runs clockwise from north (N, NE, E, SE, S, SW, W, NW).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Neighbourhood {
+    pub centre: u8,
+    pub around: [u8; 8],
+}
+
+impl Neighbourhood {
+    /// Moore neighbourhood of `(row, col)`, wrapping around the board edges.
+    pub fn of(cells: &[Vec<Cell>], row: Rows, col: Cols) -> Self {
+        const OFFSETS: [(i32, i32); 8] = [
+            (-1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+            (1, 0),
+            (1, -1),
+            (0, -1),
+            (-1, -1),
+        ];
+        let (rows, cols) = (cells.len() as i32, cells[0].len() as i32);
+
+        let mut around = [0; 8];
+        for (state, (dr, dc)) in around.iter_mut().zip(OFFSETS) {
+            let r = emod(row as i32 + dr, rows);
+            let c = emod(col as i32 + dc, cols);
+            *state = cells[r as usize][c as usize].0;
+        }
+
+        Self {
+            centre: cells[row][col].0,
+            around,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Logic {
+    Table(table::RuleTable),
+    Tree(tree::RuleTree),
+}
+
+/// A rule loaded from a Golly `.rule` file (or a legacy `.table`/`.tree` file).
+#[derive(Clone, Debug)]
+pub struct GollyRule {
+    name: String,
+    path: Option<PathBuf>,
+    n_states: usize,
+    logic: Logic,
+    colors: Vec<u32>,
+    cache: HashMap<[u8; 9], u8>,
+}
+
+impl GollyRule {
+    /// Parses the `@RULE`, `@TABLE`, `@TREE` and `@COLORS` sections; other sections are ignored.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut name = String::new();
+        let mut sections: HashMap<String, String> = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for line in text.lines() {
+            if let Some(header) = line.trim().strip_prefix('@') {
+                let (section, rest) = header
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((header, ""));
+                if section == "RULE" {
+                    name = rest.trim().to_string();
+                }
+                current = Some(section.to_string());
+                sections.entry(section.to_string()).or_default();
+            } else if let Some(section) = &current {
+                let body = sections.get_mut(section).unwrap();
+                body.push_str(line);
+                body.push('\n');
+            }
+        }
+
+        // Files from before `.rule` existed hold a bare table or tree.
+        if current.is_none() {
+            let section = if text.contains("num_nodes") {
+                "TREE"
+            } else {
+                "TABLE"
+            };
+            sections.insert(section.to_string(), text.to_string());
+        }
+
+        let logic = match (sections.get("TABLE"), sections.get("TREE")) {
+            (Some(body), _) => Logic::Table(table::parse(body)?),
+            (None, Some(body)) => Logic::Tree(tree::parse(body)?),
+            (None, None) => return Err(rule_error("rule file has neither @TABLE nor @TREE")),
+        };
+        let n_states = match &logic {
+            Logic::Table(table) => table.n_states(),
+            Logic::Tree(tree) => tree.n_states(),
+        };
+
+        let mut colors = default_colors(n_states);
+        if let Some(body) = sections.get("COLORS") {
+            parse_colors(body, &mut colors)?;
+        }
+
+        Ok(Self {
+            name,
+            path: None,
+            n_states,
+            logic,
+            colors,
+            cache: HashMap::new(),
+        })
+    }
+
+    /// Loads a rule file; an unnamed rule takes the name of the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| rule_error(&format!("cannot read {}: {err}", path.display())))?;
+
+        let mut rule = Self::parse(&text)?;
+        rule.path = Some(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        if rule.name.is_empty() {
+            rule.name = path
+                .file_stem()
+                .map_or(String::new(), |s| s.to_string_lossy().to_string());
+        }
+        Ok(rule)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The file the rule was loaded from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn n_states(&self) -> usize {
+        self.n_states
+    }
+
+    pub fn color_hex(&self, state: u8) -> u32 {
+        self.colors
+            .get(state as usize)
+            .copied()
+            .unwrap_or(0xFF00FFFF)
+    }
+
+    /// Next state of the centre cell. Cells in states the rule does not know are left alone.
+    pub fn next(&mut self, n: &Neighbourhood) -> u8 {
+        let mut key = [n.centre; 9];
+        key[1..].copy_from_slice(&n.around);
+        if let Some(&next) = self.cache.get(&key) {
+            return next;
+        }
+        if key.iter().any(|&s| s as usize >= self.n_states) {
+            return n.centre;
+        }
+
+        let next = match &self.logic {
+            Logic::Table(table) => table.next(n),
+            Logic::Tree(tree) => tree.next(n),
+        };
+        if self.cache.len() >= MAX_CACHED {
+            self.cache.clear();
+        }
+        self.cache.insert(key, next);
+        next
+    }
+}
+
+impl fmt::Display for GollyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// Golly's defaults: black background, white for two states, a red to yellow ramp otherwise.
+pub(crate) fn default_colors(n_states: usize) -> Vec<u32> {
+    let mut colors = vec![0x000000FF; n_states];
+    if n_states == 2 {
+        colors[1] = 0xFFFFFFFF;
+    } else {
+        gradient(&mut colors, (255, 0, 0), (255, 255, 0));
+    }
+    colors
+}
+
+fn gradient(colors: &mut [u32], from: (u8, u8, u8), to: (u8, u8, u8)) {
+    let live = colors.len().saturating_sub(1);
+    for (i, color) in colors.iter_mut().enumerate().skip(1) {
+        let t = if live > 1 {
+            (i - 1) as f32 / (live - 1) as f32
+        } else {
+            0.0
+        };
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u32;
+        *color = rgba(lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2));
+    }
+}
+
+fn rgba(r: u32, g: u32, b: u32) -> u32 {
+    (r << 24) | (g << 16) | (b << 8) | 0xFF
+}
+
+// `state r g b` sets one state, `r1 g1 b1 r2 g2 b2` a ramp over all live states.
+fn parse_colors(body: &str, colors: &mut [u32]) -> Result<(), String> {
+    for line in body.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let err = || rule_error(&format!("invalid @COLORS line '{line}'"));
+        let values = line
+            .split_whitespace()
+            .map(str::parse::<u8>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| err())?;
+
+        match values[..] {
+            [state, r, g, b] => {
+                if let Some(color) = colors.get_mut(state as usize) {
+                    *color = rgba(r as u32, g as u32, b as u32);
+                }
+            }
+            [r1, g1, b1, r2, g2, b2] => gradient(colors, (r1, g1, b1), (r2, g2, b2)),
+            _ => return Err(err()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng as _;
+
+    use super::*;
+    use crate::life::{Engine, Rule, UpdateScheme};
+    use crate::rng;
+
+    #[test]
+    fn a_life_rule_file_runs_like_life() {
+        let text = "\
+@RULE LifeTable
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,g,h,0
+@COLORS
+1 0 255 0
+";
+        let rule = GollyRule::parse(text).unwrap();
+        assert_eq!((rule.name(), rule.n_states()), ("LifeTable", 2));
+        assert_eq!(rule.color_hex(1), 0x00FF00FF);
+
+        let mut rng = rng::from_seed(6);
+        let mut table: Vec<Vec<Cell>> = (0..16)
+            .map(|_| (0..16).map(|_| Cell(rng.gen_bool(0.4) as u8)).collect())
+            .collect();
+        let mut life = table.clone();
+        let mut table_engine = Engine::new(rule, UpdateScheme::Synchronous, 0);
+        let mut life_engine = Engine::new(Rule::default(), UpdateScheme::Synchronous, 0);
+        for _ in 0..20 {
+            table_engine.step(&mut table);
+            life_engine.step(&mut life);
+            assert_eq!(table, life);
+        }
+    }
+}
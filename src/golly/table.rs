@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+
+use super::Neighbourhood;
+use crate::life::rule_error;
+
+// A transition never names more variables than it has cells.
+const MAX_VARS: usize = 9;
+
+type Bindings = [Option<u8>; MAX_VARS];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Neighbours {
+    Moore,
+    VonNeumann,
+    Hexagonal,
+    OneDimensional,
+}
+
+impl Neighbours {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "Moore" => Ok(Neighbours::Moore),
+            "vonNeumann" => Ok(Neighbours::VonNeumann),
+            "hexagonal" => Ok(Neighbours::Hexagonal),
+            "oneDimensional" => Ok(Neighbours::OneDimensional),
+            _ => Err(rule_error(&format!("unknown neighborhood '{name}'"))),
+        }
+    }
+
+    // Indices into `Neighbourhood::around`, in the order Golly lists them after the centre.
+    fn positions(&self) -> &'static [usize] {
+        match self {
+            Neighbours::Moore => &[0, 1, 2, 3, 4, 5, 6, 7],
+            Neighbours::VonNeumann => &[0, 2, 4, 6],
+            Neighbours::Hexagonal => &[0, 2, 3, 4, 6, 7],
+            Neighbours::OneDimensional => &[6, 2],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+struct StateSet([u64; 4]);
+
+impl StateSet {
+    fn insert(&mut self, state: u8) {
+        self.0[state as usize / 64] |= 1 << (state % 64);
+    }
+
+    fn contains(&self, state: u8) -> bool {
+        self.0[state as usize / 64] & (1 << (state % 64)) != 0
+    }
+
+    fn union(&mut self, other: &StateSet) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a |= b;
+        }
+    }
+}
+
+// One input of a transition. `var` is set when the variable is bound, i.e. it must take the
+// same value everywhere it appears, or when the output refers to it.
+#[derive(Clone, Debug)]
+struct Slot {
+    states: StateSet,
+    var: Option<usize>,
+}
+
+impl Slot {
+    fn bind(&self, state: u8, bindings: &mut Bindings) -> bool {
+        if !self.states.contains(state) {
+            return false;
+        }
+        match self.var {
+            Some(var) => match bindings[var] {
+                Some(bound) => bound == state,
+                None => {
+                    bindings[var] = Some(state);
+                    true
+                }
+            },
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Output {
+    State(u8),
+    Var(usize),
+}
+
+#[derive(Clone, Debug)]
+struct Transition {
+    centre: Slot,
+    around: Vec<Slot>,
+    output: Output,
+}
+
+/// A Golly `@TABLE`: transitions are tried in order under every symmetry, the first match wins
+/// and a cell no transition matches keeps its state.
+#[derive(Clone, Debug)]
+pub struct RuleTable {
+    n_states: usize,
+    neighbours: Neighbours,
+    // Permutations of the neighbour slots; `None` for `permute`, which allows all of them.
+    symmetries: Option<Vec<Vec<usize>>>,
+    transitions: Vec<Transition>,
+}
+
+impl RuleTable {
+    pub fn n_states(&self) -> usize {
+        self.n_states
+    }
+
+    pub fn next(&self, n: &Neighbourhood) -> u8 {
+        let around: Vec<u8> = self
+            .neighbours
+            .positions()
+            .iter()
+            .map(|&p| n.around[p])
+            .collect();
+
+        for t in self.transitions.iter() {
+            let mut bindings = [None; MAX_VARS];
+            if !t.centre.bind(n.centre, &mut bindings) {
+                continue;
+            }
+
+            let matched = match &self.symmetries {
+                Some(perms) => perms.iter().find_map(|perm| {
+                    let mut bindings = bindings;
+                    t.around
+                        .iter()
+                        .zip(perm)
+                        .all(|(slot, &p)| slot.bind(around[p], &mut bindings))
+                        .then_some(bindings)
+                }),
+                None => match_permuted(&t.around, &around, 0, bindings),
+            };
+
+            if let Some(bindings) = matched {
+                return match t.output {
+                    Output::State(state) => state,
+                    Output::Var(var) => bindings[var].unwrap_or(n.centre),
+                };
+            }
+        }
+
+        n.centre
+    }
+}
+
+// Assigns every slot to a distinct neighbour, backtracking over the choices.
+fn match_permuted(
+    slots: &[Slot],
+    around: &[u8],
+    used: u16,
+    bindings: Bindings,
+) -> Option<Bindings> {
+    let Some((slot, rest)) = slots.split_first() else {
+        return Some(bindings);
+    };
+
+    (0..around.len())
+        .filter(|&i| used & (1 << i) == 0)
+        .find_map(|i| {
+            let mut bindings = bindings;
+            if slot.bind(around[i], &mut bindings) {
+                match_permuted(rest, around, used | (1 << i), bindings)
+            } else {
+                None
+            }
+        })
+}
+
+pub fn parse(text: &str) -> Result<RuleTable, String> {
+    let mut n_states: Option<usize> = None;
+    let mut neighbours: Option<Neighbours> = None;
+    let mut symmetries = "none".to_string();
+    let mut vars: HashMap<String, StateSet> = HashMap::new();
+    let mut table: Option<RuleTable> = None;
+
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" | "num_states" => {
+                    n_states = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| (2..=256).contains(n))
+                            .ok_or_else(|| rule_error(&format!("invalid n_states '{value}'")))?,
+                    )
+                }
+                "neighborhood" => neighbours = Some(Neighbours::parse(value)?),
+                "symmetries" => symmetries = value.to_string(),
+                _ => return Err(rule_error(&format!("unknown @TABLE setting '{line}'"))),
+            }
+            continue;
+        }
+
+        let table = match &mut table {
+            Some(table) => table,
+            None => {
+                let (Some(n_states), Some(neighbours)) = (n_states, neighbours) else {
+                    return Err(rule_error(
+                        "@TABLE must set n_states and neighborhood before its transitions",
+                    ));
+                };
+                table.insert(RuleTable {
+                    n_states,
+                    neighbours,
+                    symmetries: symmetry_group(&symmetries, neighbours.positions().len())?,
+                    transitions: Vec::new(),
+                })
+            }
+        };
+
+        match line.strip_prefix("var") {
+            Some(def) if def.starts_with(char::is_whitespace) => {
+                let (name, set) = parse_var(def, &vars, table.n_states)?;
+                vars.insert(name, set);
+            }
+            _ => {
+                let t = parse_transition(line, &vars, table)?;
+                table.transitions.push(t);
+            }
+        }
+    }
+
+    table.ok_or_else(|| rule_error("@TABLE has no transitions"))
+}
+
+fn parse_state(token: &str, n_states: usize) -> Option<u8> {
+    token
+        .parse::<u8>()
+        .ok()
+        .filter(|&s| (s as usize) < n_states)
+}
+
+fn parse_var(
+    def: &str,
+    vars: &HashMap<String, StateSet>,
+    n_states: usize,
+) -> Result<(String, StateSet), String> {
+    let err = || rule_error(&format!("invalid variable 'var {}'", def.trim()));
+    let def: String = def.chars().filter(|ch| !ch.is_whitespace()).collect();
+    let (name, values) = def.split_once('=').ok_or_else(err)?;
+    let values = values
+        .strip_prefix('{')
+        .and_then(|v| v.strip_suffix('}'))
+        .ok_or_else(err)?;
+
+    let mut set = StateSet::default();
+    for value in values.split(',') {
+        match (parse_state(value, n_states), vars.get(value)) {
+            (Some(state), _) => set.insert(state),
+            (None, Some(other)) => set.union(other),
+            (None, None) => return Err(err()),
+        }
+    }
+    Ok((name.to_string(), set))
+}
+
+fn parse_transition(
+    line: &str,
+    vars: &HashMap<String, StateSet>,
+    table: &RuleTable,
+) -> Result<Transition, String> {
+    let err = |msg: &str| rule_error(&format!("{msg} in transition '{line}'"));
+
+    // `0,1,2,...` or, for rules with at most 10 states, the compact `012...` form.
+    let tokens: Vec<String> = if line.contains(',') {
+        line.split(',').map(|t| t.trim().to_string()).collect()
+    } else if table.n_states <= 10 && line.chars().all(|ch| ch.is_ascii_digit()) {
+        line.chars().map(String::from).collect()
+    } else {
+        line.split_whitespace().map(String::from).collect()
+    };
+
+    let expected = table.neighbours.positions().len() + 2;
+    if tokens.len() != expected {
+        return Err(err(&format!(
+            "expected {expected} states, found {}",
+            tokens.len()
+        )));
+    }
+    let (output, inputs) = tokens.split_last().unwrap();
+
+    // Only variables that must agree across positions, or feed the output, need a binding.
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    for token in inputs.iter() {
+        let occurrences = inputs.iter().filter(|&t| t == token).count();
+        let needed = occurrences > 1 || token == output;
+        if vars.contains_key(token) && needed && !ids.contains_key(token.as_str()) {
+            ids.insert(token, ids.len());
+        }
+    }
+
+    let slots = inputs
+        .iter()
+        .map(
+            |token| match (parse_state(token, table.n_states), vars.get(token)) {
+                (Some(state), _) => {
+                    let mut states = StateSet::default();
+                    states.insert(state);
+                    Ok(Slot { states, var: None })
+                }
+                (None, Some(&states)) => Ok(Slot {
+                    states,
+                    var: ids.get(token.as_str()).copied(),
+                }),
+                (None, None) => Err(err(&format!("unknown state or variable '{token}'"))),
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let output = match (
+        parse_state(output, table.n_states),
+        ids.get(output.as_str()),
+    ) {
+        (Some(state), _) => Output::State(state),
+        (None, Some(&var)) => Output::Var(var),
+        (None, None) => return Err(err(&format!("output '{output}' is not a bound state"))),
+    };
+
+    let (centre, around) = slots.split_first().unwrap();
+    Ok(Transition {
+        centre: centre.clone(),
+        around: around.to_vec(),
+        output,
+    })
+}
+
+// All neighbour permutations generated by a Golly symmetry such as `rotate4reflect`.
+fn symmetry_group(name: &str, k: usize) -> Result<Option<Vec<Vec<usize>>>, String> {
+    let err = || rule_error(&format!("symmetries '{name}' do not fit the neighborhood"));
+    let rotate = |steps: usize| -> Vec<usize> { (0..k).map(|i| (i + steps) % k).collect() };
+    let reflect: Vec<usize> = if k == 2 {
+        vec![1, 0]
+    } else {
+        (0..k).map(|i| (k - i) % k).collect()
+    };
+
+    let generators = match name {
+        "none" => Vec::new(),
+        "permute" => return Ok(None),
+        "reflect" | "reflect_horizontal" => vec![reflect],
+        other => {
+            let rest = other.strip_prefix("rotate").ok_or_else(err)?;
+            let (order, reflected) = match rest.strip_suffix("reflect") {
+                Some(order) => (order, true),
+                None => (rest, false),
+            };
+            let order = order
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0 && k.is_multiple_of(n))
+                .ok_or_else(err)?;
+
+            let mut generators = vec![rotate(k / order)];
+            if reflected {
+                generators.push(reflect);
+            }
+            generators
+        }
+    };
+
+    let mut group = vec![rotate(0)];
+    let mut i = 0;
+    while i < group.len() {
+        for g in generators.iter() {
+            let composed: Vec<usize> = g.iter().map(|&j| group[i][j]).collect();
+            if !group.contains(&composed) {
+                group.push(composed);
+            }
+        }
+        i += 1;
+    }
+    Ok(Some(group))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng as _;
+
+    use super::*;
+    use crate::life::{Cell, Rule};
+    use crate::rng;
+
+    const LIFE: &str = "\
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+0,1,1,1,0,0,0,0,0,1  # birth on 3
+1,1,1,0,0,0,0,0,0,1  # survival on 2
+1,1,1,1,0,0,0,0,0,1  # and 3
+1,a,b,c,d,e,f,g,h,0  # death otherwise
+";
+
+    fn neighbourhood(centre: u8, around: [u8; 8]) -> Neighbourhood {
+        Neighbourhood { centre, around }
+    }
+
+    #[test]
+    fn life_as_a_table_matches_life() {
+        let table = parse(LIFE).unwrap();
+        assert_eq!(table.n_states(), 2);
+
+        let life = Rule::default();
+        let mut rng = rng::from_seed(4);
+        for _ in 0..2000 {
+            let n = neighbourhood(
+                rng.gen_range(0..2),
+                std::array::from_fn(|_| rng.gen_range(0..2)),
+            );
+            let count = n.around.iter().filter(|&&s| s == 1).count();
+            assert_eq!(table.next(&n), life.next(Cell(n.centre), count).0, "{n:?}");
+        }
+    }
+
+    #[test]
+    fn symmetries_apply_a_transition_to_every_image_of_it() {
+        // A cell is born when its north neighbour is alive, and in whichever other directions
+        // the symmetries turn north into.
+        let born_from = |symmetries: &str| {
+            let table = parse(&format!(
+                "n_states:2\nneighborhood:Moore\nsymmetries:{symmetries}\n0,1,0,0,0,0,0,0,0,1\n"
+            ))
+            .unwrap();
+            (0..8)
+                .filter(|&p| {
+                    let mut around = [0; 8];
+                    around[p] = 1;
+                    table.next(&neighbourhood(0, around)) == 1
+                })
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(born_from("none"), [0]);
+        assert_eq!(born_from("rotate4"), [0, 2, 4, 6]);
+        assert_eq!(born_from("rotate8"), [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(born_from("rotate4reflect"), [0, 2, 4, 6]);
+        assert_eq!(born_from("permute"), [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // Reflection maps NE to NW but never to an orthogonal neighbour.
+        let table =
+            parse("n_states:2\nneighborhood:Moore\nsymmetries:reflect\n0,0,1,0,0,0,0,0,0,1\n")
+                .unwrap();
+        let born: Vec<usize> = (0..8)
+            .filter(|&p| {
+                let mut around = [0; 8];
+                around[p] = 1;
+                table.next(&neighbourhood(0, around)) == 1
+            })
+            .collect();
+        assert_eq!(born, [1, 7]);
+        assert!(
+            parse("n_states:2\nneighborhood:vonNeumann\nsymmetries:rotate8\n0,1,0,0,0,1\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn variables_bind_to_one_state_where_they_repeat() {
+        // A dead cell takes the state of its N and NE neighbours when they agree and the other
+        // six agree among themselves.
+        let table = parse(
+            "n_states:3\nneighborhood:Moore\nsymmetries:none\nvar a={1,2}\nvar any={0,a}\n\
+             0,a,a,any,any,any,any,any,any,a\n",
+        )
+        .unwrap();
+        assert_eq!(table.n_states(), 3);
+        assert_eq!(table.next(&neighbourhood(0, [2, 2, 0, 0, 0, 0, 0, 0])), 2);
+        assert_eq!(table.next(&neighbourhood(0, [1, 1, 2, 2, 2, 2, 2, 2])), 1);
+        assert_eq!(table.next(&neighbourhood(0, [1, 2, 0, 0, 0, 0, 0, 0])), 0);
+        assert_eq!(table.next(&neighbourhood(0, [2, 2, 0, 1, 0, 0, 0, 0])), 0);
+        // Cells no transition matches keep their state.
+        assert_eq!(table.next(&neighbourhood(1, [2, 2, 0, 0, 0, 0, 0, 0])), 1);
+    }
+
+    #[test]
+    fn states_must_fit_n_states() {
+        for text in [
+            "n_states:2\nneighborhood:Moore\n0,2,0,0,0,0,0,0,0,1\n",
+            "n_states:3\nneighborhood:Moore\n0,1,0,0,0,0,0,0,0,3\n",
+            "n_states:2\nneighborhood:Moore\nvar a={0,1,2}\n0,a,0,0,0,0,0,0,0,1\n",
+            "n_states:1\nneighborhood:Moore\n0,0,0,0,0,0,0,0,0,0\n",
+            "n_states:2\nneighborhood:Moore\n0,1,0,0,0,0,0,0,1\n",
+            "0,1,0,0,0,0,0,0,0,1\n",
+        ] {
+            assert!(parse(text).is_err(), "{text}");
+        }
+        // The compact form spells every state with one digit.
+        let compact = parse("n_states:2\nneighborhood:vonNeumann\n011001\n").unwrap();
+        assert_eq!(compact.next(&neighbourhood(0, [1, 0, 1, 0, 0, 0, 0, 0])), 1);
+    }
+}
@@ -0,0 +1,198 @@
+use super::Neighbourhood;
+use crate::life::rule_error;
+
+// Golly walks the tree in the order NW, NE, SW, SE, N, W, E, S (then the centre), or
+// N, W, E, S for the von Neumann neighbourhood.
+const MOORE_ORDER: [usize; 8] = [7, 1, 5, 3, 0, 6, 2, 4];
+const VON_NEUMANN_ORDER: [usize; 4] = [0, 6, 2, 4];
+
+/// A Golly `@TREE`: a decision tree with one level per neighbour, whose leaves are the next states.
+#[derive(Clone, Debug)]
+pub struct RuleTree {
+    n_states: usize,
+    order: &'static [usize],
+    nodes: Vec<Vec<usize>>,
+}
+
+impl RuleTree {
+    pub fn n_states(&self) -> usize {
+        self.n_states
+    }
+
+    pub fn next(&self, n: &Neighbourhood) -> u8 {
+        let mut node = self.nodes.len() - 1;
+        for &p in self.order.iter() {
+            node = self.nodes[node][n.around[p] as usize];
+        }
+        self.nodes[node][n.centre as usize] as u8
+    }
+}
+
+pub fn parse(text: &str) -> Result<RuleTree, String> {
+    let (mut n_states, mut n_neighbours, mut n_nodes) = (None, None, None);
+    let mut levels: Vec<usize> = Vec::new();
+    let mut nodes: Vec<Vec<usize>> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| rule_error(&format!("invalid @TREE setting '{line}'")))?;
+            match key.trim() {
+                "num_states" => n_states = Some(value),
+                "num_neighbors" => n_neighbours = Some(value),
+                "num_nodes" => n_nodes = Some(value),
+                _ => return Err(rule_error(&format!("unknown @TREE setting '{line}'"))),
+            }
+            continue;
+        }
+
+        let Some(n_states) = n_states.filter(|n| (2..=256).contains(n)) else {
+            return Err(rule_error(
+                "@TREE must set num_states (2 to 256) before its nodes",
+            ));
+        };
+
+        let err = || rule_error(&format!("invalid @TREE node '{line}'"));
+        let values = line
+            .split_whitespace()
+            .map(str::parse::<usize>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| err())?;
+        let Some((&level, children)) = values.split_first() else {
+            return Err(err());
+        };
+        if children.len() != n_states {
+            return Err(err());
+        }
+
+        let valid = match level {
+            1 => children.iter().all(|&s| s < n_states),
+            0 => false,
+            _ => children
+                .iter()
+                .all(|&c| c < nodes.len() && levels[c] == level - 1),
+        };
+        if !valid {
+            return Err(err());
+        }
+
+        levels.push(level);
+        nodes.push(children.to_vec());
+    }
+
+    let order: &'static [usize] = match n_neighbours {
+        Some(8) => &MOORE_ORDER,
+        Some(4) => &VON_NEUMANN_ORDER,
+        _ => return Err(rule_error("@TREE num_neighbors must be 4 or 8")),
+    };
+    if n_nodes.is_some_and(|n| n != nodes.len()) {
+        return Err(rule_error(&format!(
+            "@TREE declares {} nodes but has {}",
+            n_nodes.unwrap(),
+            nodes.len()
+        )));
+    }
+    if levels.last() != Some(&(order.len() + 1)) {
+        return Err(rule_error("@TREE root node is not at the top level"));
+    }
+
+    Ok(RuleTree {
+        n_states: n_states.unwrap(),
+        order,
+        nodes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng as _;
+
+    use super::*;
+    use crate::life::{Cell, Rule};
+    use crate::rng;
+
+    // A two-state outer-totalistic rule as a tree, with one node per level and count so far.
+    fn totalistic(neighbours: usize, next: impl Fn(u8, usize) -> u8) -> String {
+        let mut lines = Vec::new();
+        let mut index = std::collections::HashMap::new();
+        for level in 1..=neighbours + 1 {
+            for count in 0..=neighbours + 1 - level {
+                lines.push(if level == 1 {
+                    format!("1 {} {}", next(0, count), next(1, count))
+                } else {
+                    let child = |c: usize| index[&(level - 1, c)];
+                    format!("{level} {} {}", child(count), child(count + 1))
+                });
+                index.insert((level, count), lines.len() - 1);
+            }
+        }
+        format!(
+            "num_states=2\nnum_neighbors={neighbours}\nnum_nodes={}\n{}\n",
+            lines.len(),
+            lines.join("\n")
+        )
+    }
+
+    #[test]
+    fn life_as_a_tree_matches_life() {
+        let life = Rule::default();
+        let tree = parse(&totalistic(8, |state, count| {
+            life.next(Cell(state), count).0
+        }))
+        .unwrap();
+        assert_eq!(tree.n_states(), 2);
+
+        let mut rng = rng::from_seed(5);
+        for _ in 0..2000 {
+            let n = Neighbourhood {
+                centre: rng.gen_range(0..2),
+                around: std::array::from_fn(|_| rng.gen_range(0..2)),
+            };
+            let count = n.around.iter().filter(|&&s| s == 1).count();
+            assert_eq!(tree.next(&n), life.next(Cell(n.centre), count).0, "{n:?}");
+        }
+    }
+
+    #[test]
+    fn von_neumann_trees_only_see_orthogonal_neighbours() {
+        // Born with exactly one live orthogonal neighbour, never survives.
+        let tree = parse(&totalistic(4, |state, count| {
+            (state == 0 && count == 1) as u8
+        }))
+        .unwrap();
+        for p in 0..8 {
+            let mut around = [0; 8];
+            around[p] = 1;
+            let n = Neighbourhood { centre: 0, around };
+            assert_eq!(tree.next(&n), (p % 2 == 0) as u8, "{p}");
+        }
+    }
+
+    #[test]
+    fn malformed_trees_are_refused() {
+        let life = totalistic(8, |state, count| {
+            (count == 3 || state == 1 && count == 2) as u8
+        });
+        for broken in [
+            life.replace("num_nodes=45", "num_nodes=44"),
+            life.replace("num_neighbors=8", "num_neighbors=6"),
+            life.replace("num_states=2", "num_states=1"),
+            life.replace("1 0 0\n", "1 0 2\n"),
+            life.lines()
+                .filter(|line| !line.starts_with("9 "))
+                .collect::<Vec<_>>()
+                .join("\n")
+                .replace("num_nodes=45", "num_nodes=44"),
+        ] {
+            assert!(parse(&broken).is_err(), "{broken}");
+        }
+        assert!(parse(&life).is_ok());
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Block characters of a sparkline, lowest first.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub fn history_error(err: &str) -> String {
+    format!("[HISTORY ERROR]: {err}.")
+}
+
+/// One generation's worth of statistics, written as a CSV row.
+pub trait Sample: Copy {
+    /// The CSV header, without a line break.
+    const COLUMNS: &'static str;
+
+    fn row(&self) -> String;
+
+    /// The value plotted in sparklines and charts.
+    fn level(&self) -> f64;
+}
+
+/// Population of a Life board after a generation, with the births and deaths that led there.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LifeSample {
+    pub generation: u64,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+impl Sample for LifeSample {
+    const COLUMNS: &'static str = "generation,population,births,deaths";
+
+    fn row(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.generation, self.population, self.births, self.deaths
+        )
+    }
+
+    fn level(&self) -> f64 {
+        self.population as f64
+    }
+}
+
+/// Total and mean value of a SmoothLife field after a step.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FieldSample {
+    pub step: u64,
+    pub mass: f64,
+    pub mean: f64,
+}
+
+impl FieldSample {
+    pub fn of(step: u64, cells: &[Vec<f32>]) -> Self {
+        let mass: f64 = cells.iter().flatten().map(|&v| v as f64).sum();
+        let area = cells.len() * cells.first().map_or(0, Vec::len);
+        Self {
+            step,
+            mass,
+            mean: mass / area.max(1) as f64,
+        }
+    }
+}
+
+impl Sample for FieldSample {
+    const COLUMNS: &'static str = "step,mass,mean";
+
+    fn row(&self) -> String {
+        format!("{},{:.3},{:.6}", self.step, self.mass, self.mean)
+    }
+
+    fn level(&self) -> f64 {
+        self.mass
+    }
+}
+
+/// Every sample of a run, oldest first.
+#[derive(Clone, Debug)]
+pub struct History<S: Sample> {
+    samples: Vec<S>,
+}
+
+impl<S: Sample> Default for History<S> {
+    fn default() -> Self {
+        Self {
+            samples: Vec::new(),
+        }
+    }
+}
+
+impl<S: Sample> History<S> {
+    pub fn push(&mut self, sample: S) {
+        self.samples.push(sample);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn samples(&self) -> &[S] {
+        &self.samples
+    }
+
+    /// The last `count` samples, or all of them if there are fewer.
+    pub fn recent(&self, count: usize) -> &[S] {
+        &self.samples[self.samples.len().saturating_sub(count)..]
+    }
+
+    /// The last `width` levels as block characters, scaled between their own lowest and highest.
+    pub fn sparkline(&self, width: usize) -> String {
+        let recent = self.recent(width);
+        let (low, high) = bounds(recent);
+        recent
+            .iter()
+            .map(|sample| {
+                let t = match high - low {
+                    range if range > 0.0 => (sample.level() - low) / range,
+                    _ => 0.5,
+                };
+                SPARKS[(t * (SPARKS.len() - 1) as f64).round() as usize]
+            })
+            .collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", S::COLUMNS);
+        for sample in self.samples.iter() {
+            writeln!(csv, "{}", sample.row()).unwrap();
+        }
+        csv
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_csv())
+            .map_err(|err| history_error(&format!("cannot write {}: {err}", path.display())))
+    }
+}
+
+/// Lowest and highest level among `samples`, `(0, 0)` when there are none.
+pub fn bounds<S: Sample>(samples: &[S]) -> (f64, f64) {
+    samples
+        .iter()
+        .map(Sample::level)
+        .fold(None, |acc: Option<(f64, f64)>, level| match acc {
+            Some((low, high)) => Some((low.min(level), high.max(level))),
+            None => Some((level, level)),
+        })
+        .unwrap_or((0.0, 0.0))
+}
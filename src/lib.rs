@@ -0,0 +1,18 @@
+pub mod census;
+pub mod cycle;
+pub mod evolution;
+pub mod golly;
+pub mod history;
+pub mod life;
+pub mod pattern;
+pub mod periodic;
+pub mod predecessor;
+pub mod render;
+pub mod rng;
+pub mod sand;
+pub mod sat;
+pub mod script;
+pub mod search;
+pub mod session;
+pub mod smoothlife;
+pub mod soup;
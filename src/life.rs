@@ -0,0 +1,616 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use rand::seq::SliceRandom;
+use rand::Rng as _;
+
+use crate::golly::{GollyRule, Neighbourhood};
+use crate::rng::{self, Rng};
+use crate::script::ScriptRule;
+
+type Rows = usize;
+type Cols = usize;
+
+/// A cell state; `0` is dead. Life-like rules only use `DEAD` and `ALIVE`, Golly rules up to 255 states.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Cell(pub u8);
+
+impl Cell {
+    pub const DEAD: Cell = Cell(0);
+    pub const ALIVE: Cell = Cell(1);
+
+    pub fn is_alive(&self) -> bool {
+        self.0 != 0
+    }
+
+    pub fn as_color_hex(&self) -> u32 {
+        if self.is_alive() {
+            0xFFFFFFFF
+        } else {
+            0x000000FF
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        *self = if self.is_alive() {
+            Cell::DEAD
+        } else {
+            Cell::ALIVE
+        };
+    }
+
+    /// Steps to the next of `n_states` states, wrapping back to dead.
+    pub fn cycle(&mut self, n_states: usize) {
+        self.0 = ((self.0 as usize + 1) % n_states.max(2)) as u8;
+    }
+}
+
+pub fn rule_error(err: &str) -> String {
+    format!("[RULE ERROR]: {err}.")
+}
+
+/// Life-like outer-totalistic rule with optional per-transition probabilities.
+///
+/// A birth (`Dead -> Alive`) happens with `p_birth`, a death (`Alive -> Dead`) with `p_death`;
+/// otherwise the cell keeps its state. Both default to `1.0`, i.e. the deterministic rule.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rule {
+    birth: [bool; 9],
+    survival: [bool; 9],
+    p_birth: f64,
+    p_death: f64,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::parse("B3/S23").unwrap()
+    }
+}
+
+impl Rule {
+    /// Parses `B3/S23` notation, as well as the older `23/3` (survival/birth) form.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let rule = rule.trim();
+        let (b, s) = rule
+            .split_once('/')
+            .ok_or_else(|| rule_error(&format!("'{rule}' is not in B/S notation")))?;
+
+        let (b, s) = match (b.chars().next(), s.chars().next()) {
+            (Some('B' | 'b'), Some('S' | 's')) => (&b[1..], &s[1..]),
+            (Some('S' | 's'), Some('B' | 'b')) => (&s[1..], &b[1..]),
+            _ if !b.starts_with(char::is_alphabetic) => (s, b),
+            _ => return Err(rule_error(&format!("'{rule}' is not in B/S notation"))),
+        };
+
+        Ok(Self {
+            birth: Self::parse_counts(b)?,
+            survival: Self::parse_counts(s)?,
+            p_birth: 1.0,
+            p_death: 1.0,
+        })
+    }
+
+    /// The deterministic rule that gives birth on the counts set in `birth` and keeps cells
+    /// alive on those set in `survival`.
+    pub fn from_tables(birth: [bool; 9], survival: [bool; 9]) -> Self {
+        Self {
+            birth,
+            survival,
+            p_birth: 1.0,
+            p_death: 1.0,
+        }
+    }
+
+    pub fn birth(&self) -> [bool; 9] {
+        self.birth
+    }
+
+    pub fn survival(&self) -> [bool; 9] {
+        self.survival
+    }
+
+    pub fn with_probabilities(mut self, p_birth: f64, p_death: f64) -> Result<Self, String> {
+        for p in [p_birth, p_death] {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(rule_error(&format!("probability {p} is not in [0, 1]")));
+            }
+        }
+
+        self.p_birth = p_birth;
+        self.p_death = p_death;
+        Ok(self)
+    }
+
+    pub fn p_birth(&self) -> f64 {
+        self.p_birth
+    }
+
+    pub fn p_death(&self) -> f64 {
+        self.p_death
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.p_birth == 1.0 && self.p_death == 1.0
+    }
+
+    /// The deterministic transition for a cell with `n` live neighbours.
+    pub fn next(&self, cell: Cell, n: usize) -> Cell {
+        let table = if cell.is_alive() {
+            &self.survival
+        } else {
+            &self.birth
+        };
+
+        if table[n] {
+            Cell::ALIVE
+        } else {
+            Cell::DEAD
+        }
+    }
+
+    fn parse_counts(counts: &str) -> Result<[bool; 9], String> {
+        let mut table = [false; 9];
+        for ch in counts.chars() {
+            match ch.to_digit(10) {
+                Some(n) if n <= 8 => table[n as usize] = true,
+                _ => return Err(rule_error(&format!("invalid neighbour count '{ch}'"))),
+            }
+        }
+        Ok(table)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |table: &[bool; 9]| -> String {
+            (0..9)
+                .filter(|&n| table[n])
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UpdateScheme {
+    /// Every cell is updated at once from the previous generation.
+    Synchronous,
+    /// Cells are updated one at a time, in place, in a fresh random order every generation.
+    RandomSequential,
+    /// Every cell is updated synchronously with probability `alpha`, otherwise it is kept.
+    Alpha(f64),
+    /// The two checkerboard colours are updated one after the other.
+    Checkerboard,
+}
+
+impl UpdateScheme {
+    /// Parses `sync`, `sequential`, `checkerboard` or `alpha:<p>`.
+    pub fn parse(scheme: &str) -> Result<Self, String> {
+        match scheme.trim() {
+            "sync" => Ok(UpdateScheme::Synchronous),
+            "sequential" => Ok(UpdateScheme::RandomSequential),
+            "checkerboard" => Ok(UpdateScheme::Checkerboard),
+            other => {
+                let alpha = other
+                    .strip_prefix("alpha:")
+                    .and_then(|a| a.parse::<f64>().ok())
+                    .filter(|a| (0.0..=1.0).contains(a))
+                    .ok_or_else(|| rule_error(&format!("unknown update scheme '{other}'")))?;
+                Ok(UpdateScheme::Alpha(alpha))
+            }
+        }
+    }
+}
+
+impl fmt::Display for UpdateScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateScheme::Synchronous => write!(f, "sync"),
+            UpdateScheme::RandomSequential => write!(f, "sequential"),
+            UpdateScheme::Alpha(alpha) => write!(f, "alpha:{alpha}"),
+            UpdateScheme::Checkerboard => write!(f, "checkerboard"),
+        }
+    }
+}
+
+/// The transition function an [`Engine`] runs.
+#[derive(Clone, Debug)]
+pub enum Automaton {
+    LifeLike(Rule),
+    Golly(GollyRule),
+    Script(Box<ScriptRule>),
+}
+
+impl Automaton {
+    pub fn n_states(&self) -> usize {
+        match self {
+            Automaton::LifeLike(_) => 2,
+            Automaton::Golly(rule) => rule.n_states(),
+            Automaton::Script(rule) => rule.n_states(),
+        }
+    }
+
+    pub fn color_hex(&self, cell: Cell) -> u32 {
+        match self {
+            Automaton::LifeLike(_) => cell.as_color_hex(),
+            Automaton::Golly(rule) => rule.color_hex(cell.0),
+            Automaton::Script(rule) => rule.color_hex(cell.0),
+        }
+    }
+
+    /// The first error a scripted rule raised while running.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Automaton::Script(rule) => rule.error(),
+            _ => None,
+        }
+    }
+
+    /// Resolves a rule name as found in a pattern header: B/S notation, or else a Golly
+    /// `<name>.rule` file in `dir` or the working directory. A bounded-grid suffix is dropped.
+    pub fn resolve(name: &str, dir: &Path) -> Result<Self, String> {
+        let (name, _) = split_bounded_grid(name);
+        if let Ok(rule) = Rule::parse(name) {
+            return Ok(rule.into());
+        }
+
+        let file = format!("{name}.rule");
+        [dir.join(&file), PathBuf::from(&file)]
+            .iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                rule_error(&format!(
+                    "'{name}' is not B/S notation and {file} was not found"
+                ))
+            })
+            .and_then(GollyRule::load)
+            .map(Automaton::from)
+    }
+}
+
+/// Splits a Golly bounded-grid suffix such as `:P40,40` or `:T20,20` off a rule name. Boards here
+/// always wrap around, so the grid is not used.
+pub fn split_bounded_grid(name: &str) -> (&str, Option<&str>) {
+    match name.split_once(':') {
+        Some((rule, grid)) => (rule.trim(), Some(grid.trim())),
+        None => (name.trim(), None),
+    }
+}
+
+impl From<Rule> for Automaton {
+    fn from(rule: Rule) -> Self {
+        Automaton::LifeLike(rule)
+    }
+}
+
+impl From<GollyRule> for Automaton {
+    fn from(rule: GollyRule) -> Self {
+        Automaton::Golly(rule)
+    }
+}
+
+impl From<ScriptRule> for Automaton {
+    fn from(rule: ScriptRule) -> Self {
+        Automaton::Script(Box::new(rule))
+    }
+}
+
+impl fmt::Display for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Automaton::LifeLike(rule) => write!(f, "{rule}"),
+            Automaton::Golly(rule) => write!(f, "{rule}"),
+            Automaton::Script(rule) => write!(f, "{rule}"),
+        }
+    }
+}
+
+pub struct Engine {
+    automaton: Automaton,
+    scheme: UpdateScheme,
+    rng: Rng,
+    order: Vec<(Rows, Cols)>,
+}
+
+impl Engine {
+    pub fn new<A: Into<Automaton>>(automaton: A, scheme: UpdateScheme, seed: u64) -> Self {
+        Self {
+            automaton: automaton.into(),
+            scheme,
+            rng: rng::from_seed_stream(seed, rng::ENGINE_STREAM),
+            order: Vec::new(),
+        }
+    }
+
+    pub fn automaton(&self) -> &Automaton {
+        &self.automaton
+    }
+
+    pub fn scheme(&self) -> UpdateScheme {
+        self.scheme
+    }
+
+    /// Whether every generation follows from the previous one alone, without random draws.
+    pub fn is_deterministic(&self) -> bool {
+        let rule = match &self.automaton {
+            Automaton::LifeLike(rule) => rule.is_deterministic(),
+            Automaton::Golly(_) | Automaton::Script(_) => true,
+        };
+        rule && matches!(
+            self.scheme,
+            UpdateScheme::Synchronous | UpdateScheme::Checkerboard
+        )
+    }
+
+    /// Whether a dead cell with no live neighbours stays dead, so that an empty board stays empty.
+    pub fn keeps_empty(&mut self) -> bool {
+        let empty = vec![vec![Cell::DEAD; 3]; 3];
+        !self.transition(&empty, 1, 1).is_alive()
+    }
+
+    /// Position in the engine's random stream, so that a run can be resumed exactly.
+    pub fn rng_position(&self) -> u128 {
+        self.rng.get_word_pos()
+    }
+
+    pub fn set_rng_position(&mut self, position: u128) {
+        self.rng.set_word_pos(position);
+    }
+
+    pub fn step(&mut self, cells: &mut [Vec<Cell>]) {
+        match self.scheme {
+            UpdateScheme::Synchronous => self.step_synchronous(cells, |_, _| true),
+            UpdateScheme::Alpha(alpha) => {
+                let mut picked = vec![vec![false; cells[0].len()]; cells.len()];
+                for item in picked.iter_mut().flatten() {
+                    *item = self.rng.gen_bool(alpha);
+                }
+                self.step_synchronous(cells, |r, c| picked[r][c]);
+            }
+            UpdateScheme::Checkerboard => {
+                self.step_synchronous(cells, |r, c| (r + c) % 2 == 0);
+                self.step_synchronous(cells, |r, c| (r + c) % 2 == 1);
+            }
+            UpdateScheme::RandomSequential => self.step_sequential(cells),
+        }
+    }
+
+    fn step_synchronous<F>(&mut self, cells: &mut [Vec<Cell>], selected: F)
+    where
+        F: Fn(Rows, Cols) -> bool,
+    {
+        let old = cells.to_vec();
+
+        for (ir, row) in cells.iter_mut().enumerate() {
+            for (ic, item) in row.iter_mut().enumerate() {
+                if selected(ir, ic) {
+                    *item = self.transition(&old, ir, ic);
+                }
+            }
+        }
+    }
+
+    fn step_sequential(&mut self, cells: &mut [Vec<Cell>]) {
+        let cols = cells[0].len();
+        self.order.resize(cells.len() * cols, (0, 0));
+
+        // Shuffle from row-major order every time, so the order only depends on the random stream.
+        let mut order = std::mem::take(&mut self.order);
+        for (i, item) in order.iter_mut().enumerate() {
+            *item = (i / cols, i % cols);
+        }
+        order.shuffle(&mut self.rng);
+        for &(r, c) in order.iter() {
+            cells[r][c] = self.transition(cells, r, c);
+        }
+        self.order = order;
+    }
+
+    fn transition(&mut self, cells: &[Vec<Cell>], row: Rows, col: Cols) -> Cell {
+        let cell = cells[row][col];
+        match &mut self.automaton {
+            Automaton::LifeLike(rule) => match (cell, rule.next(cell, count_n(cells, row, col))) {
+                (Cell::DEAD, Cell::ALIVE) if !self.rng.gen_bool(rule.p_birth) => Cell::DEAD,
+                (Cell::ALIVE, Cell::DEAD) if !self.rng.gen_bool(rule.p_death) => Cell::ALIVE,
+                (_, next) => next,
+            },
+            Automaton::Golly(rule) => Cell(rule.next(&Neighbourhood::of(cells, row, col))),
+            Automaton::Script(rule) => Cell(rule.next(&Neighbourhood::of(cells, row, col))),
+        }
+    }
+}
+
+/// Number of live cells in the Moore neighbourhood, wrapping around the board edges.
+pub fn count_n(cells: &[Vec<Cell>], row: Rows, col: Cols) -> usize {
+    let (rows, cols) = (cells.len() as i32, cells[0].len() as i32);
+    let mut n: usize = 0;
+
+    for dr in 0..=2 {
+        for dc in 0..=2 {
+            if dr != 1 || dc != 1 {
+                let r = emod((row + dr) as i32 - 1, rows);
+                let c = emod((col + dc) as i32 - 1, cols);
+                if cells[r as usize][c as usize].is_alive() {
+                    n += 1;
+                }
+            }
+        }
+    }
+
+    n
+}
+
+pub fn emod(a: i32, b: i32) -> i32 {
+    (a % b + b) % b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::rle;
+
+    fn soup(seed: u64, rows: Rows, cols: Cols) -> Vec<Vec<Cell>> {
+        let mut rng = rng::from_seed(seed);
+        (0..rows)
+            .map(|_| (0..cols).map(|_| Cell(rng.gen_bool(0.4) as u8)).collect())
+            .collect()
+    }
+
+    fn run(engine: &mut Engine, cells: &[Vec<Cell>], steps: usize) -> Vec<Vec<Cell>> {
+        let mut cells = cells.to_vec();
+        for _ in 0..steps {
+            engine.step(&mut cells);
+        }
+        cells
+    }
+
+    fn board(rows: &[&str]) -> Vec<Vec<Cell>> {
+        rows.iter()
+            .map(|row| row.chars().map(|ch| Cell((ch == 'o') as u8)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn rules_parse_and_display_as_b_s_notation() {
+        for (text, shown) in [
+            ("B3/S23", "B3/S23"),
+            ("b36/s23", "B36/S23"),
+            ("S23/B3", "B3/S23"),
+            ("23/3", "B3/S23"),
+            ("B2/S", "B2/S"),
+            ("B/S012345678", "B/S012345678"),
+            (" B0/S8 ", "B0/S8"),
+        ] {
+            let rule = Rule::parse(text).unwrap();
+            assert_eq!(rule.to_string(), shown);
+            assert_eq!(Rule::parse(shown).unwrap(), rule);
+        }
+        for bad in ["", "B3", "B3/S29", "X3/S23", "B3/Sx"] {
+            assert!(Rule::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn probabilities_must_lie_in_the_unit_interval() {
+        let rule = Rule::default().with_probabilities(0.25, 1.0).unwrap();
+        assert_eq!((rule.p_birth(), rule.p_death()), (0.25, 1.0));
+        assert!(!rule.is_deterministic());
+        assert!(Rule::default()
+            .with_probabilities(1.0, 1.0)
+            .unwrap()
+            .is_deterministic());
+
+        for (p_birth, p_death) in [
+            (-0.1, 1.0),
+            (1.0, 1.5),
+            (f64::NAN, 1.0),
+            (1.0, f64::INFINITY),
+        ] {
+            assert!(Rule::default()
+                .with_probabilities(p_birth, p_death)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn update_schemes_parse_and_display() {
+        for text in ["sync", "sequential", "checkerboard", "alpha:0.5", "alpha:1"] {
+            assert_eq!(UpdateScheme::parse(text).unwrap().to_string(), text);
+        }
+        for bad in ["alpha:1.5", "alpha:", "async"] {
+            assert!(UpdateScheme::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn checkerboard_updates_the_second_colour_from_the_first() {
+        let glider = board(&[".....", "..o..", "...o.", ".ooo.", "....."]);
+        let mut engine = Engine::new(Rule::default(), UpdateScheme::Checkerboard, 0);
+        let after = board(&[".....", ".....", "...o.", "..oo.", "..oo."]);
+        assert_eq!(run(&mut engine, &glider, 1), after);
+
+        let mut engine = Engine::new(Rule::default(), UpdateScheme::Synchronous, 0);
+        let after = board(&[".....", ".....", ".o.o.", "..oo.", "..o.."]);
+        assert_eq!(run(&mut engine, &glider, 1), after);
+    }
+
+    #[test]
+    fn alpha_updates_all_or_no_cells_at_its_bounds() {
+        let cells = soup(1, 12, 12);
+        let mut sync = Engine::new(Rule::default(), UpdateScheme::Synchronous, 0);
+        let mut all = Engine::new(Rule::default(), UpdateScheme::Alpha(1.0), 0);
+        let mut none = Engine::new(Rule::default(), UpdateScheme::Alpha(0.0), 0);
+        assert_eq!(run(&mut all, &cells, 5), run(&mut sync, &cells, 5));
+        assert_eq!(run(&mut none, &cells, 5), cells);
+    }
+
+    #[test]
+    fn random_sequential_updates_see_the_cells_already_updated() {
+        // Under B1/S a lone cell gives birth around it, and each birth lets the next cell in
+        // line be born too, so a sequential step fills far more than the 8 neighbours.
+        let mut cells = vec![vec![Cell::DEAD; 9]; 9];
+        cells[4][4] = Cell::ALIVE;
+        let rule = Rule::parse("B1/S").unwrap();
+        let mut engine = Engine::new(rule.clone(), UpdateScheme::RandomSequential, 5);
+        let live = run(&mut engine, &cells, 1)
+            .iter()
+            .flatten()
+            .filter(|c| c.is_alive())
+            .count();
+        assert!(live > 8, "{live}");
+        let mut engine = Engine::new(rule, UpdateScheme::Synchronous, 5);
+        let live = run(&mut engine, &cells, 1)
+            .iter()
+            .flatten()
+            .filter(|c| c.is_alive())
+            .count();
+        assert_eq!(live, 8);
+    }
+
+    #[test]
+    fn every_scheme_is_reproducible_from_its_seed() {
+        let rule = Rule::default().with_probabilities(0.7, 0.9).unwrap();
+        let cells = soup(2, 16, 16);
+        for scheme in [
+            UpdateScheme::Synchronous,
+            UpdateScheme::RandomSequential,
+            UpdateScheme::Alpha(0.5),
+            UpdateScheme::Checkerboard,
+        ] {
+            let mut first = Engine::new(rule.clone(), scheme, 7);
+            let mut second = Engine::new(rule.clone(), scheme, 7);
+            let mut other = Engine::new(rule.clone(), scheme, 8);
+            let expected = run(&mut first, &cells, 10);
+            assert_eq!(run(&mut second, &cells, 10), expected, "{scheme}");
+            assert_ne!(run(&mut other, &cells, 10), expected, "{scheme}");
+
+            // A run resumed from its rng position carries on exactly.
+            let mut engine = Engine::new(rule.clone(), scheme, 7);
+            let halfway = run(&mut engine, &cells, 5);
+            let mut resumed = Engine::new(rule.clone(), scheme, 7);
+            resumed.set_rng_position(engine.rng_position());
+            assert_eq!(run(&mut resumed, &halfway, 5), expected, "{scheme}");
+        }
+    }
+
+    #[test]
+    fn header_rules_drop_their_bounded_grid() {
+        assert_eq!(
+            split_bounded_grid("B3/S23:P40,40"),
+            ("B3/S23", Some("P40,40"))
+        );
+        assert_eq!(split_bounded_grid("B36/S23"), ("B36/S23", None));
+
+        for header in [
+            "rule = B3/S23:P40,40",
+            "rule = B3/S23:T20,20",
+            "rule = B3/S23",
+        ] {
+            let pattern = rle::parse(&format!("x = 3, y = 1, {header}\n3o!\n")).unwrap();
+            let rule = pattern.rule.as_deref().unwrap();
+            let automaton = Automaton::resolve(rule, Path::new(".")).unwrap();
+            assert!(matches!(automaton, Automaton::LifeLike(rule) if rule == Rule::default()));
+        }
+    }
+}
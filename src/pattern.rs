@@ -0,0 +1,252 @@
+use std::fs;
+use std::path::Path;
+
+use crate::life::Cell;
+
+pub mod library;
+pub mod lif;
+pub mod macrocell;
+pub mod plaintext;
+pub mod rle;
+
+type Rows = usize;
+type Cols = usize;
+
+// Dense patterns are decoded onto a `Vec<Vec<u8>>`; refuse anything absurdly large up front.
+const MAX_CELLS: usize = 1 << 26;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+    Macrocell,
+}
+
+impl Format {
+    /// Format implied by a file extension. `.lif`/`.life` are written as Life 1.06.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "rle" => Some(Format::Rle),
+            "cells" => Some(Format::Plaintext),
+            "lif" | "life" => Some(Format::Life106),
+            "mc" => Some(Format::Macrocell),
+            _ => None,
+        }
+    }
+
+    /// Format recognised from file contents, if it is unambiguous.
+    pub fn detect(text: &str) -> Option<Self> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let first = lines.clone().next()?;
+
+        if first.starts_with(lif::LIFE_105_HEADER) {
+            return Some(Format::Life105);
+        }
+        if first.starts_with(lif::LIFE_106_HEADER) {
+            return Some(Format::Life106);
+        }
+        if first.starts_with(macrocell::HEADER) {
+            return Some(Format::Macrocell);
+        }
+        if first.starts_with('!') {
+            return Some(Format::Plaintext);
+        }
+
+        match lines.find(|l| !l.starts_with('#'))? {
+            l if l.starts_with('x') && l.contains('=') => Some(Format::Rle),
+            l if l.chars().all(|ch| matches!(ch, '.' | 'O')) => Some(Format::Plaintext),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Result<Pattern, String> {
+        match self {
+            Format::Rle => rle::parse(text),
+            Format::Plaintext => plaintext::parse(text),
+            Format::Life105 => lif::parse_105(text),
+            Format::Life106 => lif::parse_106(text),
+            Format::Macrocell => macrocell::parse(text),
+        }
+    }
+
+    pub fn write(&self, pattern: &Pattern) -> String {
+        match self {
+            Format::Rle => rle::write(pattern),
+            Format::Plaintext => plaintext::write(pattern),
+            Format::Life105 => lif::write_105(pattern),
+            Format::Life106 => lif::write_106(pattern),
+            Format::Macrocell => macrocell::write(pattern),
+        }
+    }
+}
+
+pub fn pattern_error(err: &str) -> String {
+    format!("[PATTERN ERROR]: {err}.")
+}
+
+/// A rectangular pattern of cell states, independent of any file format.
+///
+/// State `0` is dead; binary rules only use `1` for alive, multi-state rules use `1..=255`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pattern {
+    pub cells: Vec<Vec<u8>>,
+    pub rule: Option<String>,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub comments: Vec<String>,
+}
+
+impl Pattern {
+    pub fn new(rows: Rows, cols: Cols) -> Self {
+        Self {
+            cells: vec![vec![0; cols]; rows],
+            ..Default::default()
+        }
+    }
+
+    /// Builds a pattern from a board, cropped to the bounding box of its live cells.
+    pub fn from_cells(cells: &[Vec<Cell>]) -> Self {
+        let states: Vec<Vec<u8>> = cells
+            .iter()
+            .map(|row| row.iter().map(|c| c.0).collect())
+            .collect();
+
+        Self {
+            cells: states,
+            ..Default::default()
+        }
+        .cropped()
+    }
+
+    pub fn rows(&self) -> Rows {
+        self.cells.len()
+    }
+
+    pub fn cols(&self) -> Cols {
+        self.cells.first().map_or(0, |row| row.len())
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().flatten().filter(|&&s| s != 0).count()
+    }
+
+    pub fn max_state(&self) -> u8 {
+        self.cells.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// Trims empty rows and columns around the live cells.
+    pub fn cropped(mut self) -> Self {
+        let live = |r: &Vec<u8>| r.iter().any(|&s| s != 0);
+        let (Some(r0), Some(r1)) = (
+            self.cells.iter().position(live),
+            self.cells.iter().rposition(live),
+        ) else {
+            self.cells.clear();
+            return self;
+        };
+
+        let c0 = self.cells[r0..=r1]
+            .iter()
+            .filter_map(|row| row.iter().position(|&s| s != 0))
+            .min()
+            .unwrap();
+        let c1 = self.cells[r0..=r1]
+            .iter()
+            .filter_map(|row| row.iter().rposition(|&s| s != 0))
+            .max()
+            .unwrap();
+
+        self.cells = self.cells[r0..=r1]
+            .iter()
+            .map(|row| row[c0..=c1].to_vec())
+            .collect();
+        self
+    }
+
+    /// The pattern turned a quarter clockwise.
+    pub fn rotated(&self) -> Self {
+        let (rows, cols) = (self.rows(), self.cols());
+        Self {
+            cells: (0..cols)
+                .map(|c| (0..rows).rev().map(|r| self.cells[r][c]).collect())
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// The pattern mirrored left to right.
+    pub fn reflected(&self) -> Self {
+        Self {
+            cells: self
+                .cells
+                .iter()
+                .map(|row| row.iter().rev().copied().collect())
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Fails with a readable message if the pattern does not fit in a `rows x cols` board.
+    pub fn check_fits(&self, rows: Rows, cols: Cols) -> Result<(), String> {
+        if self.rows() > rows || self.cols() > cols {
+            return Err(pattern_error(&format!(
+                "pattern is {}x{} (cols x rows) but the board is only {}x{}",
+                self.cols(),
+                self.rows(),
+                cols,
+                rows
+            )));
+        }
+        Ok(())
+    }
+
+    /// Writes the pattern into `cells` with its top-left corner at `(row, col)`.
+    pub fn stamp(&self, cells: &mut [Vec<Cell>], row: Rows, col: Cols) -> Result<(), String> {
+        let (rows, cols) = (cells.len(), cells[0].len());
+        self.check_fits(rows, cols)?;
+        if row + self.rows() > rows || col + self.cols() > cols {
+            return Err(pattern_error(&format!(
+                "pattern of {}x{} (cols x rows) placed at ({col}, {row}) runs off the {cols}x{rows} board",
+                self.cols(),
+                self.rows()
+            )));
+        }
+
+        for (r, states) in self.cells.iter().enumerate() {
+            for (c, &state) in states.iter().enumerate() {
+                cells[row + r][col + c] = Cell(state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Top-left position that centres the pattern on a `rows x cols` board.
+    pub fn centred_in(&self, rows: Rows, cols: Cols) -> (Rows, Cols) {
+        (
+            rows.saturating_sub(self.rows()) / 2,
+            cols.saturating_sub(self.cols()) / 2,
+        )
+    }
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Pattern, String> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|err| pattern_error(&format!("cannot read {}: {err}", path.display())))?;
+
+    let format = Format::detect(&text)
+        .or_else(|| Format::from_path(path))
+        .ok_or_else(|| pattern_error(&format!("unknown pattern format of {}", path.display())))?;
+    format.parse(&text)
+}
+
+/// Saves in the format implied by the extension, RLE when there is none.
+pub fn save<P: AsRef<Path>>(path: P, pattern: &Pattern) -> Result<(), String> {
+    let path = path.as_ref();
+    let format = Format::from_path(path).unwrap_or(Format::Rle);
+    fs::write(path, format.write(pattern))
+        .map_err(|err| pattern_error(&format!("cannot write {}: {err}", path.display())))
+}
@@ -0,0 +1,202 @@
+use super::{rle, Pattern};
+
+/// What a library pattern does when left to run under B3/S23.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Gun,
+    Methuselah,
+}
+
+impl Kind {
+    pub fn name(&self) -> &str {
+        match self {
+            Kind::StillLife => "still life",
+            Kind::Oscillator => "oscillator",
+            Kind::Spaceship => "spaceship",
+            Kind::Gun => "gun",
+            Kind::Methuselah => "methuselah",
+        }
+    }
+}
+
+/// A built-in pattern, kept as an RLE body.
+pub struct Entry {
+    pub name: &'static str,
+    pub kind: Kind,
+    rle: &'static str,
+}
+
+impl Entry {
+    pub fn pattern(&self) -> Pattern {
+        let mut pattern = rle::parse(self.rle).expect("built-in patterns are valid RLE");
+        pattern.name = Some(self.name.to_string());
+        pattern
+    }
+}
+
+/// Common Conway's Life objects, grouped by kind.
+pub const LIBRARY: [Entry; 19] = [
+    Entry {
+        name: "Block",
+        kind: Kind::StillLife,
+        rle: "x = 2, y = 2\n2o$2o!",
+    },
+    Entry {
+        name: "Beehive",
+        kind: Kind::StillLife,
+        rle: "x = 4, y = 3\nb2o$o2bo$b2o!",
+    },
+    Entry {
+        name: "Loaf",
+        kind: Kind::StillLife,
+        rle: "x = 4, y = 4\nb2o$o2bo$bobo$2bo!",
+    },
+    Entry {
+        name: "Boat",
+        kind: Kind::StillLife,
+        rle: "x = 3, y = 3\n2o$obo$bo!",
+    },
+    Entry {
+        name: "Tub",
+        kind: Kind::StillLife,
+        rle: "x = 3, y = 3\nbo$obo$bo!",
+    },
+    Entry {
+        name: "Blinker",
+        kind: Kind::Oscillator,
+        rle: "x = 3, y = 1\n3o!",
+    },
+    Entry {
+        name: "Toad",
+        kind: Kind::Oscillator,
+        rle: "x = 4, y = 2\nb3o$3o!",
+    },
+    Entry {
+        name: "Beacon",
+        kind: Kind::Oscillator,
+        rle: "x = 4, y = 4\n2o$2o$2b2o$2b2o!",
+    },
+    Entry {
+        name: "Pulsar",
+        kind: Kind::Oscillator,
+        rle: "x = 13, y = 13\n2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$\
+              o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    Entry {
+        name: "Pentadecathlon",
+        kind: Kind::Oscillator,
+        rle: "x = 10, y = 3\n2bo4bo$2ob4ob2o$2bo4bo!",
+    },
+    Entry {
+        name: "Glider",
+        kind: Kind::Spaceship,
+        rle: "x = 3, y = 3\nbo$2bo$3o!",
+    },
+    Entry {
+        name: "LWSS",
+        kind: Kind::Spaceship,
+        rle: "x = 5, y = 4\nbo2bo$o$o3bo$4o!",
+    },
+    Entry {
+        name: "MWSS",
+        kind: Kind::Spaceship,
+        rle: "x = 6, y = 5\n3bo$bo3bo$o$o4bo$5o!",
+    },
+    Entry {
+        name: "HWSS",
+        kind: Kind::Spaceship,
+        rle: "x = 7, y = 5\n3b2o$bo4bo$o$o5bo$6o!",
+    },
+    Entry {
+        name: "Gosper glider gun",
+        kind: Kind::Gun,
+        rle: "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+              2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+    Entry {
+        name: "R-pentomino",
+        kind: Kind::Methuselah,
+        rle: "x = 3, y = 3\nb2o$2o$bo!",
+    },
+    Entry {
+        name: "Acorn",
+        kind: Kind::Methuselah,
+        rle: "x = 7, y = 3\nbo$3bo$2o2b3o!",
+    },
+    Entry {
+        name: "Diehard",
+        kind: Kind::Methuselah,
+        rle: "x = 8, y = 3\n6bo$2o$bo3b3o!",
+    },
+    Entry {
+        name: "Pi-heptomino",
+        kind: Kind::Methuselah,
+        rle: "x = 3, y = 3\n3o$obo$obo!",
+    },
+];
+
+/// A library pattern picked for stamping, with the quarter turns and mirroring to apply.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Pick {
+    index: usize,
+    turns: u8,
+    flipped: bool,
+}
+
+impl Pick {
+    pub fn entry(&self) -> &'static Entry {
+        &LIBRARY[self.index]
+    }
+
+    pub fn next(&mut self) {
+        self.index = (self.index + 1) % LIBRARY.len();
+    }
+
+    pub fn prev(&mut self) {
+        self.index = (self.index + LIBRARY.len() - 1) % LIBRARY.len();
+    }
+
+    /// Turns the pattern a quarter clockwise.
+    pub fn rotate(&mut self) {
+        self.turns = (self.turns + 1) % 4;
+    }
+
+    /// Mirrors the pattern left to right.
+    pub fn flip(&mut self) {
+        self.flipped = !self.flipped;
+    }
+
+    /// The picked pattern as it will be stamped: mirrored first, then turned.
+    pub fn pattern(&self) -> Pattern {
+        let mut pattern = self.entry().pattern();
+        if self.flipped {
+            pattern = pattern.reflected();
+        }
+        for _ in 0..self.turns {
+            pattern = pattern.rotated();
+        }
+        pattern
+    }
+
+    /// Such as `Glider (spaceship, 3/19), turned 90°, mirrored`.
+    pub fn describe(&self) -> String {
+        let entry = self.entry();
+        let mut text = format!(
+            "{} ({}, {}/{})",
+            entry.name,
+            entry.kind.name(),
+            self.index + 1,
+            LIBRARY.len()
+        );
+        if self.turns > 0 {
+            text.push_str(&format!(", turned {}°", self.turns as u32 * 90));
+        }
+        if self.flipped {
+            text.push_str(", mirrored");
+        }
+        text
+    }
+}
@@ -0,0 +1,236 @@
+use super::{pattern_error, Pattern};
+
+pub const LIFE_105_HEADER: &str = "#Life 1.05";
+pub const LIFE_106_HEADER: &str = "#Life 1.06";
+
+/// Parses Life 1.05: `#D` descriptions, `#N`/`#R` rules and `#P x y` blocks of `.` and `*`.
+///
+/// Life 1.05 has no name or author lines, so `#D Name: …` and `#D Author: …`, as written by
+/// [`write_105`], are read back as the name and author.
+pub fn parse_105(text: &str) -> Result<Pattern, String> {
+    let mut pattern = Pattern::default();
+    let mut live: Vec<(i64, i64)> = Vec::new();
+    let (mut x0, mut y) = (0i64, 0i64);
+
+    for line in text.lines().skip(1) {
+        let line = line.trim();
+        if let Some(desc) = line.strip_prefix("#D") {
+            let desc = desc.trim();
+            if let (None, Some(name)) = (&pattern.name, desc.strip_prefix("Name:")) {
+                pattern.name = Some(name.trim().to_string());
+            } else if let (None, Some(author)) = (&pattern.author, desc.strip_prefix("Author:")) {
+                pattern.author = Some(author.trim().to_string());
+            } else {
+                pattern.comments.push(desc.to_string());
+            }
+        } else if line.starts_with("#N") {
+            pattern.rule = Some("B3/S23".to_string());
+        } else if let Some(rule) = line.strip_prefix("#R") {
+            pattern.rule = Some(rule.trim().to_string());
+        } else if let Some(pos) = line.strip_prefix("#P") {
+            let (x, py) = parse_coords(pos)?;
+            (x0, y) = (x, py);
+        } else if line.starts_with('#') {
+            continue;
+        } else {
+            for (dx, ch) in line.chars().enumerate() {
+                match ch {
+                    '.' => {}
+                    '*' => live.push((x0 + dx as i64, y)),
+                    _ => {
+                        return Err(pattern_error(&format!(
+                            "unexpected '{ch}' in Life 1.05 block"
+                        )))
+                    }
+                }
+            }
+            y += 1;
+        }
+    }
+
+    pattern.cells = from_coords(&live)?;
+    Ok(pattern)
+}
+
+/// Parses Life 1.06: one `x y` pair per live cell.
+pub fn parse_106(text: &str) -> Result<Pattern, String> {
+    let live = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_coords)
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Pattern {
+        cells: from_coords(&live)?,
+        ..Default::default()
+    })
+}
+
+pub fn write_105(pattern: &Pattern) -> String {
+    let mut out = format!("{LIFE_105_HEADER}\n");
+
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#D Name: {name}\n"));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("#D Author: {author}\n"));
+    }
+    for line in pattern.comments.iter() {
+        out.push_str(&format!("#D {line}\n"));
+    }
+    match pattern.rule.as_deref() {
+        None | Some("B3/S23") => out.push_str("#N\n"),
+        Some(rule) => out.push_str(&format!("#R {}\n", survival_birth(rule))),
+    }
+
+    let (x0, y0) = (-(pattern.cols() as i64) / 2, -(pattern.rows() as i64) / 2);
+    out.push_str(&format!("#P {x0} {y0}\n"));
+    for row in pattern.cells.iter() {
+        let len = row.iter().rposition(|&s| s != 0).map_or(0, |i| i + 1);
+        out.extend(row[..len].iter().map(|&s| if s != 0 { '*' } else { '.' }));
+        if len == 0 {
+            out.push('.');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn write_106(pattern: &Pattern) -> String {
+    let mut out = format!("{LIFE_106_HEADER}\n");
+
+    for (y, row) in pattern.cells.iter().enumerate() {
+        for (x, &state) in row.iter().enumerate() {
+            if state != 0 {
+                out.push_str(&format!("{x} {y}\n"));
+            }
+        }
+    }
+    out
+}
+
+// Life 1.05 writes rules as survival/birth, e.g. `23/3`.
+fn survival_birth(rule: &str) -> String {
+    match rule.split_once('/') {
+        Some((b, s)) if b.starts_with(['B', 'b']) && s.starts_with(['S', 's']) => {
+            format!("{}/{}", &s[1..], &b[1..])
+        }
+        _ => rule.to_string(),
+    }
+}
+
+fn parse_coords(line: &str) -> Result<(i64, i64), String> {
+    let mut it = line.split_whitespace().map(str::parse::<i64>);
+    match (it.next(), it.next()) {
+        (Some(Ok(x)), Some(Ok(y))) => Ok((x, y)),
+        _ => Err(pattern_error(&format!(
+            "invalid coordinates '{}'",
+            line.trim()
+        ))),
+    }
+}
+
+fn from_coords(live: &[(i64, i64)]) -> Result<Vec<Vec<u8>>, String> {
+    let Some(x0) = live.iter().map(|&(x, _)| x).min() else {
+        return Ok(Vec::new());
+    };
+    let x1 = live.iter().map(|&(x, _)| x).max().unwrap();
+    let y0 = live.iter().map(|&(_, y)| y).min().unwrap();
+    let y1 = live.iter().map(|&(_, y)| y).max().unwrap();
+
+    // Coordinates far apart overflow the size, which is too large anyway.
+    let span = |lo: i64, hi: i64| {
+        hi.checked_sub(lo)
+            .and_then(|d| d.checked_add(1))
+            .and_then(|n| usize::try_from(n).ok())
+    };
+    let too_large = || {
+        pattern_error(&format!(
+            "pattern from ({x0}, {y0}) to ({x1}, {y1}) is too large"
+        ))
+    };
+    let (Some(cols), Some(rows)) = (span(x0, x1), span(y0, y1)) else {
+        return Err(too_large());
+    };
+    if rows.saturating_mul(cols) > super::MAX_CELLS {
+        return Err(too_large());
+    }
+
+    let mut cells = vec![vec![0u8; cols]; rows];
+    for &(x, y) in live.iter() {
+        cells[(y - y0) as usize][(x - x0) as usize] = 1;
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn life_105_round_trips() {
+        let texts = [
+            "#Life 1.05\n#D Glider\n#N\n#P -1 -1\n.*\n..*\n***\n",
+            "#Life 1.05\n#R 23/36\n#P 0 0\n*.*\n\n#P 4 3\n**\n",
+        ];
+        for text in texts {
+            let pattern = parse_105(text).unwrap();
+            let written = write_105(&pattern);
+            let again = parse_105(&written).unwrap();
+            assert_eq!(again, pattern, "{written}");
+            assert_eq!(write_105(&again), written);
+        }
+        let pattern = parse_105(texts[1]).unwrap();
+        assert_eq!(pattern.rule.as_deref(), Some("23/36"));
+        assert_eq!((pattern.rows(), pattern.cols()), (4, 6));
+    }
+
+    #[test]
+    fn life_106_round_trips() {
+        let text = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+        let pattern = parse_106(text).unwrap();
+        assert_eq!(
+            pattern.cells,
+            vec![vec![0, 1, 0], vec![0, 0, 1], vec![1, 1, 1]]
+        );
+
+        let written = write_106(&pattern);
+        let again = parse_106(&written).unwrap();
+        assert_eq!(again, pattern, "{written}");
+        assert_eq!(write_106(&again), written);
+    }
+
+    #[test]
+    fn life_105_keeps_the_name_and_author() {
+        let pattern = Pattern {
+            cells: vec![vec![1, 1], vec![1, 1]],
+            rule: Some("B3/S23".to_string()),
+            name: Some("Block".to_string()),
+            author: Some("John Conway".to_string()),
+            comments: vec!["The most common still life".to_string()],
+        };
+        let written = write_105(&pattern);
+        assert!(written.contains("#D Name: Block\n"), "{written}");
+        assert_eq!(parse_105(&written).unwrap(), pattern);
+
+        // Only the first of each is taken; later ones are plain descriptions.
+        let text = "#Life 1.05\n#D Name: A\n#D Name: B\n#N\n#P 0 0\n*\n";
+        let pattern = parse_105(text).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("A"));
+        assert_eq!(pattern.comments, vec!["Name: B".to_string()]);
+    }
+
+    #[test]
+    fn far_apart_cells_are_too_large() {
+        for text in [
+            format!("#Life 1.06\n{} 0\n{} 0\n", i64::MIN, i64::MAX),
+            format!("#Life 1.06\n0 {}\n0 {}\n", i64::MIN, i64::MAX),
+            format!("#Life 1.06\n0 0\n{} {}\n", i64::MAX, i64::MAX),
+            "#Life 1.06\n0 0\n100000 100000\n".to_string(),
+        ] {
+            let err = parse_106(&text).unwrap_err();
+            assert!(err.contains("too large"), "{err}");
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use super::{pattern_error, Pattern, MAX_CELLS};
+
+pub const HEADER: &str = "[M2]";
+
+const LEAF_LEVEL: u32 = 3;
+const LEAF_SIZE: usize = 1 << LEAF_LEVEL;
+
+enum Node {
+    /// Two-state 8x8 leaf, row-major.
+    Leaf(Box<[u8; LEAF_SIZE * LEAF_SIZE]>),
+    /// Multi-state level-1 node holding the states of its four cells.
+    States([u8; 4]),
+    /// Level >= 2 (or >= 4 for two-state) node; child `0` is the empty tree.
+    Inner { level: u32, children: [usize; 4] },
+}
+
+impl Node {
+    fn level(&self) -> u32 {
+        match self {
+            Node::Leaf(_) => LEAF_LEVEL,
+            Node::States(_) => 1,
+            Node::Inner { level, .. } => *level,
+        }
+    }
+}
+
+type Bounds = (u64, u64, u64, u64);
+
+/// Parses Golly's macrocell format into a dense pattern cropped to the live cells.
+pub fn parse(text: &str) -> Result<Pattern, String> {
+    let mut pattern = Pattern::default();
+    // Index 0 is the empty tree of any level.
+    let mut nodes: Vec<Option<Node>> = vec![None];
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('[') {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let kind = comment.chars().next();
+            let (kind, value) = comment.split_at(kind.map_or(0, char::len_utf8));
+            let value = value.trim().to_string();
+            match kind {
+                "R" => pattern.rule = Some(value),
+                "N" => pattern.name = Some(value),
+                "O" => pattern.author = Some(value),
+                "C" => pattern.comments.push(value),
+                _ => {}
+            }
+        } else if line.starts_with(['.', '*', '$']) {
+            nodes.push(Some(parse_leaf(line)?));
+        } else {
+            nodes.push(Some(parse_inner(line, &nodes)?));
+        }
+    }
+
+    let root = nodes.len() - 1;
+    let mut memo = HashMap::new();
+    let Some((x0, y0, x1, y1)) = bounds(&nodes, root, &mut memo) else {
+        return Ok(pattern);
+    };
+
+    let (cols, rows) = (x1 - x0 + 1, y1 - y0 + 1);
+    if rows.saturating_mul(cols) > MAX_CELLS as u64 {
+        return Err(pattern_error(&format!(
+            "macrocell pattern of {cols}x{rows} is too large to decode onto a board"
+        )));
+    }
+
+    let mut cells = vec![vec![0u8; cols as usize]; rows as usize];
+    paint(&nodes, root, 0, 0, (x0, y0), &mut cells);
+    pattern.cells = cells;
+    Ok(pattern)
+}
+
+/// Writes a pattern as a hash-consed quadtree; identical subtrees are stored once.
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = format!("{HEADER} (cellular_automata)\n");
+    if let Some(rule) = &pattern.rule {
+        out.push_str(&format!("#R {rule}\n"));
+    }
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {name}\n"));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("#O {author}\n"));
+    }
+    for comment in pattern.comments.iter() {
+        out.push_str(&format!("#C {comment}\n"));
+    }
+
+    let multi_state = pattern.max_state() > 1;
+    let min_level = if multi_state { 1 } else { LEAF_LEVEL };
+    let size = pattern.rows().max(pattern.cols()).max(1);
+    let level = (usize::BITS - (size - 1).leading_zeros()).max(min_level + 1);
+
+    let mut writer = Writer {
+        pattern,
+        multi_state,
+        lines: Vec::new(),
+        index: HashMap::new(),
+    };
+    let root = writer.node(0, 0, level);
+    if root == 0 {
+        // An empty pattern still needs one node for readers to find a root.
+        writer.lines.push(format!("{level} 0 0 0 0"));
+    }
+
+    for line in writer.lines.iter() {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+struct Writer<'a> {
+    pattern: &'a Pattern,
+    multi_state: bool,
+    lines: Vec<String>,
+    index: HashMap<String, usize>,
+}
+
+impl Writer<'_> {
+    fn state(&self, x: usize, y: usize) -> u8 {
+        self.pattern
+            .cells
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn node(&mut self, x: usize, y: usize, level: u32) -> usize {
+        if x >= self.pattern.cols() || y >= self.pattern.rows() {
+            return 0;
+        }
+
+        let line = if level == LEAF_LEVEL && !self.multi_state {
+            let mut line = String::new();
+            let mut pending_rows = 0;
+            for dy in 0..LEAF_SIZE {
+                let row: Vec<u8> = (0..LEAF_SIZE)
+                    .map(|dx| self.state(x + dx, y + dy))
+                    .collect();
+                let len = row.iter().rposition(|&s| s != 0).map_or(0, |i| i + 1);
+                if len == 0 {
+                    pending_rows += 1;
+                    continue;
+                }
+                line.push_str(&"$".repeat(pending_rows));
+                line.extend(row[..len].iter().map(|&s| if s != 0 { '*' } else { '.' }));
+                line.push('$');
+                pending_rows = 0;
+            }
+            if line.is_empty() {
+                return 0;
+            }
+            line
+        } else if level == 1 {
+            let states = [
+                self.state(x, y),
+                self.state(x + 1, y),
+                self.state(x, y + 1),
+                self.state(x + 1, y + 1),
+            ];
+            if states == [0; 4] {
+                return 0;
+            }
+            format!("1 {} {} {} {}", states[0], states[1], states[2], states[3])
+        } else {
+            let half = 1 << (level - 1);
+            let children = [
+                self.node(x, y, level - 1),
+                self.node(x + half, y, level - 1),
+                self.node(x, y + half, level - 1),
+                self.node(x + half, y + half, level - 1),
+            ];
+            if children == [0; 4] {
+                return 0;
+            }
+            format!(
+                "{level} {} {} {} {}",
+                children[0], children[1], children[2], children[3]
+            )
+        };
+
+        if let Some(&idx) = self.index.get(&line) {
+            return idx;
+        }
+        self.lines.push(line.clone());
+        let idx = self.lines.len();
+        self.index.insert(line, idx);
+        idx
+    }
+}
+
+fn parse_leaf(line: &str) -> Result<Node, String> {
+    let mut cells = Box::new([0u8; LEAF_SIZE * LEAF_SIZE]);
+    let (mut x, mut y) = (0, 0);
+
+    for ch in line.chars() {
+        match ch {
+            '$' => {
+                y += 1;
+                x = 0;
+                continue;
+            }
+            '.' => {}
+            '*' if x < LEAF_SIZE && y < LEAF_SIZE => cells[y * LEAF_SIZE + x] = 1,
+            _ => return Err(pattern_error(&format!("invalid macrocell leaf '{line}'"))),
+        }
+        x += 1;
+    }
+
+    Ok(Node::Leaf(cells))
+}
+
+fn parse_inner(line: &str, nodes: &[Option<Node>]) -> Result<Node, String> {
+    let err = || pattern_error(&format!("invalid macrocell node '{line}'"));
+    let fields = line
+        .split_whitespace()
+        .map(str::parse::<usize>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| err())?;
+
+    let [level, nw, ne, sw, se] = fields[..] else {
+        return Err(err());
+    };
+    let level = u32::try_from(level)
+        .ok()
+        .filter(|l| (1..64).contains(l))
+        .ok_or_else(err)?;
+
+    if level == 1 {
+        let mut states = [0u8; 4];
+        for (state, value) in states.iter_mut().zip([nw, ne, sw, se]) {
+            *state = u8::try_from(value).map_err(|_| err())?;
+        }
+        return Ok(Node::States(states));
+    }
+
+    let children = [nw, ne, sw, se];
+    for &child in children.iter() {
+        match nodes.get(child) {
+            Some(None) => {}
+            Some(Some(node)) if node.level() == level - 1 => {}
+            _ => return Err(err()),
+        }
+    }
+    Ok(Node::Inner { level, children })
+}
+
+// Bounding box of the live cells of a node, relative to its top-left corner.
+fn bounds(
+    nodes: &[Option<Node>],
+    idx: usize,
+    memo: &mut HashMap<usize, Option<Bounds>>,
+) -> Option<Bounds> {
+    if let Some(&b) = memo.get(&idx) {
+        return b;
+    }
+
+    let b = match nodes[idx].as_ref() {
+        None => None,
+        Some(Node::Leaf(cells)) => bounds_of(LEAF_SIZE, |x, y| cells[y * LEAF_SIZE + x] != 0),
+        Some(Node::States(states)) => bounds_of(2, |x, y| states[y * 2 + x] != 0),
+        Some(Node::Inner { level, children }) => {
+            let half = 1u64 << (level - 1);
+            let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+            children
+                .iter()
+                .zip(offsets)
+                .filter_map(|(&child, (ox, oy))| {
+                    let (x0, y0, x1, y1) = bounds(nodes, child, memo)?;
+                    Some((x0 + ox, y0 + oy, x1 + ox, y1 + oy))
+                })
+                .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+        }
+    };
+
+    memo.insert(idx, b);
+    b
+}
+
+fn bounds_of<F: Fn(usize, usize) -> bool>(size: usize, live: F) -> Option<Bounds> {
+    let mut b: Option<Bounds> = None;
+    for y in 0..size {
+        for x in 0..size {
+            if live(x, y) {
+                let (x, y) = (x as u64, y as u64);
+                b = Some(match b {
+                    None => (x, y, x, y),
+                    Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                });
+            }
+        }
+    }
+    b
+}
+
+fn paint(
+    nodes: &[Option<Node>],
+    idx: usize,
+    x: u64,
+    y: u64,
+    origin: (u64, u64),
+    cells: &mut [Vec<u8>],
+) {
+    let mut set = |dx: usize, dy: usize, state: u8| {
+        if state != 0 {
+            let (cx, cy) = (x + dx as u64 - origin.0, y + dy as u64 - origin.1);
+            cells[cy as usize][cx as usize] = state;
+        }
+    };
+
+    match nodes[idx].as_ref() {
+        None => {}
+        Some(Node::Leaf(leaf)) => {
+            for (i, &state) in leaf.iter().enumerate() {
+                set(i % LEAF_SIZE, i / LEAF_SIZE, state);
+            }
+        }
+        Some(Node::States(states)) => {
+            for (i, &state) in states.iter().enumerate() {
+                set(i % 2, i / 2, state);
+            }
+        }
+        Some(Node::Inner { level, children }) => {
+            let half = 1u64 << (level - 1);
+            let offsets = [(0, 0), (half, 0), (0, half), (half, half)];
+            for (&child, (ox, oy)) in children.iter().zip(offsets) {
+                paint(nodes, child, x + ox, y + oy, origin, cells);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::rle;
+
+    #[test]
+    fn comments_may_start_with_any_character() {
+        let pattern =
+            parse("[M2] (golly 4.2)\n#é unknown\n#N Block – still life\n#\n#C ü\n$$$$$$**$**$\n4 1 0 0 0\n")
+                .unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Block – still life"));
+        assert_eq!(pattern.comments, vec!["ü".to_string()]);
+        assert_eq!(pattern.cells, vec![vec![1, 1], vec![1, 1]]);
+    }
+
+    #[test]
+    fn parse_write_parse_round_trips() {
+        let mut gun = rle::parse(
+            "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+             2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!\n",
+        )
+        .unwrap();
+        gun.rule = Some("B3/S23".to_string());
+        gun.name = Some("Gosper glider gun".to_string());
+        gun.comments
+            .push("A true period 30 glider gun.".to_string());
+        let states = rle::parse("x = 3, y = 3, rule = Generations\nA.B$.C$2.D!\n").unwrap();
+
+        for pattern in [gun, states] {
+            let written = write(&pattern);
+            let again = parse(&written).unwrap();
+            assert_eq!(again, pattern, "{written}");
+            assert_eq!(write(&again), written);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use super::{pattern_error, Pattern};
+
+/// Parses the `.cells` plaintext format: `!` comment lines followed by rows of `.` and `O`.
+pub fn parse(text: &str) -> Result<Pattern, String> {
+    let mut pattern = Pattern::default();
+    let mut rows: Vec<Vec<u8>> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+
+        let row = line
+            .chars()
+            .map(|ch| match ch {
+                '.' => Ok(0),
+                'O' | '*' => Ok(1),
+                _ => Err(pattern_error(&format!(
+                    "unexpected '{ch}' in plaintext pattern"
+                ))),
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        rows.push(row);
+    }
+
+    let cols = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    for row in rows.iter_mut() {
+        row.resize(cols, 0);
+    }
+    pattern.cells = rows;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("!Name: {name}\n"));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("!Author: {author}\n"));
+    }
+    for comment in pattern.comments.iter() {
+        out.push_str(&format!("!{comment}\n"));
+    }
+
+    for row in pattern.cells.iter() {
+        out.extend(row.iter().map(|&s| if s != 0 { 'O' } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_write_parse_round_trips() {
+        let text = "!Name: Beacon\n!A period 2 oscillator.\n!\nOO..\nO\n...O\n..OO\n....\n";
+        let pattern = parse(text).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Beacon"));
+        assert_eq!((pattern.rows(), pattern.cols()), (5, 4));
+
+        let written = write(&pattern);
+        let again = parse(&written).unwrap();
+        assert_eq!(again, pattern, "{written}");
+        assert_eq!(write(&again), written);
+    }
+}
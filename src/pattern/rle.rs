@@ -0,0 +1,259 @@
+use super::{pattern_error, Pattern};
+
+const MAX_LINE: usize = 70;
+
+/// Parses a Run Length Encoded pattern, including multi-state (`.`, `A`..`X`, `pA`..`yO`) bodies.
+pub fn parse(text: &str) -> Result<Pattern, String> {
+    let mut pattern = Pattern::default();
+    let mut size: Option<(usize, usize)> = None;
+    let mut body = String::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if size.is_none() {
+            if let Some(comment) = line.strip_prefix('#') {
+                let kind = comment.chars().next();
+                let (kind, value) = comment.split_at(kind.map_or(0, char::len_utf8));
+                let value = value.trim().to_string();
+                match kind {
+                    "N" => pattern.name = Some(value),
+                    "O" => pattern.author = Some(value),
+                    "C" | "c" => pattern.comments.push(value),
+                    _ => {}
+                }
+            } else if line.starts_with('x') {
+                let (cols, rows, rule) = parse_header(line)?;
+                size = Some((rows, cols));
+                pattern.rule = rule;
+            } else if !line.is_empty() {
+                return Err(pattern_error("RLE header 'x = .., y = ..' is missing"));
+            }
+        } else {
+            body.push_str(line);
+            if line.contains('!') {
+                break;
+            }
+        }
+    }
+
+    let (rows, cols) =
+        size.ok_or_else(|| pattern_error("RLE header 'x = .., y = ..' is missing"))?;
+    pattern.cells = parse_body(&body, rows, cols)?;
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut out = String::new();
+
+    if let Some(name) = &pattern.name {
+        out.push_str(&format!("#N {name}\n"));
+    }
+    if let Some(author) = &pattern.author {
+        out.push_str(&format!("#O {author}\n"));
+    }
+    for comment in pattern.comments.iter() {
+        out.push_str(&format!("#C {comment}\n"));
+    }
+
+    out.push_str(&format!("x = {}, y = {}", pattern.cols(), pattern.rows()));
+    if let Some(rule) = &pattern.rule {
+        out.push_str(&format!(", rule = {rule}"));
+    }
+    out.push('\n');
+
+    let multi_state = pattern.max_state() > 1;
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    let mut push = |count: usize, token: String| match runs.last_mut() {
+        Some((n, last)) if *last == token => *n += count,
+        _ => runs.push((count, token)),
+    };
+
+    for (ir, row) in pattern.cells.iter().enumerate() {
+        let len = row.iter().rposition(|&s| s != 0).map_or(0, |i| i + 1);
+        for &state in row[..len].iter() {
+            push(1, state_token(state, multi_state));
+        }
+        if ir + 1 < pattern.rows() {
+            push(1, "$".to_string());
+        }
+    }
+
+    while runs.last().is_some_and(|(_, token)| token == "$") {
+        runs.pop();
+    }
+
+    let mut line = String::new();
+    for (count, token) in runs.iter() {
+        let run = if *count > 1 {
+            format!("{count}{token}")
+        } else {
+            token.clone()
+        };
+        if line.len() + run.len() > MAX_LINE {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        line.push_str(&run);
+    }
+    if line.len() + 1 > MAX_LINE {
+        out.push_str(&line);
+        out.push('\n');
+        line.clear();
+    }
+    line.push('!');
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
+
+// The rule runs to the end of the line, since rules such as `B3/S23:P40,40` hold commas.
+fn parse_header(line: &str) -> Result<(usize, usize, Option<String>), String> {
+    let (mut cols, mut rows, mut rule) = (None, None, None);
+    let malformed = || pattern_error(&format!("malformed RLE header '{line}'"));
+
+    let mut rest = line;
+    while !rest.trim().is_empty() {
+        let (key, value) = rest.split_once('=').ok_or_else(malformed)?;
+        if key.trim() == "rule" {
+            rule = Some(value.trim().to_string());
+            break;
+        }
+        let (value, next) = value.split_once(',').unwrap_or((value, ""));
+        let value = value.trim();
+        match key.trim() {
+            "x" => cols = value.parse::<usize>().ok(),
+            "y" => rows = value.parse::<usize>().ok(),
+            _ => {}
+        }
+        rest = next;
+    }
+
+    match (cols, rows) {
+        (Some(cols), Some(rows)) => Ok((cols, rows, rule)),
+        _ => Err(malformed()),
+    }
+}
+
+fn parse_body(body: &str, rows: usize, cols: usize) -> Result<Vec<Vec<u8>>, String> {
+    if rows.saturating_mul(cols) > super::MAX_CELLS {
+        return Err(pattern_error(&format!(
+            "pattern of {cols}x{rows} is too large"
+        )));
+    }
+
+    let mut cells = vec![vec![0u8; cols]; rows];
+    let (mut r, mut c) = (0usize, 0usize);
+    let mut count = String::new();
+    let mut prefix: Option<char> = None;
+
+    for ch in body.chars() {
+        if ch.is_ascii_digit() {
+            count.push(ch);
+            continue;
+        }
+
+        let n = if count.is_empty() {
+            1
+        } else {
+            count
+                .parse::<usize>()
+                .map_err(|_| pattern_error(&format!("invalid RLE run count '{count}'")))?
+        };
+        count.clear();
+
+        let state = match (prefix.take(), ch) {
+            (_, '!') => break,
+            (None, '$') => {
+                r += n;
+                c = 0;
+                continue;
+            }
+            (None, 'b' | '.') => 0,
+            (None, 'o') => 1,
+            (None, 'A'..='X') => ch as u8 - b'A' + 1,
+            (None, 'p'..='y') => {
+                prefix = Some(ch);
+                continue;
+            }
+            (Some(p), 'A'..='X') => {
+                let state = 24 * (p as usize - 'p' as usize + 1) + (ch as usize - 'A' as usize + 1);
+                u8::try_from(state)
+                    .map_err(|_| pattern_error(&format!("state {state} is out of range")))?
+            }
+            (_, ch) if ch.is_whitespace() => continue,
+            (_, ch) => return Err(pattern_error(&format!("unexpected '{ch}' in RLE data"))),
+        };
+
+        if state != 0 {
+            if r >= rows || c + n > cols {
+                return Err(pattern_error(&format!(
+                    "RLE data does not fit the {cols}x{rows} size given in its header"
+                )));
+            }
+            cells[r][c..c + n].fill(state);
+        }
+        c += n;
+    }
+
+    Ok(cells)
+}
+
+fn state_token(state: u8, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (1..=24, true) => char::from(b'A' + state - 1).to_string(),
+        (_, true) => {
+            let prefix = char::from(b'p' + (state - 25) / 24);
+            let letter = char::from(b'A' + (state - 25) % 24);
+            format!("{prefix}{letter}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_rules_may_hold_commas() {
+        let pattern = parse("x = 3, y = 3, rule = B3/S23:P40,40\nbo$2bo$3o!\n").unwrap();
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23:P40,40"));
+        assert_eq!(
+            pattern.cells,
+            vec![vec![0, 1, 0], vec![0, 0, 1], vec![1, 1, 1]]
+        );
+
+        let pattern = parse("x=2,y=1\n2o!\n").unwrap();
+        assert_eq!(pattern.rule, None);
+        assert_eq!(pattern.cells, vec![vec![1, 1]]);
+    }
+
+    #[test]
+    fn comments_may_start_with_any_character() {
+        let pattern = parse("#é unknown\n#N Glider – a ship\n#\n#C ü\nx = 1, y = 1\no!\n").unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider – a ship"));
+        assert_eq!(pattern.comments, vec!["ü".to_string()]);
+        assert_eq!(pattern.cells, vec![vec![1]]);
+    }
+
+    #[test]
+    fn parse_write_parse_round_trips() {
+        let texts = [
+            "#N Gosper glider gun\n#O Bill Gosper\n#C A true period 30 glider gun.\n\
+             x = 36, y = 9, rule = B3/S23\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$\
+             2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!\n",
+            "x = 4, y = 3, rule = B3/S23:T20,20\n$b2o$o2bo!\n",
+            "x = 5, y = 2, rule = Generations\n.A2.B$pAC.yO!\n",
+        ];
+        for text in texts {
+            let pattern = parse(text).unwrap();
+            let written = write(&pattern);
+            let again = parse(&written).unwrap();
+            assert_eq!(again, pattern, "{written}");
+            assert_eq!(write(&again), written);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+
+use crate::life::{Cell, Engine, Rule, UpdateScheme};
+use crate::predecessor::{neighbourhood, Found, Next, Search, Transitions};
+use crate::sat::{Answer, Lit, Solver, Var};
+use crate::soup::Symmetry;
+
+type Rows = usize;
+type Cols = usize;
+
+pub fn periodic_error(err: &str) -> String {
+    format!("[PERIODIC ERROR]: {err}.")
+}
+
+/// What to look for: a pattern that comes back `shift` cells away after `period` generations,
+/// which makes it an oscillator (or a still life) when the shift is zero and a spaceship otherwise.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Options {
+    pub period: usize,
+    /// Columns right and rows down the pattern moves every period.
+    pub shift: (i64, i64),
+    /// Size of the box every generation must fit in.
+    pub rows: Rows,
+    pub cols: Cols,
+    pub symmetry: Symmetry,
+    /// Conflicts after which the solver gives up, if any.
+    pub max_conflicts: Option<u64>,
+}
+
+impl Options {
+    /// Parses a `<dx>,<dy>` shift, e.g. `0,1` for a spaceship moving down.
+    pub fn parse_shift(shift: &str) -> Result<(i64, i64), String> {
+        shift
+            .split_once(',')
+            .and_then(|(dx, dy)| Some((dx.trim().parse().ok()?, dy.trim().parse().ok()?)))
+            .ok_or_else(|| periodic_error(&format!("'{shift}' is not a <dx>,<dy> shift")))
+    }
+
+    pub fn is_oscillator(&self) -> bool {
+        self.shift == (0, 0)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let (dx, dy) = self.shift;
+        if self.period == 0 || self.rows == 0 || self.cols == 0 {
+            return Err(periodic_error("the period and the box must not be empty"));
+        }
+        if dx.unsigned_abs() as usize > self.period || dy.unsigned_abs() as usize > self.period {
+            return Err(periodic_error(&format!(
+                "{self} is faster than light, which moves a cell a generation"
+            )));
+        }
+        if dx.unsigned_abs() as usize >= self.cols || dy.unsigned_abs() as usize >= self.rows {
+            return Err(periodic_error(&format!(
+                "a {}x{} box is too small to move {dx},{dy} in",
+                self.rows, self.cols
+            )));
+        }
+        if self.symmetry.needs_square() && self.rows != self.cols {
+            return Err(periodic_error(&format!(
+                "{} symmetry needs a square box",
+                self.symmetry
+            )));
+        }
+        // A pattern keeps its symmetry, so it can only move along its mirror.
+        let moves = match self.symmetry {
+            Symmetry::C1 => true,
+            Symmetry::D2V => dx == 0,
+            Symmetry::D2H => dy == 0,
+            Symmetry::C2 | Symmetry::C4 | Symmetry::D4 | Symmetry::D8 => self.is_oscillator(),
+        };
+        if !moves {
+            return Err(periodic_error(&format!(
+                "a pattern with {} symmetry cannot move {dx},{dy}",
+                self.symmetry
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (dx, dy) = self.shift;
+        match self.period {
+            1 if self.is_oscillator() => write!(f, "still life"),
+            p if self.is_oscillator() => write!(f, "period {p} oscillator"),
+            p => write!(f, "({dx},{dy})c/{p} spaceship"),
+        }
+    }
+}
+
+/// Looks for an oscillator or a spaceship under `rule`, as a SAT problem.
+///
+/// Every cell of every generation in the box is a variable, and each generation is tied to the
+/// previous one as in [`predecessor::search`](crate::predecessor::search), the last one to the
+/// first one moved by the shift. Cells outside the box stay dead. The first generation must not
+/// be empty, nor come back any sooner than the period. What the solver finds is run again with an
+/// [`Engine`], so a pattern is only returned if the engine agrees it has the period and shift.
+pub fn search(rule: &Rule, options: Options) -> Result<Search, String> {
+    if !rule.is_deterministic() {
+        return Err(periodic_error(
+            "periodic patterns need a rule without probabilities",
+        ));
+    }
+    if rule.next(Cell::DEAD, 0).is_alive() {
+        return Err(periodic_error(
+            "periodic patterns need a rule without B0, under which empty space comes alive",
+        ));
+    }
+    options.check()?;
+
+    let (rows, cols, period) = (options.rows as i64, options.cols as i64, options.period);
+    let mut solver = Solver::new();
+    for _ in 0..period as i64 * rows * cols {
+        solver.new_var();
+    }
+    // Generation `t` at (r, c), if it is in the box; generation `period` is the first one moved.
+    let var = |t: usize, r: i64, c: i64| -> Option<Var> {
+        let (t, r, c) = match t {
+            t if t == period => (0, r - options.shift.1, c - options.shift.0),
+            t => (t, r, c),
+        };
+        ((0..rows).contains(&r) && (0..cols).contains(&c))
+            .then(|| ((t as i64 * rows + r) * cols + c) as usize)
+    };
+
+    // Every cell that can come alive: the box, one more cell around it, and where the last
+    // generation is moved to.
+    let (dx, dy) = options.shift;
+    let mut transitions = Transitions::new(rule);
+    for t in 0..period {
+        for r in -1 - dy.abs()..=rows + dy.abs() {
+            for c in -1 - dx.abs()..=cols + dx.abs() {
+                let cells = neighbourhood(|dr, dc| var(t, r + dr, c + dc));
+                let next = match var(t + 1, r, c) {
+                    Some(v) => Next::Lit(Lit::new(v, true)),
+                    None => Next::State(false),
+                };
+                transitions.require(&mut solver, &cells, next);
+            }
+        }
+    }
+
+    let cells: Vec<(i64, i64)> = (0..rows)
+        .flat_map(|r| (0..cols).map(move |c| (r, c)))
+        .collect();
+    let alive: Vec<Lit> = cells
+        .iter()
+        .map(|&(r, c)| Lit::new(var(0, r, c).unwrap(), true))
+        .collect();
+    solver.add_clause(&alive);
+
+    for &(r, c) in cells.iter() {
+        let cell = Lit::new(var(0, r, c).unwrap(), true);
+        let orbit = options
+            .symmetry
+            .orbit(r as usize, c as usize, options.rows, options.cols);
+        for (ir, ic) in orbit {
+            let image = Lit::new(var(0, ir as i64, ic as i64).unwrap(), true);
+            solver.add_clause(&[!cell, image]);
+        }
+    }
+
+    // A shorter period, with the shift in proportion, must leave some cell different.
+    for d in (1..period).filter(|&d| period.is_multiple_of(d)) {
+        let times = (period / d) as i64;
+        if dx % times != 0 || dy % times != 0 {
+            continue;
+        }
+        let (sx, sy) = (dx / times, dy / times);
+        let mut differs = Vec::new();
+        for r in sy.min(0)..rows + sy.max(0) {
+            for c in sx.min(0)..cols + sx.max(0) {
+                match (var(d, r, c), var(0, r - sy, c - sx)) {
+                    (Some(a), Some(b)) => {
+                        let (a, b) = (Lit::new(a, true), Lit::new(b, true));
+                        let x = Lit::new(solver.new_var(), true);
+                        solver.add_clause(&[!x, a, b]);
+                        solver.add_clause(&[!x, !a, !b]);
+                        differs.push(x);
+                    }
+                    (Some(v), None) | (None, Some(v)) => differs.push(Lit::new(v, true)),
+                    (None, None) => {}
+                }
+            }
+        }
+        solver.add_clause(&differs);
+    }
+
+    let (variables, clauses) = (solver.num_vars(), solver.num_clauses());
+    let found = match solver.solve(options.max_conflicts) {
+        Answer::Sat(model) => {
+            let pattern: Vec<Vec<Cell>> = (0..rows)
+                .map(|r| {
+                    (0..cols)
+                        .map(|c| match model[var(0, r, c).unwrap()] {
+                            true => Cell::ALIVE,
+                            false => Cell::DEAD,
+                        })
+                        .collect()
+                })
+                .collect();
+            if !has_period(rule, &pattern, options.period, options.shift) {
+                return Err(periodic_error(&format!(
+                    "the engine does not run the pattern found as a {options}"
+                )));
+            }
+            Found::Pattern(pattern)
+        }
+        Answer::Unsat => Found::Impossible,
+        Answer::Unknown => Found::GaveUp,
+    };
+    Ok(Search {
+        found,
+        variables,
+        clauses,
+        conflicts: solver.conflicts(),
+    })
+}
+
+/// Whether `cells` come back moved by `shift` after `period` generations of an [`Engine`]
+/// running `rule`, and not sooner.
+///
+/// The cells are run on a board two cells larger on every side, which is enough for the patterns
+/// [`search`] finds: they never leave their box.
+pub fn has_period(rule: &Rule, cells: &[Vec<Cell>], period: usize, shift: (i64, i64)) -> bool {
+    const PADDING: usize = 2;
+    let (rows, cols) = (cells.len(), cells.first().map_or(0, Vec::len));
+    let mut board = vec![vec![Cell::DEAD; cols + 2 * PADDING]; rows + 2 * PADDING];
+    for (r, row) in cells.iter().enumerate() {
+        board[r + PADDING][PADDING..PADDING + cols].copy_from_slice(row);
+    }
+    let start = board.clone();
+    let mut engine = Engine::new(rule.clone(), UpdateScheme::Synchronous, 0);
+
+    let moved = |board: &[Vec<Cell>], (dx, dy): (i64, i64)| {
+        board.iter().enumerate().all(|(r, row)| {
+            row.iter().enumerate().all(|(c, cell)| {
+                let (sr, sc) = (r as i64 - dy, c as i64 - dx);
+                let was = start
+                    .get(sr as usize)
+                    .and_then(|row| row.get(sc as usize))
+                    .filter(|_| sr >= 0 && sc >= 0)
+                    .is_some_and(|cell| cell.is_alive());
+                cell.is_alive() == was
+            })
+        })
+    };
+    for t in 1..=period {
+        engine.step(&mut board);
+        let times = (period / t) as i64;
+        let sooner = t < period
+            && period.is_multiple_of(t)
+            && shift.0 % times == 0
+            && shift.1 % times == 0
+            && moved(&board, (shift.0 / times, shift.1 / times));
+        if sooner {
+            return false;
+        }
+    }
+    moved(&board, shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(period: usize, shift: (i64, i64), rows: Rows, cols: Cols) -> Options {
+        Options {
+            period,
+            shift,
+            rows,
+            cols,
+            symmetry: Symmetry::C1,
+            max_conflicts: None,
+        }
+    }
+
+    fn find(options: Options) -> Found {
+        search(&Rule::default(), options).unwrap().found
+    }
+
+    #[test]
+    fn finds_an_oscillator_of_the_period() {
+        let Found::Pattern(cells) = find(options(2, (0, 0), 5, 5)) else {
+            panic!("no period 2 oscillator in a 5x5 box");
+        };
+        assert!(has_period(&Rule::default(), &cells, 2, (0, 0)));
+        assert!(!has_period(&Rule::default(), &cells, 1, (0, 0)));
+
+        let Found::Pattern(cells) = find(options(1, (0, 0), 4, 4)) else {
+            panic!("no still life in a 4x4 box");
+        };
+        assert!(has_period(&Rule::default(), &cells, 1, (0, 0)));
+    }
+
+    #[test]
+    fn finds_the_glider() {
+        let Found::Pattern(cells) = find(options(4, (1, 1), 5, 5)) else {
+            panic!("no (1,1)c/4 spaceship in a 5x5 box");
+        };
+        let population = cells.iter().flatten().filter(|c| c.is_alive()).count();
+        assert_eq!(population, 5);
+        assert!(has_period(&Rule::default(), &cells, 4, (1, 1)));
+
+        // The glider moves diagonally only, and a 2x2 box is too small for anything moving.
+        assert_eq!(find(options(4, (0, 1), 4, 4)), Found::Impossible);
+        assert_eq!(find(options(4, (1, 1), 2, 2)), Found::Impossible);
+    }
+
+    #[test]
+    fn shorter_periods_are_excluded() {
+        // Only still lifes fit a 2x2 box, and they repeat every generation.
+        assert_eq!(find(options(2, (0, 0), 2, 2)), Found::Impossible);
+        assert!(matches!(find(options(1, (0, 0), 2, 2)), Found::Pattern(_)));
+
+        // Blinkers and still lifes come back after 4 generations but sooner too, and no true
+        // period 4 oscillator fits a 5x5 box.
+        assert_eq!(find(options(4, (0, 0), 5, 5)), Found::Impossible);
+        // Nor is the glider a (2,2)c/8 spaceship, as it moves (1, 1) after 4 generations.
+        assert_eq!(find(options(8, (2, 2), 6, 6)), Found::Impossible);
+        // The engine check agrees: a block comes back after 2 generations, but after 1 already.
+        let block = vec![vec![Cell::ALIVE; 2]; 2];
+        assert!(!has_period(&Rule::default(), &block, 2, (0, 0)));
+    }
+
+    #[test]
+    fn impossible_options_are_refused() {
+        let check = |options: Options| options.check().is_ok();
+        assert!(check(options(4, (1, 1), 5, 5)));
+        assert!(!check(options(2, (3, 0), 8, 8)));
+        assert!(!check(options(4, (0, -5), 8, 8)));
+        assert!(!check(options(0, (0, 0), 8, 8)));
+        assert!(!check(options(4, (0, 2), 2, 8)));
+
+        let symmetric = |symmetry, shift, rows, cols| Options {
+            symmetry,
+            ..options(4, shift, rows, cols)
+        };
+        assert!(check(symmetric(Symmetry::D2V, (0, 1), 6, 5)));
+        assert!(!check(symmetric(Symmetry::D2V, (1, 0), 6, 5)));
+        assert!(check(symmetric(Symmetry::D2H, (1, 0), 6, 5)));
+        assert!(!check(symmetric(Symmetry::D2H, (0, 1), 6, 5)));
+        assert!(!check(symmetric(Symmetry::C2, (0, 1), 6, 6)));
+        assert!(check(symmetric(Symmetry::C4, (0, 0), 6, 6)));
+        assert!(!check(symmetric(Symmetry::C4, (0, 0), 6, 5)));
+        assert!(!check(symmetric(Symmetry::D8, (1, 1), 6, 6)));
+
+        assert!(search(&Rule::parse("B03/S23").unwrap(), options(2, (0, 0), 5, 5)).is_err());
+        let noisy = Rule::default().with_probabilities(0.5, 1.0).unwrap();
+        assert!(search(&noisy, options(2, (0, 0), 5, 5)).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::life::{Cell, Rule};
+use crate::sat::{Answer, Lit, Solver, Var};
+
+/// Cells a predecessor may reach beyond the target on every side by default. Farther cells cannot
+/// affect the target.
+pub const DEFAULT_MARGIN: usize = 1;
+
+/// Conflicts the solver may run into before a search gives up, by default.
+pub const DEFAULT_MAX_CONFLICTS: u64 = 1_000_000;
+
+// The centre of a 3x3 neighbourhood, whose cells are numbered row by row.
+const CENTRE: usize = 4;
+
+pub fn predecessor_error(err: &str) -> String {
+    format!("[PREDECESSOR ERROR]: {err}.")
+}
+
+/// How a predecessor is looked for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Options {
+    /// Cells the predecessor may reach beyond the target on every side; everything farther out
+    /// is dead.
+    pub margin: usize,
+    /// Also require the next generation to be dead around the target, so that the predecessor
+    /// evolves into the target alone rather than into something that contains it.
+    pub exact: bool,
+    /// Conflicts after which the solver gives up, if any.
+    pub max_conflicts: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            margin: DEFAULT_MARGIN,
+            exact: false,
+            max_conflicts: Some(DEFAULT_MAX_CONFLICTS),
+        }
+    }
+}
+
+impl Options {
+    /// Whether finding no predecessor proves the target an orphan: a pattern no board evolves
+    /// into, which makes every board holding it a Garden of Eden.
+    pub fn proves_orphan(&self) -> bool {
+        !self.exact && self.margin >= 1
+    }
+}
+
+/// What a predecessor or [periodic](crate::periodic) search found.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Found {
+    /// A pattern that does what was asked: a predecessor `margin` cells larger than the target on
+    /// every side whose next generation matches the target, or the first generation of an
+    /// oscillator or spaceship, the size of its box.
+    Pattern(Vec<Vec<Cell>>),
+    /// No pattern that fits does what was asked.
+    Impossible,
+    /// The solver gave up after the maximum number of conflicts.
+    GaveUp,
+}
+
+/// A finished search, with the size of the problem the solver was given.
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub found: Found,
+    pub variables: usize,
+    pub clauses: usize,
+    pub conflicts: u64,
+}
+
+/// Looks for a pattern whose next generation under `rule` matches `target`, as a SAT problem.
+///
+/// Every cell of the predecessor is a variable. For each cell of the target, the states of its
+/// neighbourhood that lead to the wrong state are forbidden; they are merged into as few clauses
+/// as their prime implicants allow, since most of them only depend on a few of the nine cells.
+/// The plane is unbounded: cells beyond the margin are dead.
+pub fn search(rule: &Rule, target: &[Vec<Cell>], options: Options) -> Result<Search, String> {
+    if !rule.is_deterministic() {
+        return Err(predecessor_error(
+            "predecessors need a rule without probabilities",
+        ));
+    }
+    if options.exact && rule.next(Cell::DEAD, 0).is_alive() {
+        return Err(predecessor_error(
+            "exact predecessors need a rule without B0, under which empty space comes alive",
+        ));
+    }
+    let (rows, cols) = (target.len(), target.first().map_or(0, Vec::len));
+    if rows == 0 || cols == 0 {
+        return Err(predecessor_error("the target is empty"));
+    }
+
+    let m = options.margin as i64;
+    let (height, width) = (rows as i64 + 2 * m, cols as i64 + 2 * m);
+    let mut solver = Solver::new();
+    for _ in 0..height * width {
+        solver.new_var();
+    }
+    let var = |r: i64, c: i64| {
+        ((0..height).contains(&r) && (0..width).contains(&c)).then(|| (r * width + c) as usize)
+    };
+
+    // The cells whose next state is required, with that state: the target itself, and with
+    // `exact` every other cell the predecessor reaches.
+    let wanted = |r: i64, c: i64| -> Option<bool> {
+        let (tr, tc) = (r - m, c - m);
+        if (0..rows as i64).contains(&tr) && (0..cols as i64).contains(&tc) {
+            Some(target[tr as usize][tc as usize].is_alive())
+        } else {
+            options.exact.then_some(false)
+        }
+    };
+
+    let mut transitions = Transitions::new(rule);
+    for r in -1..=height {
+        for c in -1..=width {
+            if let Some(alive) = wanted(r, c) {
+                let cells = neighbourhood(|dr, dc| var(r + dr, c + dc));
+                transitions.require(&mut solver, &cells, Next::State(alive));
+            }
+        }
+    }
+
+    let (variables, clauses) = (solver.num_vars(), solver.num_clauses());
+    let found = match solver.solve(options.max_conflicts) {
+        Answer::Sat(model) => Found::Pattern(
+            model
+                .chunks(width as usize)
+                .map(|row| {
+                    row.iter()
+                        .map(|&alive| if alive { Cell::ALIVE } else { Cell::DEAD })
+                        .collect()
+                })
+                .collect(),
+        ),
+        Answer::Unsat => Found::Impossible,
+        Answer::Unknown => Found::GaveUp,
+    };
+    Ok(Search {
+        found,
+        variables,
+        clauses,
+        conflicts: solver.conflicts(),
+    })
+}
+
+/// The state a cell must take in the next generation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Next {
+    State(bool),
+    /// Whatever the literal is.
+    Lit(Lit),
+}
+
+/// The variables of a 3x3 neighbourhood row by row, `None` for cells that are always dead, from
+/// the variable at each offset from the centre.
+pub(crate) fn neighbourhood(mut var: impl FnMut(i64, i64) -> Option<Var>) -> [Option<Var>; 9] {
+    std::array::from_fn(|k| var(k as i64 / 3 - 1, k as i64 % 3 - 1))
+}
+
+/// Adds the clauses that tie cells' next states to their neighbourhoods under a rule, caching
+/// the covers of forbidden neighbourhoods by which cells exist and the state wanted.
+pub(crate) struct Transitions<'a> {
+    rule: &'a Rule,
+    covers: HashMap<(u16, bool), Vec<(u16, u16)>>,
+}
+
+impl<'a> Transitions<'a> {
+    pub(crate) fn new(rule: &'a Rule) -> Self {
+        Self {
+            rule,
+            covers: HashMap::new(),
+        }
+    }
+
+    /// Requires the centre of `cells` to become `next`.
+    pub(crate) fn require(&mut self, solver: &mut Solver, cells: &[Option<Var>; 9], next: Next) {
+        let present = (0..9)
+            .filter(|&k| cells[k].is_some())
+            .fold(0u16, |mask, k| mask | 1 << k);
+        // With a literal, the neighbourhoods forbidden for each state only are while the literal
+        // has that state.
+        let wanted = match next {
+            Next::State(alive) => vec![(alive, None)],
+            Next::Lit(lit) => vec![(true, Some(!lit)), (false, Some(lit))],
+        };
+        for (alive, guard) in wanted {
+            let rule = self.rule;
+            let cover = self
+                .covers
+                .entry((present, alive))
+                .or_insert_with(|| forbidden_cover(rule, present, alive));
+            for &(care, value) in cover.iter() {
+                let clause: Vec<Lit> = (0..9)
+                    .filter(|&k| care & 1 << k != 0)
+                    .map(|k| Lit::new(cells[k].unwrap(), value & 1 << k == 0))
+                    .chain(guard)
+                    .collect();
+                solver.add_clause(&clause);
+            }
+        }
+    }
+}
+
+/// The next generation of `cells` on an unbounded plane, one cell larger on every side.
+pub fn evolve(rule: &Rule, cells: &[Vec<Cell>]) -> Vec<Vec<Cell>> {
+    let (rows, cols) = (cells.len() as i64, cells.first().map_or(0, Vec::len) as i64);
+    let alive = |r: i64, c: i64| {
+        (0..rows).contains(&r) && (0..cols).contains(&c) && cells[r as usize][c as usize].is_alive()
+    };
+    (-1..=rows)
+        .map(|r| {
+            (-1..=cols)
+                .map(|c| {
+                    let n = (0..9)
+                        .filter(|&k| k != CENTRE as i64 && alive(r + k / 3 - 1, c + k % 3 - 1))
+                        .count();
+                    let cell = if alive(r, c) { Cell::ALIVE } else { Cell::DEAD };
+                    rule.next(cell, n)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Clauses forbidding every neighbourhood that does not lead to `alive`, as (care, value) cubes
+// over the nine cells: the cells in `care` having the states in `value` is forbidden. Cells
+// missing from `present` are dead.
+//
+// The forbidden neighbourhoods are merged into prime implicants, Quine-McCluskey style, and a
+// greedy cover of them is kept.
+fn forbidden_cover(rule: &Rule, present: u16, alive: bool) -> Vec<(u16, u16)> {
+    let forbidden: Vec<u16> = (0..1u16 << 9)
+        .filter(|&cells| cells & !present == 0)
+        .filter(|&cells| {
+            let centre = if cells & 1 << CENTRE != 0 {
+                Cell::ALIVE
+            } else {
+                Cell::DEAD
+            };
+            let n = (cells & !(1 << CENTRE)).count_ones() as usize;
+            rule.next(centre, n).is_alive() != alive
+        })
+        .collect();
+
+    let mut primes = Vec::new();
+    let mut cubes: HashSet<(u16, u16)> = forbidden.iter().map(|&cells| (present, cells)).collect();
+    while !cubes.is_empty() {
+        let mut merged = HashSet::new();
+        let mut used = HashSet::new();
+        for &(care, value) in cubes.iter() {
+            for k in (0..9).filter(|&k| care & 1 << k != 0 && value & 1 << k == 0) {
+                let other = (care, value | 1 << k);
+                if cubes.contains(&other) {
+                    merged.insert((care & !(1 << k), value));
+                    used.insert((care, value));
+                    used.insert(other);
+                }
+            }
+        }
+        primes.extend(cubes.difference(&used).copied());
+        cubes = merged;
+    }
+    primes.sort_unstable();
+
+    let mut uncovered: HashSet<u16> = forbidden.into_iter().collect();
+    let mut cover = Vec::new();
+    while !uncovered.is_empty() {
+        let covers = |&(care, value): &(u16, u16)| {
+            uncovered
+                .iter()
+                .filter(|&&cells| cells & care == value)
+                .count()
+        };
+        let best = *primes.iter().max_by_key(|&prime| covers(prime)).unwrap();
+        uncovered.retain(|&cells| cells & best.0 != best.1);
+        cover.push(best);
+    }
+    cover
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng as _;
+
+    use super::*;
+    use crate::rng;
+
+    fn cells(rows: &[&str]) -> Vec<Vec<Cell>> {
+        rows.iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| if c == 'o' { Cell::ALIVE } else { Cell::DEAD })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn covers_forbid_exactly_the_wrong_neighbourhoods() {
+        for rule in ["B3/S23", "B2/S", "B36/S125", "B0/S8"] {
+            let rule = Rule::parse(rule).unwrap();
+            for present in [0x1ff, 0x1ef, 0x0db, 0x007] {
+                for alive in [false, true] {
+                    let cover = forbidden_cover(&rule, present, alive);
+                    for cells in (0..1u16 << 9).filter(|&cells| cells & !present == 0) {
+                        let centre = if cells & 1 << CENTRE != 0 {
+                            Cell::ALIVE
+                        } else {
+                            Cell::DEAD
+                        };
+                        let n = (cells & !(1 << CENTRE)).count_ones() as usize;
+                        let wrong = rule.next(centre, n).is_alive() != alive;
+                        let forbidden = cover.iter().any(|&(care, value)| cells & care == value);
+                        assert_eq!(forbidden, wrong, "{rule} {present:#x} {alive} {cells:#x}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn predecessors_evolve_into_their_target() {
+        let rule = Rule::default();
+        let mut rng = rng::from_seed(1);
+        for _ in 0..10 {
+            let soup: Vec<Vec<Cell>> = (0..4)
+                .map(|_| {
+                    (0..4)
+                        .map(|_| match rng.gen_bool(0.5) {
+                            true => Cell::ALIVE,
+                            false => Cell::DEAD,
+                        })
+                        .collect()
+                })
+                .collect();
+            // The next generation of a soup always has a predecessor: the soup itself.
+            let target = evolve(&rule, &soup);
+            for exact in [false, true] {
+                let options = Options {
+                    margin: 1,
+                    exact,
+                    max_conflicts: None,
+                };
+                let Found::Pattern(before) = search(&rule, &target, options).unwrap().found else {
+                    panic!("no predecessor of {target:?}");
+                };
+                let after = evolve(&rule, &before);
+                let inner: Vec<Vec<Cell>> = after[2..after.len() - 2]
+                    .iter()
+                    .map(|row| row[2..row.len() - 2].to_vec())
+                    .collect();
+                assert_eq!(inner, target);
+                if exact {
+                    let outside = after.iter().flatten().filter(|c| c.is_alive()).count();
+                    let inside = target.iter().flatten().filter(|c| c.is_alive()).count();
+                    assert_eq!(outside, inside);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_no_predecessor_of_an_orphan() {
+        // Under B2/S every live cell dies, so a solid 3x3 comes from nine dead cells, yet its
+        // centre needs two live neighbours to be born.
+        let rule = Rule::parse("B2/S").unwrap();
+        let target = cells(&["ooo", "ooo", "ooo"]);
+        let search = search(&rule, &target, Options::default()).unwrap();
+        assert_eq!(search.found, Found::Impossible);
+        assert!(Options::default().proves_orphan());
+    }
+}
@@ -0,0 +1,406 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use self::gif::GifRecorder;
+use self::stream::FrameStream;
+use crate::life::{Automaton, Cell};
+use crate::pattern::Pattern;
+
+pub mod gif;
+pub mod ppm;
+pub mod sequence;
+pub mod stream;
+
+type Rows = usize;
+type Cols = usize;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG";
+
+pub fn image_error(err: &str) -> String {
+    format!("[IMAGE ERROR]: {err}.")
+}
+
+/// Colours blended from the first (dead cells, empty field) to the last (live cells, full field).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette(Vec<[u8; 3]>);
+
+impl Palette {
+    /// A palette name (`gray`, `inverted`, `heat`, `ocean`) or comma-separated hex colours such
+    /// as `000000,ff8800,ffffff`.
+    pub fn parse(palette: &str) -> Result<Self, String> {
+        let stops = match palette.trim() {
+            "gray" | "grey" => vec![[0, 0, 0], [255, 255, 255]],
+            "inverted" => vec![[255, 255, 255], [0, 0, 0]],
+            "heat" => vec![[0, 0, 0], [200, 30, 0], [255, 200, 0], [255, 255, 255]],
+            "ocean" => vec![[0, 0, 0], [0, 40, 120], [0, 200, 220], [255, 255, 255]],
+            colours => colours
+                .split(',')
+                .map(parse_hex)
+                .collect::<Option<Vec<_>>>()
+                .filter(|stops| stops.len() >= 2)
+                .ok_or_else(|| {
+                    image_error(&format!(
+                        "'{palette}' is neither a palette name nor two or more hex colours"
+                    ))
+                })?,
+        };
+        Ok(Self(stops))
+    }
+
+    pub fn gray() -> Self {
+        Self(vec![[0, 0, 0], [255, 255, 255]])
+    }
+
+    /// `n` colours evenly spaced along the palette.
+    pub fn colours(&self, n: usize) -> Vec<[u8; 3]> {
+        (0..n)
+            .map(|i| self.at(i as f32 / n.saturating_sub(1).max(1) as f32))
+            .collect()
+    }
+
+    /// Colour at `t` in `[0, 1]`, linearly interpolated between the stops.
+    pub fn at(&self, t: f32) -> [u8; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let x = t * (self.0.len() - 1) as f32;
+        let i = (x as usize).min(self.0.len() - 2);
+        let (a, b, f) = (self.0[i], self.0[i + 1], x - i as f32);
+        [0, 1, 2].map(|k| (a[k] as f32 + (b[k] as f32 - a[k] as f32) * f).round() as u8)
+    }
+}
+
+/// Colour of every state of a rule, from its own colours or spread along `palette`.
+pub fn state_colours(automaton: &Automaton, palette: Option<&Palette>) -> Vec<[u8; 3]> {
+    let n_states = automaton.n_states();
+    match palette {
+        Some(palette) => palette.colours(n_states),
+        None => (0..n_states)
+            .map(|s| rgb(automaton.color_hex(Cell(s as u8))))
+            .collect(),
+    }
+}
+
+/// Drops the alpha channel of an `0xRRGGBBAA` colour.
+pub fn rgb(rgba: u32) -> [u8; 3] {
+    [(rgba >> 24) as u8, (rgba >> 16) as u8, (rgba >> 8) as u8]
+}
+
+/// How the frames of a run are captured.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CaptureOptions {
+    pub delay_ms: u32,
+    /// Only every `skip`-th generation becomes a frame.
+    pub skip: usize,
+    /// Frames to capture; `None` captures until the capture is closed.
+    pub frames: Option<usize>,
+}
+
+impl CaptureOptions {
+    /// Frames that make up `seconds` of animation.
+    pub fn frames_in(&self, seconds: f64) -> usize {
+        (seconds * 1000.0 / self.delay_ms.max(1) as f64)
+            .ceil()
+            .max(1.0) as usize
+    }
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            delay_ms: 100,
+            skip: 1,
+            frames: None,
+        }
+    }
+}
+
+/// A capture length in seconds, which must be positive.
+pub fn parse_duration(seconds: &str) -> Result<f64, String> {
+    seconds
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|s| s.is_finite() && *s > 0.0)
+        .ok_or_else(|| image_error(&format!("'{seconds}' is not a positive number of seconds")))
+}
+
+/// Somewhere the frames of a run go as it runs: a GIF or a video stream.
+pub trait Capture: Sized {
+    /// Offers the next generation, which is kept unless it is skipped or the capture is done.
+    fn offer(&mut self, frame: &Frame) -> Result<(), String>;
+
+    /// Whether the requested number of frames has been captured.
+    fn is_done(&self) -> bool;
+
+    /// Ends the capture and reports what was written.
+    fn close(self) -> Result<String, String>;
+}
+
+/// Offers `frame` to the capture in `slot`, if any, and closes it once it has all its frames or
+/// fails. Returns its report when it ended.
+pub fn record<C: Capture>(slot: &mut Option<C>, frame: &Frame) -> Option<Result<String, String>> {
+    let capture = slot.as_mut()?;
+    let offered = capture.offer(frame);
+    if offered.is_ok() && !capture.is_done() {
+        return None;
+    }
+    let capture = slot.take()?;
+    Some(offered.and_then(|_| capture.close()))
+}
+
+/// Closes the capture in `slot`, if any, and returns its report.
+pub fn stop<C: Capture>(slot: &mut Option<C>) -> Option<Result<String, String>> {
+    slot.take().map(Capture::close)
+}
+
+/// What a run is captured into: a GIF recording and a video stream, each optional.
+#[derive(Default)]
+pub struct Captures {
+    pub gif: Option<GifRecorder>,
+    pub stream: Option<FrameStream>,
+}
+
+impl Captures {
+    pub fn is_active(&self) -> bool {
+        self.gif.is_some() || self.stream.is_some()
+    }
+
+    /// Whether frames go to stdout, which then cannot carry anything else.
+    pub fn is_stdout(&self) -> bool {
+        self.stream.as_ref().is_some_and(FrameStream::is_stdout)
+    }
+
+    /// Offers the next generation to the running captures, drawing it only if there are any.
+    /// Returns the reports of those that ended.
+    pub fn offer(&mut self, frame: impl FnOnce() -> Frame) -> Vec<Result<String, String>> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        let frame = frame();
+        [
+            record(&mut self.gif, &frame),
+            record(&mut self.stream, &frame),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Closes every capture and returns their reports.
+    pub fn stop(&mut self) -> Vec<Result<String, String>> {
+        [stop(&mut self.gif), stop(&mut self.stream)]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+fn parse_hex(colour: &str) -> Option<[u8; 3]> {
+    let hex = colour.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8])
+}
+
+/// An RGB image, three bytes per pixel, row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        }
+    }
+
+    /// Draws a board with `cell_size` pixels per cell. Without a palette the rule's own colours
+    /// are used; with one, states are spread evenly along it.
+    pub fn from_cells(
+        cells: &[Vec<Cell>],
+        automaton: &Automaton,
+        cell_size: usize,
+        palette: Option<&Palette>,
+    ) -> Self {
+        let colours = state_colours(automaton, palette);
+        Self::from_grid(cells, cell_size, |cell| {
+            colours
+                .get(cell.0 as usize)
+                .copied()
+                .unwrap_or_else(|| rgb(automaton.color_hex(*cell)))
+        })
+    }
+
+    /// Draws a continuous field, mapping `[0, 1]` along the palette.
+    pub fn from_field(field: &[Vec<f32>], cell_size: usize, palette: &Palette) -> Self {
+        Self::from_grid(field, cell_size, |&v| palette.at(v))
+    }
+
+    /// Draws any grid, colouring each cell with `color`.
+    pub fn from_grid<T, F>(grid: &[Vec<T>], cell_size: usize, color: F) -> Self
+    where
+        F: Fn(&T) -> [u8; 3],
+    {
+        let cell_size = cell_size.max(1);
+        let (rows, cols) = (grid.len(), grid.first().map_or(0, Vec::len));
+        let mut frame = Self::new(cols * cell_size, rows * cell_size);
+
+        for (r, row) in grid.iter().enumerate() {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|cell| {
+                    let rgb = color(cell);
+                    (0..cell_size).flat_map(move |_| rgb)
+                })
+                .collect();
+            for y in r * cell_size..(r + 1) * cell_size {
+                let start = y * frame.width * 3;
+                frame.pixels[start..start + line.len()].copy_from_slice(&line);
+            }
+        }
+        frame
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let err = |err: &dyn std::fmt::Display| {
+            image_error(&format!("cannot write {}: {err}", path.display()))
+        };
+
+        let file = File::create(path).map_err(|e| err(&e))?;
+        self.write_png(BufWriter::new(file)).map_err(|e| err(&e))
+    }
+
+    /// Encodes the frame as an 8-bit RGB PNG into `writer`.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+    }
+}
+
+/// Luminance in `[0, 1]` of every pixel of a PNG or PPM/PGM image. Transparent pixels count
+/// as black.
+pub fn load_luminance<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<f32>>, String> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)
+        .map_err(|err| image_error(&format!("cannot read {}: {err}", path.display())))?;
+
+    if bytes.starts_with(PNG_SIGNATURE) {
+        decode_png(&bytes)
+    } else if ppm::is_ppm(&bytes) {
+        ppm::decode(&bytes)
+    } else {
+        Err(image_error(&format!(
+            "{} is neither a PNG nor a PPM/PGM image",
+            path.display()
+        )))
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Vec<Vec<f32>>, String> {
+    let err = |err: png::DecodingError| image_error(&format!("invalid PNG: {err}"));
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(err)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(err)?;
+
+    let channels = info.color_type.samples();
+    let to_luma = |px: &[u8]| -> f32 {
+        let v = |i: usize| px[i] as f32 / 255.0;
+        match info.color_type {
+            png::ColorType::Grayscale => v(0),
+            png::ColorType::GrayscaleAlpha => v(0) * v(1),
+            png::ColorType::Rgba => luma(v(0), v(1), v(2)) * v(3),
+            _ => luma(v(0), v(1), v(2)),
+        }
+    };
+
+    Ok(buf[..info.buffer_size()]
+        .chunks(info.line_size)
+        .map(|line| line.chunks(channels).map(to_luma).collect())
+        .collect())
+}
+
+// Rec. 709 luma.
+pub(crate) fn luma(r: f32, g: f32, b: f32) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// Scales an image to the largest size that fits `rows` x `cols` while keeping its aspect
+/// ratio, averaging the pixels that fall into each cell.
+pub fn fit(image: &[Vec<f32>], rows: Rows, cols: Cols) -> Vec<Vec<f32>> {
+    let (h, w) = (image.len(), image.first().map_or(0, Vec::len));
+    if h == 0 || w == 0 {
+        return Vec::new();
+    }
+
+    let scale = (rows as f64 / h as f64).min(cols as f64 / w as f64);
+    let th = ((h as f64 * scale).round() as usize).clamp(1, rows);
+    let tw = ((w as f64 * scale).round() as usize).clamp(1, cols);
+    let span = |t: usize, n: usize, tn: usize| {
+        let lo = t * n / tn;
+        let hi = ((t + 1) * n).div_ceil(tn).max(lo + 1);
+        lo..hi.min(n)
+    };
+
+    (0..th)
+        .map(|ty| {
+            (0..tw)
+                .map(|tx| {
+                    let (ys, xs) = (span(ty, h, th), span(tx, w, tw));
+                    let count = (ys.len() * xs.len()) as f32;
+                    let sum: f32 = ys.flat_map(|y| xs.clone().map(move |x| image[y][x])).sum();
+                    sum / count
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Imports an image as a pattern no larger than `rows` x `cols`: pixels brighter than
+/// `threshold` become live cells.
+pub fn import_pattern<P: AsRef<Path>>(
+    path: P,
+    rows: Rows,
+    cols: Cols,
+    threshold: f32,
+) -> Result<Pattern, String> {
+    let image = fit(&load_luminance(path)?, rows, cols);
+    Ok(Pattern {
+        cells: image
+            .iter()
+            .map(|row| row.iter().map(|&v| (v > threshold) as u8).collect())
+            .collect(),
+        ..Default::default()
+    })
+}
+
+/// Imports an image as a `rows` x `cols` field whose values are the pixels' luminance, centred
+/// on an empty background.
+pub fn import_field<P: AsRef<Path>>(
+    path: P,
+    rows: Rows,
+    cols: Cols,
+) -> Result<Vec<Vec<f32>>, String> {
+    let image = fit(&load_luminance(path)?, rows, cols);
+    let mut field = vec![vec![0.0; cols]; rows];
+    let (h, w) = (image.len(), image.first().map_or(0, Vec::len));
+    let (r0, c0) = ((rows - h) / 2, (cols - w) / 2);
+
+    for (r, row) in image.iter().enumerate() {
+        field[r0 + r][c0..c0 + w].copy_from_slice(row);
+    }
+    Ok(field)
+}
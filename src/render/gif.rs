@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use ::gif::{Encoder, Repeat};
+
+use super::{image_error, Capture, CaptureOptions, Frame};
+
+/// Writes generations to an animated GIF, looping forever.
+///
+/// GIF frames hold at most 256 colours, so every pixel is mapped to the closest colour of the
+/// table the recorder was created with: the states of a rule, or samples along a palette.
+pub struct GifRecorder {
+    path: PathBuf,
+    encoder: Encoder<BufWriter<File>>,
+    colours: Vec<[u8; 3]>,
+    lookup: HashMap<[u8; 3], u8>,
+    width: usize,
+    height: usize,
+    options: CaptureOptions,
+    offered: usize,
+    recorded: usize,
+}
+
+impl GifRecorder {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        width: usize,
+        height: usize,
+        colours: &[[u8; 3]],
+        options: CaptureOptions,
+    ) -> Result<Self, String> {
+        let path = path.as_ref();
+        let err = |err: &dyn std::fmt::Display| {
+            image_error(&format!("cannot write {}: {err}", path.display()))
+        };
+        if width == 0 || height == 0 || width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(image_error(&format!(
+                "{width}x{height} frames do not fit in a GIF"
+            )));
+        }
+        if colours.is_empty() || colours.len() > 256 {
+            return Err(image_error("a GIF needs between 1 and 256 colours"));
+        }
+
+        let file = File::create(path).map_err(|e| err(&e))?;
+        let table: Vec<u8> = colours.iter().flatten().copied().collect();
+        let mut encoder = Encoder::new(BufWriter::new(file), width as u16, height as u16, &table)
+            .map_err(|e| err(&e))?;
+        encoder.set_repeat(Repeat::Infinite).map_err(|e| err(&e))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            encoder,
+            colours: colours.to_vec(),
+            lookup: HashMap::new(),
+            width,
+            height,
+            options,
+            offered: 0,
+            recorded: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn recorded(&self) -> usize {
+        self.recorded
+    }
+
+    /// Whether the requested number of frames has been recorded.
+    pub fn is_done(&self) -> bool {
+        self.options.frames.is_some_and(|n| self.recorded >= n)
+    }
+
+    /// Offers the next generation, which is recorded unless it is skipped or the recording is done.
+    pub fn offer(&mut self, frame: &Frame) -> Result<(), String> {
+        let skipped = !self.offered.is_multiple_of(self.options.skip.max(1));
+        self.offered += 1;
+        if skipped || self.is_done() {
+            return Ok(());
+        }
+        if (frame.width, frame.height) != (self.width, self.height) {
+            return Err(image_error(&format!(
+                "frame is {}x{} but the recording is {}x{}",
+                frame.width, frame.height, self.width, self.height
+            )));
+        }
+
+        let indices: Vec<u8> = frame
+            .pixels
+            .chunks_exact(3)
+            .map(|px| self.index_of([px[0], px[1], px[2]]))
+            .collect();
+        let gif_frame = ::gif::Frame {
+            width: self.width as u16,
+            height: self.height as u16,
+            // GIF delays are in hundredths of a second.
+            delay: (self.options.delay_ms / 10).clamp(1, u16::MAX as u32) as u16,
+            buffer: Cow::Owned(indices),
+            ..Default::default()
+        };
+        self.encoder
+            .write_frame(&gif_frame)
+            .map_err(|err| image_error(&format!("cannot write {}: {err}", self.path.display())))?;
+        self.recorded += 1;
+        Ok(())
+    }
+
+    /// Writes the end of the file; returns the number of frames recorded.
+    pub fn finish(self) -> Result<usize, String> {
+        let err = |err: std::io::Error| {
+            image_error(&format!("cannot write {}: {err}", self.path.display()))
+        };
+        self.encoder
+            .into_inner()
+            .and_then(|mut writer| writer.flush())
+            .map_err(err)?;
+        Ok(self.recorded)
+    }
+
+    fn index_of(&mut self, rgb: [u8; 3]) -> u8 {
+        if let Some(&i) = self.lookup.get(&rgb) {
+            return i;
+        }
+        let distance =
+            |c: &[u8; 3]| -> i32 { (0..3).map(|k| (c[k] as i32 - rgb[k] as i32).pow(2)).sum() };
+        let i = (0..self.colours.len())
+            .min_by_key(|&i| distance(&self.colours[i]))
+            .unwrap_or(0) as u8;
+        self.lookup.insert(rgb, i);
+        i
+    }
+}
+
+impl Capture for GifRecorder {
+    fn offer(&mut self, frame: &Frame) -> Result<(), String> {
+        GifRecorder::offer(self, frame)
+    }
+
+    fn is_done(&self) -> bool {
+        GifRecorder::is_done(self)
+    }
+
+    fn close(self) -> Result<String, String> {
+        let path = self.path.display().to_string();
+        let frames = self.finish()?;
+        Ok(format!("Recorded {frames} frames to {path}"))
+    }
+}
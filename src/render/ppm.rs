@@ -0,0 +1,89 @@
+use super::{image_error, luma};
+
+// Refuse headers that promise more pixels than any sane board needs.
+const MAX_PIXELS: usize = 1 << 28;
+
+/// Whether `bytes` start like a Netpbm PGM (`P2`, `P5`) or PPM (`P3`, `P6`) image.
+pub fn is_ppm(bytes: &[u8]) -> bool {
+    matches!(bytes, [b'P', b'2' | b'3' | b'5' | b'6', ..])
+}
+
+/// Decodes a plain or raw PGM/PPM image into luminance in `[0, 1]`.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Vec<f32>>, String> {
+    if !is_ppm(bytes) {
+        return Err(image_error("not a PGM/PPM image"));
+    }
+    let (plain, channels) = match bytes[1] {
+        b'2' => (true, 1),
+        b'3' => (true, 3),
+        b'5' => (false, 1),
+        _ => (false, 3),
+    };
+
+    let mut pos = 2;
+    let mut header = [0; 3];
+    for value in header.iter_mut() {
+        *value =
+            next_number(bytes, &mut pos).ok_or_else(|| image_error("invalid PGM/PPM header"))?;
+    }
+    let [width, height, max] = header;
+    if width == 0 || height == 0 || !(1..=65535).contains(&max) {
+        return Err(image_error("invalid PGM/PPM header"));
+    }
+    if width.saturating_mul(height) > MAX_PIXELS {
+        return Err(image_error(&format!("{width}x{height} image is too large")));
+    }
+
+    let n = width * height * channels;
+    let samples: Vec<usize> = if plain {
+        (0..n)
+            .map(|_| next_number(bytes, &mut pos))
+            .collect::<Option<_>>()
+            .ok_or_else(|| image_error("PGM/PPM image is truncated"))?
+    } else {
+        // A single whitespace byte separates the header from the raster.
+        let data = bytes.get(pos + 1..).unwrap_or_default();
+        let sample_size = if max < 256 { 1 } else { 2 };
+        if data.len() < n * sample_size {
+            return Err(image_error("PGM/PPM image is truncated"));
+        }
+        data.chunks_exact(sample_size)
+            .take(n)
+            .map(|s| s.iter().fold(0, |acc, &b| acc << 8 | b as usize))
+            .collect()
+    };
+
+    let v = |s: usize| s.min(max) as f32 / max as f32;
+    Ok(samples
+        .chunks(width * channels)
+        .map(|row| {
+            row.chunks(channels)
+                .map(|px| match px {
+                    [g] => v(*g),
+                    _ => luma(v(px[0]), v(px[1]), v(px[2])),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+// Next decimal number, skipping whitespace and `#` comments.
+fn next_number(bytes: &[u8], pos: &mut usize) -> Option<usize> {
+    loop {
+        match bytes.get(*pos)? {
+            b'#' => {
+                while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => *pos += 1,
+            _ => break,
+        }
+    }
+
+    let start = *pos;
+    while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&bytes[start..*pos]).ok()?.parse().ok()
+}
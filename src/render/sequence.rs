@@ -0,0 +1,64 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use super::{image_error, Frame};
+
+/// Writes frames as PNGs: numbered `frame_00000.png`, `frame_00001.png`, ... in a directory, or
+/// back to back on stdout when the directory is `-`.
+pub struct FrameSequence {
+    dir: Option<PathBuf>,
+    written: usize,
+}
+
+impl FrameSequence {
+    /// Creates the directory if it does not exist yet.
+    pub fn create(dir: &str) -> Result<Self, String> {
+        if dir == "-" {
+            return Ok(Self {
+                dir: None,
+                written: 0,
+            });
+        }
+
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir)
+            .map_err(|err| image_error(&format!("cannot create {}: {err}", dir.display())))?;
+        Ok(Self {
+            dir: Some(dir),
+            written: 0,
+        })
+    }
+
+    /// Whether frames go to stdout, which then cannot carry anything else.
+    pub fn is_stdout(&self) -> bool {
+        self.dir.is_none()
+    }
+
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Where the frames go, for messages.
+    pub fn describe(&self) -> String {
+        self.dir
+            .as_ref()
+            .map_or_else(|| "stdout".to_string(), |dir| dir.display().to_string())
+    }
+
+    pub fn write(&mut self, frame: &Frame) -> Result<(), String> {
+        match &self.dir {
+            Some(dir) => frame.save_png(dir.join(format!("frame_{:05}.png", self.written)))?,
+            None => {
+                let mut stdout = BufWriter::new(io::stdout().lock());
+                frame
+                    .write_png(&mut stdout)
+                    .map_err(|err| err.to_string())
+                    .and_then(|_| stdout.flush().map_err(|err| err.to_string()))
+                    .map_err(|err| image_error(&format!("cannot write to stdout: {err}")))?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+
+use super::{image_error, Capture, CaptureOptions, Frame};
+
+/// Raw video formats an encoder can read from a pipe.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StreamFormat {
+    /// YUV4MPEG2 with full-resolution (4:4:4) chroma.
+    Y4m,
+    /// Binary PPM (`P6`) images back to back.
+    Ppm,
+}
+
+impl StreamFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_lowercase().as_str() {
+            "y4m" => Ok(StreamFormat::Y4m),
+            "ppm" => Ok(StreamFormat::Ppm),
+            _ => Err(image_error(&format!(
+                "'{format}' is not a stream format, expected y4m or ppm"
+            ))),
+        }
+    }
+}
+
+/// Streams generations as raw video into a file, a named pipe or stdout (`-`), flushing every
+/// frame so an encoder on the other end keeps up with the run.
+pub struct FrameStream {
+    target: String,
+    writer: BufWriter<Box<dyn Write>>,
+    format: StreamFormat,
+    options: CaptureOptions,
+    size: Option<(usize, usize)>,
+    offered: usize,
+    streamed: usize,
+}
+
+impl FrameStream {
+    /// Opens `target` for writing; a named pipe blocks here until its reader opens it.
+    pub fn open(
+        target: &str,
+        format: StreamFormat,
+        options: CaptureOptions,
+    ) -> Result<Self, String> {
+        let writer: Box<dyn Write> = if target == "-" {
+            Box::new(io::stdout())
+        } else {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(target)
+                .map_err(|err| image_error(&format!("cannot open {target}: {err}")))?;
+            Box::new(file)
+        };
+
+        Ok(Self {
+            target: target.to_string(),
+            writer: BufWriter::new(writer),
+            format,
+            options,
+            size: None,
+            offered: 0,
+            streamed: 0,
+        })
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        self.target == "-"
+    }
+
+    pub fn streamed(&self) -> usize {
+        self.streamed
+    }
+
+    /// Where the frames go, for messages.
+    pub fn describe(&self) -> &str {
+        if self.is_stdout() {
+            "stdout"
+        } else {
+            &self.target
+        }
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let (w, h) = (frame.width, frame.height);
+        match self.format {
+            StreamFormat::Ppm => {
+                write!(self.writer, "P6\n{w} {h}\n255\n")?;
+                self.writer.write_all(&frame.pixels)?;
+            }
+            StreamFormat::Y4m => {
+                if self.streamed == 0 {
+                    // Frames per second as the exact fraction 1000 / delay.
+                    let delay = self.options.delay_ms.max(1);
+                    let d = gcd(1000, delay);
+                    writeln!(
+                        self.writer,
+                        "YUV4MPEG2 W{w} H{h} F{}:{} Ip A1:1 C444",
+                        1000 / d,
+                        delay / d
+                    )?;
+                }
+                self.writer.write_all(b"FRAME\n")?;
+                let yuv: Vec<[u8; 3]> = frame.pixels.chunks_exact(3).map(to_yuv).collect();
+                for plane in 0..3 {
+                    let samples: Vec<u8> = yuv.iter().map(|px| px[plane]).collect();
+                    self.writer.write_all(&samples)?;
+                }
+            }
+        }
+        self.writer.flush()
+    }
+}
+
+impl Capture for FrameStream {
+    fn offer(&mut self, frame: &Frame) -> Result<(), String> {
+        let skipped = !self.offered.is_multiple_of(self.options.skip.max(1));
+        self.offered += 1;
+        if skipped || self.is_done() {
+            return Ok(());
+        }
+
+        let size = (frame.width, frame.height);
+        // A Y4M stream fixes its frame size in the header.
+        if let Some((w, h)) = self.size.filter(|&s| s != size) {
+            if self.format == StreamFormat::Y4m {
+                return Err(image_error(&format!(
+                    "frame is {}x{} but the stream is {w}x{h}",
+                    size.0, size.1
+                )));
+            }
+        }
+        self.size = Some(size);
+
+        self.write_frame(frame)
+            .map_err(|err| image_error(&format!("cannot write to {}: {err}", self.describe())))?;
+        self.streamed += 1;
+        Ok(())
+    }
+
+    fn is_done(&self) -> bool {
+        self.options.frames.is_some_and(|n| self.streamed >= n)
+    }
+
+    fn close(mut self) -> Result<String, String> {
+        self.writer
+            .flush()
+            .map_err(|err| image_error(&format!("cannot write to {}: {err}", self.describe())))?;
+        Ok(format!(
+            "Streamed {} frames to {}",
+            self.streamed,
+            self.describe()
+        ))
+    }
+}
+
+// BT.601 studio-swing YCbCr, as encoders expect from Y4M.
+fn to_yuv(px: &[u8]) -> [u8; 3] {
+    let (r, g, b) = (px[0] as i32, px[1] as i32, px[2] as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    [y as u8, u as u8, v as u8]
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
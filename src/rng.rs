@@ -0,0 +1,25 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+pub type Rng = ChaCha8Rng;
+
+/// Independent streams derived from one seed, so that e.g. drawing a soup
+/// does not shift the random numbers the engine sees afterwards.
+pub const SOUP_STREAM: u64 = 0;
+pub const ENGINE_STREAM: u64 = 1;
+pub const EVOLUTION_STREAM: u64 = 2;
+
+/// Returns the seed to use for a run, picking a fresh one when none was given.
+pub fn resolve_seed(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(rand::random)
+}
+
+pub fn from_seed(seed: u64) -> Rng {
+    Rng::seed_from_u64(seed)
+}
+
+pub fn from_seed_stream(seed: u64, stream: u64) -> Rng {
+    let mut rng = from_seed(seed);
+    rng.set_stream(stream);
+    rng
+}
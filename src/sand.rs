@@ -0,0 +1,399 @@
+use rand::seq::SliceRandom;
+use rand::Rng as _;
+
+use crate::rng::{self, Rng};
+
+const FIRE_BURNOUT: f64 = 0.08;
+const FIRE_SPREAD: f64 = 0.3;
+const FIRE_RISE: f64 = 0.5;
+const SMOKE_FADE: f64 = 0.02;
+const PLANT_GROWTH: f64 = 0.04;
+
+type Rows = usize;
+type Cols = usize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Material {
+    Empty,
+    Sand,
+    Water,
+    Wall,
+    Fire,
+    Smoke,
+    Plant,
+}
+
+impl Material {
+    pub const ALL: [Material; 7] = [
+        Material::Empty,
+        Material::Sand,
+        Material::Water,
+        Material::Wall,
+        Material::Fire,
+        Material::Smoke,
+        Material::Plant,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Material::Empty => "Empty",
+            Material::Sand => "Sand",
+            Material::Water => "Water",
+            Material::Wall => "Wall",
+            Material::Fire => "Fire",
+            Material::Smoke => "Smoke",
+            Material::Plant => "Plant",
+        }
+    }
+
+    pub fn as_color_hex(&self) -> u32 {
+        match self {
+            Material::Empty => 0x000000FF,
+            Material::Sand => 0xC2B280FF,
+            Material::Water => 0x1E64DCFF,
+            Material::Wall => 0x808080FF,
+            Material::Fire => 0xFF5A0AFF,
+            Material::Smoke => 0x505050FF,
+            Material::Plant => 0x22A02EFF,
+        }
+    }
+
+    // Solids never move; everything else is swapped by comparing densities.
+    fn density(&self) -> Option<u8> {
+        match self {
+            Material::Wall | Material::Plant => None,
+            Material::Smoke | Material::Fire => Some(0),
+            Material::Empty => Some(1),
+            Material::Water => Some(5),
+            Material::Sand => Some(10),
+        }
+    }
+
+    fn is_flammable(&self) -> bool {
+        *self == Material::Plant
+    }
+}
+
+pub struct SandBoard {
+    cells: Vec<Vec<Material>>,
+    moved: Vec<Vec<bool>>,
+    order: Vec<(Rows, Cols)>,
+    rng: Rng,
+}
+
+impl SandBoard {
+    pub fn new(rows: Rows, cols: Cols, seed: u64) -> Self {
+        let order = (0..rows)
+            .flat_map(|r| (0..cols).map(move |c| (r, c)))
+            .collect();
+
+        Self {
+            cells: vec![vec![Material::Empty; cols]; rows],
+            moved: vec![vec![false; cols]; rows],
+            order,
+            rng: rng::from_seed(seed),
+        }
+    }
+
+    pub fn cells(&self) -> &[Vec<Material>] {
+        &self.cells
+    }
+
+    pub fn rows(&self) -> Rows {
+        self.cells.len()
+    }
+
+    pub fn cols(&self) -> Cols {
+        self.cells[0].len()
+    }
+
+    pub fn clear(&mut self) {
+        for row in self.cells.iter_mut() {
+            row.fill(Material::Empty);
+        }
+    }
+
+    pub fn paint(&mut self, row: Rows, col: Cols, radius: usize, material: Material) {
+        let r0 = row.saturating_sub(radius);
+        let c0 = col.saturating_sub(radius);
+        let r1 = (row + radius).min(self.rows() - 1);
+        let c1 = (col + radius).min(self.cols() - 1);
+
+        for r in r0..=r1 {
+            for c in c0..=c1 {
+                let (dr, dc) = (r.abs_diff(row), c.abs_diff(col));
+                if dr * dr + dc * dc <= radius * radius {
+                    self.cells[r][c] = material;
+                }
+            }
+        }
+    }
+
+    pub fn step(&mut self) {
+        for row in self.moved.iter_mut() {
+            row.fill(false);
+        }
+
+        let mut order = std::mem::take(&mut self.order);
+        order.shuffle(&mut self.rng);
+
+        for &(r, c) in order.iter() {
+            if self.moved[r][c] {
+                continue;
+            }
+
+            match self.cells[r][c] {
+                Material::Empty | Material::Wall => {}
+                Material::Sand => self.update_sand(r, c),
+                Material::Water => self.update_water(r, c),
+                Material::Fire => self.update_fire(r, c),
+                Material::Smoke => self.update_smoke(r, c),
+                Material::Plant => self.update_plant(r, c),
+            }
+        }
+
+        self.order = order;
+    }
+
+    fn update_sand(&mut self, r: Rows, c: Cols) {
+        let side = self.random_side();
+        let _ = self.try_sink(r, c, 1, 0)
+            || self.try_sink(r, c, 1, side)
+            || self.try_sink(r, c, 1, -side);
+    }
+
+    fn update_water(&mut self, r: Rows, c: Cols) {
+        let side = self.random_side();
+        let _ = self.try_sink(r, c, 1, 0)
+            || self.try_sink(r, c, 1, side)
+            || self.try_sink(r, c, 1, -side)
+            || self.try_sink(r, c, 0, side)
+            || self.try_sink(r, c, 0, -side);
+    }
+
+    fn update_smoke(&mut self, r: Rows, c: Cols) {
+        if self.rng.gen_bool(SMOKE_FADE) {
+            self.cells[r][c] = Material::Empty;
+            return;
+        }
+
+        let side = self.random_side();
+        let _ = self.try_rise(r, c, -1, 0)
+            || self.try_rise(r, c, -1, side)
+            || self.try_rise(r, c, -1, -side)
+            || self.try_rise(r, c, 0, side);
+    }
+
+    fn update_fire(&mut self, r: Rows, c: Cols) {
+        let mut quenched = false;
+        for (nr, nc) in self.neighbours(r, c) {
+            match self.cells[nr][nc] {
+                Material::Water => quenched = true,
+                m if m.is_flammable() && self.rng.gen_bool(FIRE_SPREAD) => {
+                    self.cells[nr][nc] = Material::Fire;
+                    self.moved[nr][nc] = true;
+                }
+                _ => {}
+            }
+        }
+
+        if quenched || self.rng.gen_bool(FIRE_BURNOUT) {
+            self.cells[r][c] = Material::Smoke;
+        } else if self.rng.gen_bool(FIRE_RISE) {
+            let side = self.random_side();
+            let _ = self.try_rise(r, c, -1, 0) || self.try_rise(r, c, -1, side);
+        }
+    }
+
+    fn update_plant(&mut self, r: Rows, c: Cols) {
+        for (nr, nc) in self.neighbours(r, c) {
+            if self.cells[nr][nc] == Material::Water && self.rng.gen_bool(PLANT_GROWTH) {
+                self.cells[nr][nc] = Material::Plant;
+                self.moved[nr][nc] = true;
+            }
+        }
+    }
+
+    // Moves the cell into a lighter neighbour, e.g. sand falling through water.
+    fn try_sink(&mut self, r: Rows, c: Cols, dr: isize, dc: isize) -> bool {
+        self.try_swap(r, c, dr, dc, |this, other| this > other)
+    }
+
+    // Moves the cell into a heavier, non-solid neighbour, e.g. smoke rising through air.
+    fn try_rise(&mut self, r: Rows, c: Cols, dr: isize, dc: isize) -> bool {
+        self.try_swap(r, c, dr, dc, |this, other| this < other)
+    }
+
+    fn try_swap<F>(&mut self, r: Rows, c: Cols, dr: isize, dc: isize, can_swap: F) -> bool
+    where
+        F: Fn(u8, u8) -> bool,
+    {
+        let Some((nr, nc)) = self.offset(r, c, dr, dc) else {
+            return false;
+        };
+        if self.moved[nr][nc] {
+            return false;
+        }
+
+        let (this, other) = (self.cells[r][c], self.cells[nr][nc]);
+        match (this.density(), other.density()) {
+            (Some(a), Some(b)) if can_swap(a, b) => {
+                self.cells[r][c] = other;
+                self.cells[nr][nc] = this;
+                self.moved[r][c] = true;
+                self.moved[nr][nc] = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn offset(&self, r: Rows, c: Cols, dr: isize, dc: isize) -> Option<(Rows, Cols)> {
+        let nr = r.checked_add_signed(dr)?;
+        let nc = c.checked_add_signed(dc)?;
+        (nr < self.rows() && nc < self.cols()).then_some((nr, nc))
+    }
+
+    fn neighbours(&self, r: Rows, c: Cols) -> Vec<(Rows, Cols)> {
+        let mut n = Vec::with_capacity(8);
+        for dr in -1..=1 {
+            for dc in -1..=1 {
+                if dr != 0 || dc != 0 {
+                    n.extend(self.offset(r, c, dr, dc));
+                }
+            }
+        }
+        n
+    }
+
+    fn random_side(&mut self) -> isize {
+        if self.rng.gen() {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(rows: &[&str], seed: u64) -> SandBoard {
+        let mut board = SandBoard::new(rows.len(), rows[0].len(), seed);
+        for (r, row) in rows.iter().enumerate() {
+            for (c, ch) in row.chars().enumerate() {
+                board.cells[r][c] = match ch {
+                    '.' => Material::Empty,
+                    's' => Material::Sand,
+                    'w' => Material::Water,
+                    '#' => Material::Wall,
+                    'f' => Material::Fire,
+                    '~' => Material::Smoke,
+                    'p' => Material::Plant,
+                    _ => panic!("unknown material {ch}"),
+                };
+            }
+        }
+        board
+    }
+
+    fn count(board: &SandBoard, material: Material) -> usize {
+        board
+            .cells()
+            .iter()
+            .flatten()
+            .filter(|&&m| m == material)
+            .count()
+    }
+
+    #[test]
+    fn grains_fall_one_row_per_step() {
+        let mut board = fill(&[".s.", "...", "...", "..."], 1);
+        for row in 1..4 {
+            board.step();
+            assert_eq!(board.cells()[row][1], Material::Sand);
+            assert_eq!(count(&board, Material::Sand), 1);
+        }
+        // Resting on the floor.
+        board.step();
+        assert_eq!(board.cells()[3][1], Material::Sand);
+    }
+
+    #[test]
+    fn heavier_materials_sink_through_lighter_ones() {
+        // Walled in on the sides so nothing can slide away.
+        let mut board = fill(&["#s#", "#w#", "#w#"], 2);
+        board.step();
+        assert_eq!(board.cells()[1][1], Material::Sand);
+        assert_eq!(board.cells()[0][1], Material::Water);
+        board.step();
+        assert_eq!(board.cells()[2][1], Material::Sand);
+        for _ in 0..5 {
+            board.step();
+        }
+        assert_eq!(board.cells()[2][1], Material::Sand);
+        assert_eq!(count(&board, Material::Water), 2);
+
+        // Water rests on sand and solids hold anything up.
+        let mut board = fill(&["w#s", "s#p", "###"], 3);
+        let before = board.cells().to_vec();
+        board.step();
+        assert_eq!(board.cells(), before);
+    }
+
+    #[test]
+    fn smoke_rises_through_air() {
+        let mut board = fill(&["...", "...", ".~."], 4);
+        // Smoke may fade out on any step, but never sinks.
+        for _ in 0..2 {
+            board.step();
+        }
+        let row = board
+            .cells()
+            .iter()
+            .position(|row| row.contains(&Material::Smoke));
+        assert!(matches!(row, Some(0) | None), "{row:?}");
+    }
+
+    #[test]
+    fn water_spreads_to_the_sides() {
+        let mut board = fill(&["w....", "#####"], 5);
+        for _ in 0..20 {
+            board.step();
+        }
+        assert_eq!(count(&board, Material::Water), 1);
+        assert_ne!(board.cells()[0][0], Material::Water);
+    }
+
+    #[test]
+    fn every_cell_is_updated_once_in_a_random_order() {
+        let mut board = fill(&["ss..", "s.s.", "....", "...."], 6);
+        let first = board.order.clone();
+        board.step();
+        let mut visited = board.order.clone();
+        assert_ne!(visited, first);
+        visited.sort();
+        assert_eq!(visited, first);
+
+        // No grain moves more than a row in a step, even when the cell below was visited first.
+        let mut falling = fill(&["s", "s", "s", ".", ".", "."], 7);
+        falling.step();
+        assert_eq!(count(&falling, Material::Sand), 3);
+        assert_eq!(falling.cells()[5][0], Material::Empty);
+    }
+
+    #[test]
+    fn steps_are_reproduced_by_their_seed() {
+        let rows = ["..s.w.s..", ".sw.f.ws.", "..pp.pp..", "#.......#"];
+        let run = |seed| {
+            let mut board = fill(&rows, seed);
+            for _ in 0..30 {
+                board.step();
+            }
+            board.cells().to_vec()
+        };
+        assert_eq!(run(8), run(8));
+        assert!((9..20).any(|seed| run(seed) != run(8)));
+    }
+}
@@ -0,0 +1,524 @@
+use std::ops::Not;
+
+/// A boolean variable of a [`Solver`], numbered from 0.
+pub type Var = usize;
+
+// Conflicts between restarts are this many times the Luby sequence.
+const RESTART_UNIT: u64 = 100;
+
+// Activities of the variables in recent conflicts fade by this much at each conflict.
+const ACTIVITY_DECAY: f64 = 0.95;
+
+// Learnt clauses kept before the longest half are dropped, and how much that grows each time.
+const FIRST_REDUCE: usize = 2000;
+const REDUCE_GROWTH: f64 = 1.1;
+
+/// A variable or its negation.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn new(var: Var, positive: bool) -> Self {
+        Lit((var as u32) << 1 | !positive as u32)
+    }
+
+    pub fn var(self) -> Var {
+        (self.0 >> 1) as Var
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 & 1 == 0
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// What a [`Solver`] found.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Answer {
+    /// A value for every variable that satisfies all clauses.
+    Sat(Vec<bool>),
+    /// No assignment satisfies all clauses.
+    Unsat,
+    /// The conflict budget ran out first.
+    Unknown,
+}
+
+struct Clause {
+    lits: Vec<Lit>,
+    learnt: bool,
+    deleted: bool,
+}
+
+/// A small CDCL SAT solver: two watched literals, first-UIP clause learning, variable activities
+/// with phase saving, Luby restarts and a bounded store of learnt clauses.
+///
+/// Decisions scan every variable for the most active one, which suits the few hundred or thousand
+/// variables of the problems it is used for.
+pub struct Solver {
+    clauses: Vec<Clause>,
+    // Clauses watching each literal, indexed by `Lit::index`.
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_limits: Vec<usize>,
+    head: usize,
+    activity: Vec<f64>,
+    bump: f64,
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+    learnts: usize,
+    max_learnts: f64,
+    conflicts: u64,
+    inconsistent: bool,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            clauses: Vec::new(),
+            watches: Vec::new(),
+            values: Vec::new(),
+            levels: Vec::new(),
+            reasons: Vec::new(),
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            head: 0,
+            activity: Vec::new(),
+            bump: 1.0,
+            phases: Vec::new(),
+            seen: Vec::new(),
+            learnts: 0,
+            max_learnts: FIRST_REDUCE as f64,
+            conflicts: 0,
+            inconsistent: false,
+        }
+    }
+
+    /// Adds a variable, which is false when nothing forces it either way.
+    pub fn new_var(&mut self) -> Var {
+        let var = self.values.len();
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.phases.push(false);
+        self.seen.push(false);
+        self.watches.extend([Vec::new(), Vec::new()]);
+        var
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Original clauses added so far, not counting those already satisfied.
+    pub fn num_clauses(&self) -> usize {
+        self.clauses.iter().filter(|c| !c.learnt).count()
+    }
+
+    /// Conflicts met by every `solve` so far.
+    pub fn conflicts(&self) -> u64 {
+        self.conflicts
+    }
+
+    /// Requires at least one of `lits` to hold.
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        if self.inconsistent {
+            return;
+        }
+        let mut clause: Vec<Lit> = Vec::with_capacity(lits.len());
+        for &lit in lits {
+            match self.value(lit) {
+                Some(true) => return,
+                Some(false) => {}
+                None if clause.contains(&!lit) => return,
+                None if !clause.contains(&lit) => clause.push(lit),
+                None => {}
+            }
+        }
+        match clause.len() {
+            0 => self.inconsistent = true,
+            1 => {
+                self.assign(clause[0], None);
+                if self.propagate().is_some() {
+                    self.inconsistent = true;
+                }
+            }
+            _ => {
+                self.attach(clause, false);
+            }
+        }
+    }
+
+    /// Looks for an assignment satisfying every clause, giving up after `max_conflicts`
+    /// conflicts if given.
+    pub fn solve(&mut self, max_conflicts: Option<u64>) -> Answer {
+        if self.inconsistent {
+            return Answer::Unsat;
+        }
+        let start = self.conflicts;
+        let mut restarts = 0;
+
+        loop {
+            let budget = luby(restarts) * RESTART_UNIT;
+            restarts += 1;
+            let mut conflicts = 0;
+
+            loop {
+                if let Some(conflict) = self.propagate() {
+                    self.conflicts += 1;
+                    conflicts += 1;
+                    if self.trail_limits.is_empty() {
+                        self.inconsistent = true;
+                        return Answer::Unsat;
+                    }
+                    let (learnt, level) = self.analyze(conflict);
+                    self.backtrack(level);
+                    match learnt.len() {
+                        1 => self.assign(learnt[0], None),
+                        _ => {
+                            let first = learnt[0];
+                            let index = self.attach(learnt, true);
+                            self.assign(first, Some(index));
+                        }
+                    }
+                    self.bump /= ACTIVITY_DECAY;
+                    if max_conflicts.is_some_and(|max| self.conflicts - start >= max) {
+                        self.backtrack(0);
+                        return Answer::Unknown;
+                    }
+                    continue;
+                }
+
+                if conflicts >= budget {
+                    self.backtrack(0);
+                    break;
+                }
+                if self.learnts as f64 >= self.max_learnts + self.trail.len() as f64 {
+                    self.reduce();
+                }
+                match self.pick() {
+                    Some(var) => {
+                        self.trail_limits.push(self.trail.len());
+                        self.assign(Lit::new(var, self.phases[var]), None);
+                    }
+                    None => {
+                        let model = self.values.iter().map(|v| v.unwrap_or(false)).collect();
+                        self.backtrack(0);
+                        return Answer::Sat(model);
+                    }
+                }
+            }
+        }
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        lit_value(&self.values, lit)
+    }
+
+    fn level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.values[var] = Some(lit.is_positive());
+        self.levels[var] = self.level();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    // Stores a clause of at least two literals, watching its first two.
+    fn attach(&mut self, lits: Vec<Lit>, learnt: bool) -> usize {
+        let index = self.clauses.len();
+        self.watches[lits[0].index()].push(index);
+        self.watches[lits[1].index()].push(index);
+        self.clauses.push(Clause {
+            lits,
+            learnt,
+            deleted: false,
+        });
+        if learnt {
+            self.learnts += 1;
+        }
+        index
+    }
+
+    // Assigns everything the trail implies; returns a clause left with every literal false.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let falsified = !self.trail[self.head];
+            self.head += 1;
+
+            let mut watching = std::mem::take(&mut self.watches[falsified.index()]);
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watching.len() {
+                let index = watching[i];
+                let clause = &mut self.clauses[index];
+                if clause.deleted {
+                    watching.swap_remove(i);
+                    continue;
+                }
+                // The falsified literal goes second, so the first is the one that may be implied.
+                if clause.lits[0] == falsified {
+                    clause.lits.swap(0, 1);
+                }
+                let first = clause.lits[0];
+                if lit_value(&self.values, first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                let replacement = (2..clause.lits.len())
+                    .find(|&k| lit_value(&self.values, clause.lits[k]) != Some(false));
+                if let Some(k) = replacement {
+                    clause.lits.swap(1, k);
+                    self.watches[clause.lits[1].index()].push(index);
+                    watching.swap_remove(i);
+                    continue;
+                }
+                if lit_value(&self.values, first) == Some(false) {
+                    conflict = Some(index);
+                    break;
+                }
+                self.assign(first, Some(index));
+                i += 1;
+            }
+            self.watches[falsified.index()].extend(watching);
+
+            if conflict.is_some() {
+                self.head = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // Learns the first-UIP clause of a conflict, with its asserting literal first, and returns
+    // it with the level to go back to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let level = self.level();
+        let mut learnt = vec![Lit(0)];
+        let mut pending = 0;
+        let mut implied: Option<Lit> = None;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+
+        loop {
+            for k in 0..self.clauses[clause].lits.len() {
+                let lit = self.clauses[clause].lits[k];
+                let var = lit.var();
+                if Some(lit) == implied || self.seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                self.seen[var] = true;
+                self.bump_activity(var);
+                if self.levels[var] == level {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[lit.var()] = false;
+            implied = Some(lit);
+            pending -= 1;
+            if pending == 0 {
+                break;
+            }
+            clause = self.reasons[lit.var()].expect("implied literals have a reason");
+        }
+        learnt[0] = !implied.expect("a conflict has a literal at its level");
+
+        // Literals implied by others of the clause add nothing.
+        let redundant: Vec<bool> = learnt
+            .iter()
+            .map(|&lit| match self.reasons[lit.var()] {
+                Some(reason) if lit != learnt[0] => self.clauses[reason].lits[1..]
+                    .iter()
+                    .all(|&other| self.seen[other.var()] || self.levels[other.var()] == 0),
+                _ => false,
+            })
+            .collect();
+        for &lit in learnt.iter() {
+            self.seen[lit.var()] = false;
+        }
+        let mut learnt: Vec<Lit> = learnt
+            .into_iter()
+            .zip(redundant)
+            .filter(|&(_, redundant)| !redundant)
+            .map(|(lit, _)| lit)
+            .collect();
+
+        // The deepest of the other literals is watched too, so the clause wakes up with it.
+        let mut back_to = 0;
+        if learnt.len() > 1 {
+            let deepest = (1..learnt.len())
+                .max_by_key(|&k| self.levels[learnt[k].var()])
+                .unwrap();
+            learnt.swap(1, deepest);
+            back_to = self.levels[learnt[1].var()];
+        }
+        (learnt, back_to)
+    }
+
+    fn bump_activity(&mut self, var: Var) {
+        self.activity[var] += self.bump;
+        if self.activity[var] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.bump *= 1e-100;
+        }
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.level() <= level {
+            return;
+        }
+        let keep = self.trail_limits[level];
+        for lit in self.trail.drain(keep..) {
+            let var = lit.var();
+            self.values[var] = None;
+            self.reasons[var] = None;
+            self.phases[var] = lit.is_positive();
+        }
+        self.trail_limits.truncate(level);
+        self.head = self.trail.len();
+    }
+
+    // The unassigned variable most involved in recent conflicts.
+    fn pick(&self) -> Option<Var> {
+        (0..self.values.len())
+            .filter(|&var| self.values[var].is_none())
+            .max_by(|&a, &b| {
+                self.activity[a]
+                    .total_cmp(&self.activity[b])
+                    .then(b.cmp(&a))
+            })
+    }
+
+    // Drops the longer half of the learnt clauses that are not the reason of an assignment.
+    fn reduce(&mut self) {
+        let mut candidates: Vec<usize> = (0..self.clauses.len())
+            .filter(|&index| {
+                let clause = &self.clauses[index];
+                clause.learnt
+                    && !clause.deleted
+                    && clause.lits.len() > 2
+                    && self.reasons[clause.lits[0].var()] != Some(index)
+            })
+            .collect();
+        candidates.sort_by_key(|&index| std::cmp::Reverse(self.clauses[index].lits.len()));
+        for &index in candidates.iter().take(candidates.len() / 2) {
+            let clause = &mut self.clauses[index];
+            clause.deleted = true;
+            clause.lits = Vec::new();
+            self.learnts -= 1;
+        }
+        self.max_learnts *= REDUCE_GROWTH;
+    }
+}
+
+fn lit_value(values: &[Option<bool>], lit: Lit) -> Option<bool> {
+    values[lit.var()].map(|value| value == lit.is_positive())
+}
+
+// The Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, ... at `i`.
+fn luby(mut i: u64) -> u64 {
+    let (mut size, mut power) = (1, 0);
+    while size < i + 1 {
+        power += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        power -= 1;
+        i %= size;
+    }
+    1 << power
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng as _;
+
+    use super::*;
+    use crate::rng;
+
+    fn satisfies(clauses: &[Vec<Lit>], model: &[bool]) -> bool {
+        clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|lit| model[lit.var()] == lit.is_positive())
+        })
+    }
+
+    #[test]
+    fn agrees_with_brute_force_on_random_3_sat() {
+        let mut rng = rng::from_seed(1);
+        let (mut sat, mut unsat) = (0, 0);
+        for _ in 0..300 {
+            let vars = rng.gen_range(3..=12);
+            // Around 4.26 clauses a variable, where random 3-SAT is as likely SAT as not.
+            let clauses: Vec<Vec<Lit>> = (0..rng.gen_range(vars * 3..=vars * 6))
+                .map(|_| {
+                    (0..3)
+                        .map(|_| Lit::new(rng.gen_range(0..vars), rng.gen_bool(0.5)))
+                        .collect()
+                })
+                .collect();
+
+            let mut solver = Solver::new();
+            for _ in 0..vars {
+                solver.new_var();
+            }
+            for clause in clauses.iter() {
+                solver.add_clause(clause);
+            }
+            let brute = (0..1u32 << vars).any(|bits| {
+                let model: Vec<bool> = (0..vars).map(|v| bits & 1 << v != 0).collect();
+                satisfies(&clauses, &model)
+            });
+
+            match solver.solve(None) {
+                Answer::Sat(model) => {
+                    assert!(brute, "found a model of an unsatisfiable instance");
+                    assert!(satisfies(&clauses, &model));
+                    sat += 1;
+                }
+                Answer::Unsat => {
+                    assert!(!brute, "missed a model");
+                    unsat += 1;
+                }
+                Answer::Unknown => panic!("gave up without a conflict limit"),
+            }
+        }
+        assert!(sat > 0 && unsat > 0);
+    }
+}
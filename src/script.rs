@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rhai::{Array, Dynamic, Scope, AST};
+
+use crate::golly::{self, Neighbourhood};
+use crate::life::rule_error;
+
+// Rules with at most this many neighbourhoods (4 states over 9 cells) are tabulated up front.
+const MAX_LUT: usize = 1 << 18;
+// Distinct neighbourhoods remembered before the cache is flushed, for rules too big to tabulate.
+const MAX_CACHED: usize = 1 << 20;
+// Stops a script that never returns from hanging the simulation.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A transition function written in Rhai.
+///
+/// The script defines `fn next(state, neighbours)`, where `neighbours` holds the 8 surrounding
+/// states clockwise from north, and returns the next state. An optional `fn states()` gives the
+/// number of states, 2 by default. Results are cached, so `next` must be deterministic.
+#[derive(Debug)]
+pub struct ScriptRule {
+    name: String,
+    path: Option<PathBuf>,
+    n_states: usize,
+    engine: rhai::Engine,
+    ast: AST,
+    lut: Option<Vec<u8>>,
+    cache: HashMap<[u8; 9], u8>,
+    colors: Vec<u32>,
+    error: Option<String>,
+}
+
+impl ScriptRule {
+    pub fn parse(name: &str, source: &str) -> Result<Self, String> {
+        let engine = script_engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|err| rule_error(&format!("{name}: {err}")))?;
+
+        let defines = |f: &str, arity: usize| {
+            ast.iter_functions()
+                .any(|meta| meta.name == f && meta.params.len() == arity)
+        };
+        if !defines("next", 2) {
+            return Err(rule_error(&format!(
+                "{name} does not define fn next(state, neighbours)"
+            )));
+        }
+
+        let n_states = if defines("states", 0) {
+            let n = engine
+                .call_fn::<i64>(&mut Scope::new(), &ast, "states", ())
+                .map_err(|err| rule_error(&format!("{name}: {err}")))?;
+            usize::try_from(n)
+                .ok()
+                .filter(|n| (2..=256).contains(n))
+                .ok_or_else(|| rule_error(&format!("{name}: states() must be in 2..=256")))?
+        } else {
+            2
+        };
+
+        let mut rule = Self {
+            name: name.to_string(),
+            path: None,
+            n_states,
+            engine,
+            ast,
+            lut: None,
+            cache: HashMap::new(),
+            colors: golly::default_colors(n_states),
+            error: None,
+        };
+
+        match n_states.checked_pow(9).filter(|&size| size <= MAX_LUT) {
+            Some(size) => {
+                let lut = (0..size)
+                    .map(|i| rule.eval(&neighbourhood_at(i, n_states)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                rule.lut = Some(lut);
+            }
+            None => {
+                rule.eval(&[0; 9])?;
+            }
+        }
+        Ok(rule)
+    }
+
+    /// Loads a `.rhai` script, named after its file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|err| rule_error(&format!("cannot read {}: {err}", path.display())))?;
+        let name = path
+            .file_stem()
+            .map_or(String::new(), |s| s.to_string_lossy().to_string());
+        let mut rule = Self::parse(&name, &source)?;
+        rule.path = Some(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        Ok(rule)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The file the script was loaded from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn n_states(&self) -> usize {
+        self.n_states
+    }
+
+    pub fn color_hex(&self, state: u8) -> u32 {
+        self.colors
+            .get(state as usize)
+            .copied()
+            .unwrap_or(0xFF00FFFF)
+    }
+
+    /// The first error the script raised while running; the cells it failed on kept their state.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn next(&mut self, n: &Neighbourhood) -> u8 {
+        let mut key = [n.centre; 9];
+        key[1..].copy_from_slice(&n.around);
+        if key.iter().any(|&s| s as usize >= self.n_states) {
+            return n.centre;
+        }
+
+        if let Some(lut) = &self.lut {
+            let index = key
+                .iter()
+                .fold(0, |acc, &s| acc * self.n_states + s as usize);
+            return lut[index];
+        }
+        if let Some(&next) = self.cache.get(&key) {
+            return next;
+        }
+
+        match self.eval(&key) {
+            Ok(next) => {
+                if self.cache.len() >= MAX_CACHED {
+                    self.cache.clear();
+                }
+                self.cache.insert(key, next);
+                next
+            }
+            Err(err) => {
+                self.error.get_or_insert(err);
+                n.centre
+            }
+        }
+    }
+
+    fn eval(&self, key: &[u8; 9]) -> Result<u8, String> {
+        let around: Array = key[1..].iter().map(|&s| Dynamic::from(s as i64)).collect();
+        let next = self
+            .engine
+            .call_fn::<i64>(
+                &mut Scope::new(),
+                &self.ast,
+                "next",
+                (key[0] as i64, around),
+            )
+            .map_err(|err| rule_error(&format!("{}: {err}", self.name)))?;
+
+        u8::try_from(next)
+            .ok()
+            .filter(|&s| (s as usize) < self.n_states)
+            .ok_or_else(|| {
+                rule_error(&format!(
+                    "{}: next returned {next}, which is not one of its {} states",
+                    self.name, self.n_states
+                ))
+            })
+    }
+}
+
+// A Rhai engine is not `Clone`; a fresh one runs the same compiled script.
+impl Clone for ScriptRule {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            n_states: self.n_states,
+            engine: script_engine(),
+            ast: self.ast.clone(),
+            lut: self.lut.clone(),
+            cache: self.cache.clone(),
+            colors: self.colors.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ScriptRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+fn script_engine() -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine
+}
+
+// The `index`-th neighbourhood in lookup table order, centre first.
+fn neighbourhood_at(mut index: usize, n_states: usize) -> [u8; 9] {
+    let mut key = [0; 9];
+    for state in key.iter_mut().rev() {
+        *state = (index % n_states) as u8;
+        index /= n_states;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::life::{Cell, Rule};
+
+    const LIFE: &str = r#"
+        fn next(state, neighbours) {
+            let n = 0;
+            for s in neighbours { n += s; }
+            if n == 3 || (state == 1 && n == 2) { 1 } else { 0 }
+        }
+    "#;
+
+    fn neighbourhood(index: usize) -> Neighbourhood {
+        let key = neighbourhood_at(index, 2);
+        Neighbourhood {
+            centre: key[0],
+            around: key[1..].try_into().unwrap(),
+        }
+    }
+
+    #[test]
+    fn scripts_must_define_next() {
+        assert!(ScriptRule::parse("none", "fn states() { 2 }").is_err());
+        assert!(ScriptRule::parse("unary", "fn next(state) { state }").is_err());
+        assert!(ScriptRule::parse("broken", "fn next(state, neighbours) {").is_err());
+        assert!(ScriptRule::parse("ok", "fn next(state, neighbours) { state }").is_ok());
+    }
+
+    #[test]
+    fn states_must_be_in_range() {
+        let with_states =
+            |n: i64| format!("fn states() {{ {n} }}\nfn next(state, neighbours) {{ 0 }}");
+        for n in [-1, 0, 1, 257] {
+            assert!(ScriptRule::parse("states", &with_states(n)).is_err(), "{n}");
+        }
+        for n in [2, 3, 256] {
+            let rule = ScriptRule::parse("states", &with_states(n)).unwrap();
+            assert_eq!(rule.n_states(), n as usize);
+        }
+        let rule = ScriptRule::parse("default", "fn next(state, neighbours) { 0 }").unwrap();
+        assert_eq!(rule.n_states(), 2);
+    }
+
+    #[test]
+    fn life_script_matches_life() {
+        let mut script = ScriptRule::parse("life", LIFE).unwrap();
+        let life = Rule::default();
+        for index in 0..512 {
+            let n = neighbourhood(index);
+            let alive = n.around.iter().filter(|&&s| s == 1).count();
+            assert_eq!(
+                Cell(script.next(&n)),
+                life.next(Cell(n.centre), alive),
+                "{n:?}"
+            );
+        }
+        assert_eq!(script.error(), None);
+    }
+
+    #[test]
+    fn tabulated_and_cached_rules_agree() {
+        // 2 states are tabulated up front, 5 are too many and go through the cache instead.
+        let mut tabulated = ScriptRule::parse("life", LIFE).unwrap();
+        let mut cached =
+            ScriptRule::parse("life", &format!("fn states() {{ 5 }}\n{LIFE}")).unwrap();
+        assert!(tabulated.lut.is_some());
+        assert!(cached.lut.is_none());
+
+        for index in 0..512 {
+            let n = neighbourhood(index);
+            assert_eq!(tabulated.next(&n), cached.next(&n), "{n:?}");
+        }
+        assert!(tabulated.cache.is_empty());
+        assert_eq!(cached.cache.len(), 512);
+        // Asking again is answered from the cache.
+        assert_eq!(
+            cached.next(&neighbourhood(7)),
+            tabulated.next(&neighbourhood(7))
+        );
+        assert_eq!(cached.cache.len(), 512);
+    }
+
+    #[test]
+    fn out_of_range_states_are_errors() {
+        // A tabulated rule is evaluated everywhere when parsed, so the error is found there.
+        let bad = "fn next(state, neighbours) { if state == 1 { 9 } else { 0 } }";
+        assert!(ScriptRule::parse("bad", bad).is_err());
+        let negative = "fn next(state, neighbours) { -1 }";
+        assert!(ScriptRule::parse("negative", negative).is_err());
+
+        // A cached rule only finds it on the way; the cell keeps its state and the first error
+        // is kept.
+        let bad = format!("fn states() {{ 5 }}\n{bad}");
+        let mut rule = ScriptRule::parse("bad", &bad).unwrap();
+        let zeros = Neighbourhood {
+            centre: 0,
+            around: [0; 8],
+        };
+        assert_eq!(rule.next(&zeros), 0);
+        assert_eq!(rule.error(), None);
+        let one = Neighbourhood {
+            centre: 1,
+            around: [0; 8],
+        };
+        assert_eq!(rule.next(&one), 1);
+        let error = rule.error().unwrap().to_string();
+        assert!(error.contains("next returned 9"), "{error}");
+        assert_eq!(rule.next(&one), 1);
+        assert_eq!(rule.error(), Some(error.as_str()));
+
+        // States the rule does not have are left alone without running the script.
+        let foreign = Neighbourhood {
+            centre: 7,
+            around: [0; 8],
+        };
+        assert_eq!(rule.next(&foreign), 7);
+    }
+}
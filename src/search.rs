@@ -0,0 +1,608 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::census::{self, Census, Class, Object, Point};
+use crate::life::{Cell, Rule};
+use crate::soup::Soup;
+
+type Rows = usize;
+type Cols = usize;
+
+/// Soups kept per object or methuselah to reproduce it with.
+pub const MAX_EXAMPLES: usize = 5;
+
+// Generations between two looks for spaceships flying away from a soup.
+const ESCAPE_INTERVAL: u64 = 8;
+
+// Dead cells a spaceship must leave between itself and the rest of a soup, in the direction it
+// moves, before it is taken off the plane.
+const ESCAPE_GAP: i64 = 16;
+
+// Spaceships of more cells are left on the plane, since telling what larger islands do takes long.
+const MAX_ESCAPING: usize = 64;
+
+// A soup that grows past this many times its area is given up on as unsettled.
+const MAX_GROWTH: usize = 16;
+
+pub fn search_error(err: &str) -> String {
+    format!("[SEARCH ERROR]: {err}.")
+}
+
+/// A soup run on the unbounded plane, until it settles or for at most some generations.
+#[derive(Clone, Debug)]
+pub struct SoupRun {
+    /// Generation the soup settled at, `None` if it had not by the end.
+    pub lifespan: Option<u64>,
+    /// Objects left at the end, spaceships that flew away included.
+    pub census: Census,
+    /// Live cells left on the plane every generation, the first one included.
+    pub populations: Vec<usize>,
+    /// Live cells left on the plane at the end, in their bounding box.
+    pub cells: Vec<Vec<Cell>>,
+}
+
+/// Runs `soup` under `rule` on the unbounded plane until it repeats, for at most
+/// `max_generations`, and takes a census of what it leaves.
+///
+/// Nothing wraps around, so spaceships fly away instead of crashing back into the soup. Every
+/// few generations, a spaceship far enough from the rest and moving away from it is taken off the
+/// plane and counted in the census. A soup that grows past sixteen times its area is given up on
+/// as unsettled, and what it grew into left unclassified.
+pub fn run_soup(rule: &Rule, soup: &[Vec<bool>], max_generations: u64) -> Result<SoupRun, String> {
+    let mut cells: HashSet<Point> = soup
+        .iter()
+        .enumerate()
+        .flat_map(|(r, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|&(_, &alive)| alive)
+                .map(move |(c, _)| (r as i64, c as i64))
+        })
+        .collect();
+    let max_population = MAX_GROWTH * soup.len() * soup.first().map_or(0, Vec::len);
+    let mut escaped = Vec::new();
+    let mut shapes = HashMap::new();
+    let mut seen: HashMap<(u64, usize), u64> = HashMap::new();
+    let mut populations = vec![cells.len()];
+    let mut generation = 0;
+
+    let lifespan = loop {
+        if let Some(&since) = seen.get(&fingerprint(&cells)) {
+            break Some(since);
+        }
+        seen.insert(fingerprint(&cells), generation);
+        if generation >= max_generations || cells.len() > max_population {
+            break None;
+        }
+        cells = census::step(rule, &cells);
+        generation += 1;
+        if generation.is_multiple_of(ESCAPE_INTERVAL) {
+            take_off_escaping(rule, &mut cells, &mut escaped, &mut shapes);
+        }
+        populations.push(cells.len());
+    };
+
+    // Classifying what a soup grew into would take longer than running it, so it is left whole.
+    let census = match cells.len() > max_population {
+        true => {
+            let blob = Object {
+                apgcode: None,
+                name: None,
+                class: Class::Unclassified,
+                period: None,
+                displacement: None,
+                population: cells.len(),
+                x: 0,
+                y: 0,
+            };
+            escaped.push((blob, bounds(cells.iter()).0));
+            Census::of_plane(rule, &HashSet::new(), generation, escaped)?
+        }
+        false => Census::of_plane(rule, &cells, generation, escaped)?,
+    };
+    let ((r0, c0), (r1, c1)) = bounds(cells.iter());
+    let cells = (r0..=r1)
+        .map(|r| {
+            (c0..=c1)
+                .map(|c| match cells.contains(&(r, c)) {
+                    true => Cell::ALIVE,
+                    false => Cell::DEAD,
+                })
+                .collect()
+        })
+        .collect();
+    Ok(SoupRun {
+        lifespan,
+        census,
+        populations,
+        cells,
+    })
+}
+
+// A hash of the live cells that does not depend on their order, with their count.
+fn fingerprint(cells: &HashSet<Point>) -> (u64, usize) {
+    let hash = cells.iter().fold(0u64, |hash, &(r, c)| {
+        // SplitMix64's finalizer, so nearby cells hash far apart.
+        let mut z = (r as u64) << 32 ^ (c as u64 & 0xffff_ffff);
+        z = (z ^ z >> 30).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ z >> 27).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash.wrapping_add(z ^ z >> 31)
+    });
+    (hash, cells.len())
+}
+
+// Takes off the plane every spaceship that is at least `ESCAPE_GAP` cells from anything else and
+// as far beyond the rest of the cells, in a direction it moves in. Spaceships flying alongside at
+// the same velocity never meet, so they do not hold each other back. What each shape is when left
+// alone is remembered in `shapes`.
+fn take_off_escaping(
+    rule: &Rule,
+    cells: &mut HashSet<Point>,
+    escaped: &mut Vec<(Object, Point)>,
+    shapes: &mut HashMap<Vec<Point>, Option<(Object, Point)>>,
+) {
+    let groups = census::plane_clusters(cells, 2);
+    let boxes: Vec<(Point, Point)> = groups.iter().map(|group| bounds(group.iter())).collect();
+    let apart = |(a0, a1): (Point, Point), (b0, b1): (Point, Point)| {
+        a0.0 > b1.0 + ESCAPE_GAP
+            || a1.0 < b0.0 - ESCAPE_GAP
+            || a0.1 > b1.1 + ESCAPE_GAP
+            || a1.1 < b0.1 - ESCAPE_GAP
+    };
+
+    let ships: HashMap<usize, (Object, Point)> = (0..groups.len())
+        .filter(|&i| groups[i].len() <= MAX_ESCAPING)
+        .filter(|&i| (0..groups.len()).all(|j| j == i || apart(boxes[i], boxes[j])))
+        .filter_map(|i| {
+            let (origin, _) = boxes[i];
+            let mut shape: Vec<Point> = groups[i]
+                .iter()
+                .map(|&(r, c)| (r - origin.0, c - origin.1))
+                .collect();
+            shape.sort_unstable();
+            let ship = shapes
+                .entry(shape)
+                .or_insert_with(|| census::spaceship(rule, &groups[i]));
+            Some((i, ship.clone()?))
+        })
+        .collect();
+
+    let mut gone = Vec::new();
+    for (&i, ship) in ships.iter() {
+        let rest = bounds(
+            (0..groups.len())
+                .filter(|&j| j != i && !ships.get(&j).is_some_and(|other| alongside(ship, other)))
+                .flat_map(|j| groups[j].iter()),
+        );
+        let (dr, dc) = ship.1;
+        let ((g0, g1), (rest0, rest1)) = (boxes[i], rest);
+        let away = rest0.0 > rest1.0
+            || (dr > 0 && g0.0 > rest1.0 + ESCAPE_GAP)
+            || (dr < 0 && g1.0 < rest0.0 - ESCAPE_GAP)
+            || (dc > 0 && g0.1 > rest1.1 + ESCAPE_GAP)
+            || (dc < 0 && g1.1 < rest0.1 - ESCAPE_GAP);
+        if away {
+            gone.push(i);
+        }
+    }
+    gone.sort_unstable_by_key(|&i| boxes[i]);
+    for i in gone {
+        for point in groups[i].iter() {
+            cells.remove(point);
+        }
+        escaped.push((ships[&i].0.clone(), boxes[i].0));
+    }
+}
+// Whether two spaceships move the same number of cells per generation, the same way.
+fn alongside((a, (ar, ac)): &(Object, Point), (b, (br, bc)): &(Object, Point)) -> bool {
+    let (pa, pb) = (a.period.unwrap_or(1) as i64, b.period.unwrap_or(1) as i64);
+    ar * pb == br * pa && ac * pb == bc * pa
+}
+
+// Top-left and bottom-right corners of some cells; the first is past the second if there are none.
+fn bounds<'a>(cells: impl Iterator<Item = &'a Point>) -> (Point, Point) {
+    cells.fold(
+        ((i64::MAX, i64::MAX), (i64::MIN, i64::MIN)),
+        |((r0, c0), (r1, c1)), &(r, c)| ((r0.min(r), c0.min(c)), (r1.max(r), c1.max(c))),
+    )
+}
+
+/// How a search report is written out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(search_error(&format!(
+                "unknown format '{format}', expected json or csv"
+            ))),
+        }
+    }
+
+    /// CSV for a `.csv` file, JSON for anything else.
+    pub fn of_path(path: &str) -> Self {
+        match Path::new(path).extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// What every soup of a search was drawn and run with.
+#[derive(Clone, Debug, Serialize)]
+pub struct Settings {
+    pub rule: String,
+    /// Size of the soup, run on the unbounded plane.
+    pub soup_rows: Rows,
+    pub soup_cols: Cols,
+    pub density: f64,
+    pub symmetry: String,
+    /// Soups are seeded `first_seed`, `first_seed + 1`, and so on.
+    pub first_seed: u64,
+    pub soups: usize,
+    pub max_generations: u64,
+}
+
+impl Settings {
+    pub fn new(
+        rule: String,
+        (soup_rows, soup_cols): (Rows, Cols),
+        soup: &Soup,
+        first_seed: u64,
+        soups: usize,
+        max_generations: u64,
+    ) -> Self {
+        Self {
+            rule,
+            soup_rows,
+            soup_cols,
+            density: soup.density,
+            symmetry: soup.symmetry.to_string(),
+            first_seed,
+            soups,
+            max_generations,
+        }
+    }
+}
+
+/// One kind of object, over every soup of a search.
+#[derive(Clone, Debug, Serialize)]
+pub struct Found {
+    pub apgcode: Option<String>,
+    pub name: Option<String>,
+    pub class: Class,
+    pub period: Option<u64>,
+    pub population: usize,
+    /// Objects of this kind across all soups.
+    pub count: usize,
+    /// Soups that left at least one.
+    pub soups: usize,
+    /// Seen in so few soups that it deserves a look.
+    pub rare: bool,
+    /// Seeds of the soups that left one, the smallest [`MAX_EXAMPLES`] of them.
+    pub seeds: Vec<u64>,
+}
+
+/// A soup that took long to settle.
+#[derive(Clone, Debug, Serialize)]
+pub struct Methuselah {
+    pub seed: u64,
+    /// Generation the soup settled at.
+    pub lifespan: u64,
+    /// Live cells once settled.
+    pub population: usize,
+}
+
+/// Aggregated censuses of a soup search, most common objects first.
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchReport {
+    pub settings: Settings,
+    pub settled: usize,
+    /// Seeds of the soups that had not settled after `max_generations`.
+    pub unsettled: Vec<u64>,
+    pub objects: Vec<Found>,
+    /// Soups that settled no earlier than the methuselah threshold, longest lived first.
+    pub methuselahs: Vec<Methuselah>,
+    // Unclassified objects are told apart by their population, as in a census.
+    #[serde(skip)]
+    index: HashMap<(Option<String>, usize), usize>,
+}
+
+impl SearchReport {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            settled: 0,
+            unsettled: Vec::new(),
+            objects: Vec::new(),
+            methuselahs: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Adds the census of the soup seeded `seed`, taken at `lifespan` if it settled then. Soups may
+    /// come in any order: every object keeps the smallest seeds that left it as its examples.
+    pub fn add(&mut self, seed: u64, lifespan: Option<u64>, census: &Census) {
+        match lifespan {
+            Some(_) => self.settled += 1,
+            None => self.unsettled.push(seed),
+        }
+        if let Some(lifespan) = lifespan {
+            self.methuselahs.push(Methuselah {
+                seed,
+                lifespan,
+                population: census.population,
+            });
+        }
+
+        for tally in census.tally.iter() {
+            let population = if tally.apgcode.is_some() {
+                0
+            } else {
+                tally.population
+            };
+            let key = (tally.apgcode.clone(), population);
+            let i = *self.index.entry(key).or_insert_with(|| {
+                self.objects.push(Found {
+                    apgcode: tally.apgcode.clone(),
+                    name: tally.name.clone(),
+                    class: tally.class,
+                    period: tally.period,
+                    population: tally.population,
+                    count: 0,
+                    soups: 0,
+                    rare: false,
+                    seeds: Vec::new(),
+                });
+                self.objects.len() - 1
+            });
+            let found = &mut self.objects[i];
+            found.count += tally.count;
+            found.soups += 1;
+            let at = found.seeds.partition_point(|&s| s < seed);
+            if at < MAX_EXAMPLES {
+                found.seeds.insert(at, seed);
+                found.seeds.truncate(MAX_EXAMPLES);
+            }
+        }
+    }
+
+    /// Sorts the report and flags what stands out: classified objects seen in at most `rare`
+    /// soups, and soups that lived at least `methuselah` generations.
+    pub fn finish(&mut self, rare: usize, methuselah: u64) {
+        for found in self.objects.iter_mut() {
+            found.rare = found.apgcode.is_some() && found.soups <= rare;
+        }
+        self.objects.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.apgcode.cmp(&b.apgcode))
+                .then(a.population.cmp(&b.population))
+        });
+        self.index.clear();
+
+        self.methuselahs.retain(|m| m.lifespan >= methuselah);
+        self.methuselahs
+            .sort_by(|a, b| b.lifespan.cmp(&a.lifespan).then(a.seed.cmp(&b.seed)));
+        self.unsettled.sort_unstable();
+    }
+
+    /// Summary table: the objects found, then the rare ones and the methuselahs.
+    pub fn table(&self) -> String {
+        let settings = &self.settings;
+        let mut out = format!(
+            "Searched {} {} soups of {}x{} ({} density) under {}: {} settled, {} did not\n",
+            settings.soups,
+            settings.symmetry,
+            settings.soup_rows,
+            settings.soup_cols,
+            settings.density,
+            settings.rule,
+            self.settled,
+            self.unsettled.len()
+        );
+        let width = self
+            .objects
+            .iter()
+            .filter_map(|f| f.apgcode.as_ref().map(String::len))
+            .max()
+            .unwrap_or(0)
+            .max("apgcode".len());
+        writeln!(
+            out,
+            "{:>8}  {:>6}  {:<width$}  {:<12}  {:>6}  name",
+            "count", "soups", "apgcode", "class", "period"
+        )
+        .unwrap();
+        for found in self.objects.iter() {
+            let period = found.period.map_or("-".to_string(), |p| p.to_string());
+            writeln!(
+                out,
+                "{:>8}  {:>6}  {:<width$}  {:<12}  {:>6}  {}{}",
+                found.count,
+                found.soups,
+                found.apgcode.as_deref().unwrap_or("?"),
+                found.class.name(),
+                period,
+                found.name.as_deref().unwrap_or(""),
+                if found.rare { " (rare)" } else { "" }
+            )
+            .unwrap();
+        }
+
+        for found in self.objects.iter().filter(|f| f.rare) {
+            let seeds: Vec<String> = found.seeds.iter().map(u64::to_string).collect();
+            writeln!(
+                out,
+                "Rare: {} in soup {}",
+                found.apgcode.as_deref().unwrap_or("?"),
+                seeds.join(", ")
+            )
+            .unwrap();
+        }
+        for m in self.methuselahs.iter().take(MAX_EXAMPLES) {
+            writeln!(
+                out,
+                "Methuselah: soup {} settled at generation {} with {} cells",
+                m.seed, m.lifespan, m.population
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a search report always serializes")
+    }
+
+    /// One row per kind of object; the soups that left it are separated by spaces.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "apgcode,name,class,period,population,count,soups,rare,seeds\n".to_string();
+        for found in self.objects.iter() {
+            let seeds: Vec<String> = found.seeds.iter().map(u64::to_string).collect();
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{}",
+                found.apgcode.as_deref().unwrap_or(""),
+                found.name.as_deref().unwrap_or(""),
+                found.class.name(),
+                found.period.map_or(String::new(), |p| p.to_string()),
+                found.population,
+                found.count,
+                found.soups,
+                found.rare,
+                seeds.join(" ")
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    pub fn write(&self, format: Format) -> String {
+        match format {
+            Format::Json => self.to_json() + "\n",
+            Format::Csv => self.to_csv(),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.write(format))
+            .map_err(|err| search_error(&format!("cannot write {}: {err}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rand::Rng as _;
+
+    use super::*;
+    use crate::census::Tally;
+    use crate::rng;
+
+    // A census as a soup might leave it: some blocks, blinkers and unclassified blobs.
+    fn census(seed: u64) -> Census {
+        let mut rng = rng::from_seed(seed);
+        let kinds = [
+            (Some("xs4_33"), Class::StillLife, 4),
+            (Some("xp2_7"), Class::Oscillator, 3),
+            (None, Class::Unclassified, 10),
+            (None, Class::Unclassified, 23),
+        ];
+        let tally: Vec<Tally> = kinds
+            .iter()
+            .filter_map(|&(apgcode, class, population)| {
+                let count = rng.gen_range(0..4);
+                (count > 0).then(|| Tally {
+                    apgcode: apgcode.map(str::to_string),
+                    name: None,
+                    class,
+                    period: None,
+                    displacement: None,
+                    population,
+                    count,
+                })
+            })
+            .collect();
+        Census {
+            rule: Rule::default().to_string(),
+            generation: 0,
+            population: tally.iter().map(|t| t.population * t.count).sum(),
+            tally,
+            objects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reports_do_not_depend_on_the_order_soups_come_in() {
+        let soups = 40;
+        let runs: Vec<(u64, Option<u64>, Census)> = (0..soups as u64)
+            .map(|seed| {
+                let lifespan = (seed % 7 != 0).then_some(seed * 37 % 500);
+                (seed, lifespan, census(seed))
+            })
+            .collect();
+        let report = |order: &[usize]| {
+            let settings = Settings::new(
+                "B3/S23".to_string(),
+                (8, 8),
+                &Soup::default(),
+                0,
+                soups,
+                500,
+            );
+            let mut report = SearchReport::new(settings);
+            for &i in order {
+                let (seed, lifespan, census) = &runs[i];
+                report.add(*seed, *lifespan, census);
+            }
+            report.finish(2, 100);
+            (report.to_json(), report.to_csv())
+        };
+
+        let mut order: Vec<usize> = (0..soups).collect();
+        let expected = report(&order);
+        for (apgcode, population) in [("xs4_33", 4), ("", 10), ("", 23)] {
+            let with: Vec<String> = runs
+                .iter()
+                .filter(|(_, _, census)| {
+                    let code = |t: &&Tally| t.apgcode.as_deref().unwrap_or("") == apgcode;
+                    census
+                        .tally
+                        .iter()
+                        .any(|t| code(&t) && t.population == population)
+                })
+                .map(|(seed, _, _)| seed.to_string())
+                .collect();
+            assert!(with.len() > MAX_EXAMPLES);
+            let row = format!("{population},");
+            let line = expected
+                .1
+                .lines()
+                .find(|l| l.starts_with(&format!("{apgcode},")) && l.contains(&row))
+                .unwrap();
+            assert!(
+                line.ends_with(&format!(",{}", with[..MAX_EXAMPLES].join(" "))),
+                "{line}"
+            );
+            assert!(line.contains(&format!(",{},false,", with.len())), "{line}");
+        }
+
+        let mut rng = rng::from_seed(1);
+        for _ in 0..5 {
+            order.shuffle(&mut rng);
+            assert_eq!(report(&order), expected);
+        }
+    }
+}
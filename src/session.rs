@@ -0,0 +1,331 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::golly::GollyRule;
+use crate::life::{Automaton, Cell, Engine, Rule, UpdateScheme};
+use crate::pattern::{rle, Pattern};
+use crate::rng::{self, Rng};
+use crate::script::ScriptRule;
+use crate::soup;
+
+pub const VERSION: u32 = 1;
+const MAGIC: &str = "# GoLrs session";
+
+type Rows = usize;
+type Cols = usize;
+
+pub fn session_error(err: &str) -> String {
+    format!("[SESSION ERROR]: {err}.")
+}
+
+/// Where the rule of a session comes from; Golly rules and scripts are reloaded from their file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleSource {
+    LifeLike(String),
+    Golly(PathBuf),
+    Script(PathBuf),
+}
+
+impl RuleSource {
+    pub fn of(automaton: &Automaton) -> Result<Self, String> {
+        let unsaved = || session_error(&format!("rule '{automaton}' was not loaded from a file"));
+        match automaton {
+            Automaton::LifeLike(rule) => Ok(RuleSource::LifeLike(rule.to_string())),
+            Automaton::Golly(rule) => Ok(RuleSource::Golly(
+                rule.path().ok_or_else(unsaved)?.to_path_buf(),
+            )),
+            Automaton::Script(rule) => Ok(RuleSource::Script(
+                rule.path().ok_or_else(unsaved)?.to_path_buf(),
+            )),
+        }
+    }
+
+    pub fn load(&self, p_birth: f64, p_death: f64) -> Result<Automaton, String> {
+        match self {
+            RuleSource::LifeLike(rule) => Ok(Rule::parse(rule)?
+                .with_probabilities(p_birth, p_death)?
+                .into()),
+            RuleSource::Golly(path) => Ok(GollyRule::load(path)?.into()),
+            RuleSource::Script(path) => Ok(ScriptRule::load(path)?.into()),
+        }
+    }
+}
+
+/// Everything needed to resume a `gol_term` run exactly where it stopped.
+///
+/// `mode` and `render_mode` are kept as the names `gol_term` gives them (`edit`/`run`,
+/// `ascii`/`unicode`), and positions are `(col, row)` like its cursor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub cells: Vec<Vec<Cell>>,
+    pub rule: RuleSource,
+    pub p_birth: f64,
+    pub p_death: f64,
+    pub scheme: UpdateScheme,
+    pub seed: u64,
+    /// Word positions in the soup and engine random streams.
+    pub soup_rng: u128,
+    pub engine_rng: u128,
+    pub generation: u64,
+    pub cursor: (Cols, Rows),
+    pub selection: Option<(Cols, Rows)>,
+    pub mode: String,
+    pub render_mode: String,
+}
+
+impl Session {
+    /// Rebuilds the engine, with its random stream where it was left.
+    pub fn engine(&self) -> Result<Engine, String> {
+        let automaton = self.rule.load(self.p_birth, self.p_death)?;
+        let mut engine = Engine::new(automaton, self.scheme, self.seed);
+        engine.set_rng_position(self.engine_rng);
+        Ok(engine)
+    }
+
+    /// Rebuilds the soup random stream where it was left.
+    pub fn soup_rng(&self) -> Rng {
+        let mut rng = rng::from_seed_stream(self.seed, rng::SOUP_STREAM);
+        rng.set_word_pos(self.soup_rng);
+        rng
+    }
+
+    /// Writes the session as `key value` lines after a `# GoLrs session` header, followed by
+    /// a `board` line and the board as RLE. See the README for the list of keys.
+    pub fn write(&self) -> String {
+        let (rule_kind, rule) = match &self.rule {
+            RuleSource::LifeLike(rule) => ("life", rule.clone()),
+            RuleSource::Golly(path) => ("golly", path.display().to_string()),
+            RuleSource::Script(path) => ("script", path.display().to_string()),
+        };
+        let (rows, cols) = (self.cells.len(), self.cells[0].len());
+
+        let mut out = format!("{MAGIC}\nversion {VERSION}\n");
+        out.push_str(&format!("size {rows}x{cols}\n"));
+        out.push_str(&format!("rule {rule_kind} {rule}\n"));
+        out.push_str(&format!("p-birth {}\n", self.p_birth));
+        out.push_str(&format!("p-death {}\n", self.p_death));
+        out.push_str(&format!("update {}\n", self.scheme));
+        out.push_str(&format!("seed {}\n", self.seed));
+        out.push_str(&format!("soup-rng {}\n", self.soup_rng));
+        out.push_str(&format!("engine-rng {}\n", self.engine_rng));
+        out.push_str(&format!("generation {}\n", self.generation));
+        out.push_str(&format!("cursor {} {}\n", self.cursor.0, self.cursor.1));
+        if let Some((c, r)) = self.selection {
+            out.push_str(&format!("selection {c} {r}\n"));
+        }
+        out.push_str(&format!("mode {}\n", self.mode));
+        out.push_str(&format!("render {}\n", self.render_mode));
+
+        let board = Pattern {
+            cells: self
+                .cells
+                .iter()
+                .map(|row| row.iter().map(|c| c.0).collect())
+                .collect(),
+            ..Default::default()
+        };
+        out.push_str("board\n");
+        out.push_str(&rle::write(&board));
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some(MAGIC) {
+            return Err(session_error("not a GoLrs session file"));
+        }
+
+        let mut size: Option<(Rows, Cols)> = None;
+        let mut rule: Option<RuleSource> = None;
+        let (mut p_birth, mut p_death) = (1.0, 1.0);
+        let mut scheme = UpdateScheme::Synchronous;
+        let mut version = None;
+        let (mut seed, mut soup_rng, mut engine_rng, mut generation) = (None, 0, 0, 0);
+        let (mut cursor, mut selection) = ((0, 0), None);
+        let (mut mode, mut render_mode) = ("edit".to_string(), "ascii".to_string());
+        let mut board: Option<String> = None;
+
+        while let Some(line) = lines.next() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "board" {
+                board = Some(lines.by_ref().collect::<Vec<_>>().join("\n"));
+                break;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            let invalid = || session_error(&format!("invalid '{key}' value '{value}'"));
+            match key {
+                "version" => {
+                    let v = value.parse::<u32>().map_err(|_| invalid())?;
+                    if v > VERSION {
+                        return Err(session_error(&format!(
+                            "session version {v} is newer than the supported version {VERSION}"
+                        )));
+                    }
+                    version = Some(v);
+                }
+                "size" => size = Some(soup::parse_size(value).map_err(|_| invalid())?),
+                "rule" => {
+                    let (kind, rest) = value.split_once(' ').ok_or_else(invalid)?;
+                    rule = Some(match kind {
+                        "life" => RuleSource::LifeLike(rest.to_string()),
+                        "golly" => RuleSource::Golly(PathBuf::from(rest)),
+                        "script" => RuleSource::Script(PathBuf::from(rest)),
+                        _ => return Err(invalid()),
+                    });
+                }
+                "p-birth" => p_birth = value.parse().map_err(|_| invalid())?,
+                "p-death" => p_death = value.parse().map_err(|_| invalid())?,
+                "update" => scheme = UpdateScheme::parse(value)?,
+                "seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                "soup-rng" => soup_rng = value.parse().map_err(|_| invalid())?,
+                "engine-rng" => engine_rng = value.parse().map_err(|_| invalid())?,
+                "generation" => generation = value.parse().map_err(|_| invalid())?,
+                "cursor" => cursor = parse_position(value).ok_or_else(invalid)?,
+                "selection" => selection = Some(parse_position(value).ok_or_else(invalid)?),
+                "mode" => mode = value.to_string(),
+                "render" => render_mode = value.to_string(),
+                // Keys added by later minor revisions are skipped.
+                _ => {}
+            }
+        }
+
+        let missing = |key: &str| session_error(&format!("'{key}' is missing"));
+        version.ok_or_else(|| missing("version"))?;
+        let (rows, cols) = size.ok_or_else(|| missing("size"))?;
+        let board = rle::parse(&board.ok_or_else(|| missing("board"))?)?;
+        if board.rows() != rows || board.cols() != cols {
+            return Err(session_error(&format!(
+                "board is {}x{} but the session size is {rows}x{cols}",
+                board.rows(),
+                board.cols()
+            )));
+        }
+        if cursor.0 >= cols
+            || cursor.1 >= rows
+            || selection.is_some_and(|(c, r)| c >= cols || r >= rows)
+        {
+            return Err(session_error("cursor or selection is outside the board"));
+        }
+
+        Ok(Self {
+            cells: board
+                .cells
+                .iter()
+                .map(|row| row.iter().map(|&s| Cell(s)).collect())
+                .collect(),
+            rule: rule.ok_or_else(|| missing("rule"))?,
+            p_birth,
+            p_death,
+            scheme,
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            soup_rng,
+            engine_rng,
+            generation,
+            cursor,
+            selection,
+            mode,
+            render_mode,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| session_error(&format!("cannot read {}: {err}", path.display())))?;
+        Self::parse(&text)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.write())
+            .map_err(|err| session_error(&format!("cannot write {}: {err}", path.display())))
+    }
+}
+
+fn parse_position(value: &str) -> Option<(Cols, Rows)> {
+    let (c, r) = value.split_once(' ')?;
+    Some((c.trim().parse().ok()?, r.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let mut cells = vec![vec![Cell::DEAD; 7]; 5];
+        for (r, c) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            cells[r][c] = Cell::ALIVE;
+        }
+        Session {
+            cells,
+            rule: RuleSource::LifeLike("B36/S23".to_string()),
+            p_birth: 0.75,
+            p_death: 1.0,
+            scheme: UpdateScheme::Alpha(0.5),
+            seed: 42,
+            soup_rng: 1 << 70,
+            engine_rng: 12345,
+            generation: 99,
+            cursor: (6, 4),
+            selection: None,
+            mode: "run".to_string(),
+            render_mode: "unicode".to_string(),
+        }
+    }
+
+    #[test]
+    fn write_parse_round_trips() {
+        let mut golly = session();
+        golly.rule = RuleSource::Golly(PathBuf::from("rules/Wire World.rule"));
+        golly.selection = Some((3, 2));
+        golly.scheme = UpdateScheme::Checkerboard;
+        let mut script = session();
+        script.rule = RuleSource::Script(PathBuf::from("life.rhai"));
+        script.cells = vec![vec![Cell::DEAD; 3]; 2];
+        script.cursor = (0, 0);
+
+        for session in [session(), golly, script] {
+            let written = session.write();
+            let again = Session::parse(&written).unwrap();
+            assert_eq!(again, session, "{written}");
+            assert_eq!(again.write(), written);
+        }
+    }
+
+    #[test]
+    fn newer_versions_are_refused_and_unknown_keys_skipped() {
+        let written = session().write();
+        let newer = written.replace(
+            &format!("version {VERSION}\n"),
+            &format!("version {}\n", VERSION + 1),
+        );
+        assert!(Session::parse(&newer).unwrap_err().contains("newer"));
+
+        let extended = written.replace("seed 42\n", "seed 42\nzoom 3\nlabel a b c\n");
+        assert_eq!(Session::parse(&extended).unwrap(), session());
+    }
+
+    #[test]
+    fn positions_and_sizes_must_fit_the_board() {
+        let written = session().write();
+
+        let cursor = written.replace("cursor 6 4\n", "cursor 7 4\n");
+        assert!(Session::parse(&cursor).unwrap_err().contains("outside"));
+        let selection = written.replace("cursor 6 4\n", "cursor 6 4\nselection 2 5\n");
+        assert!(Session::parse(&selection).unwrap_err().contains("outside"));
+
+        let size = written.replace("size 5x7\n", "size 6x7\n");
+        assert!(Session::parse(&size).unwrap_err().contains("session size"));
+
+        let missing = written.replace("seed 42\n", "");
+        assert!(Session::parse(&missing)
+            .unwrap_err()
+            .contains("'seed' is missing"));
+        assert!(Session::parse("version 1\n").is_err());
+    }
+}
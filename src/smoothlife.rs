@@ -0,0 +1,271 @@
+use std::f32::consts::PI;
+
+use crate::life::emod;
+
+pub mod blobs;
+pub mod snapshot;
+pub mod sweep;
+
+/// Parameters of the SmoothLife transition: outer radius, birth and death intervals, sigmoid
+/// widths and time step. The inner radius is always a third of `ra`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Params {
+    pub ra: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub d1: f32,
+    pub d2: f32,
+    pub alpha_n: f32,
+    pub alpha_m: f32,
+    pub dt: f32,
+}
+
+impl Params {
+    pub fn ri(&self) -> f32 {
+        self.ra / 3.0
+    }
+
+    /// Checks the parameters the transition cannot run with: `ra` below 2, which leaves the inner
+    /// disc empty, and sigmoid widths or a time step that are not positive.
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.ra >= 2.0 && self.ra.is_finite()) {
+            return Err(format!("ra must be at least 2, got {}", self.ra));
+        }
+        for (name, value) in [
+            ("alpha-n", self.alpha_n),
+            ("alpha-m", self.alpha_m),
+            ("dt", self.dt),
+        ] {
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(format!("{name} must be positive, got {value}"));
+            }
+        }
+        Ok(())
+    }
+
+    fn sigma(x: f32, a: f32, alpha: f32) -> f32 {
+        1.0 / (1.0 + (-(x - a) * 4.0 / alpha).exp())
+    }
+
+    fn sigma_n(&self, x: f32, a: f32, b: f32) -> f32 {
+        Self::sigma(x, a, self.alpha_n) * (1.0 - Self::sigma(x, b, self.alpha_n))
+    }
+
+    fn sigma_m(&self, x: f32, y: f32, m: f32) -> f32 {
+        x * (1.0 - Self::sigma(m, 0.5, self.alpha_m)) + y * Self::sigma(m, 0.5, self.alpha_m)
+    }
+
+    /// Transition function for inner filling `m` and outer filling `n`.
+    pub fn s(&self, m: f32, n: f32) -> f32 {
+        self.sigma_n(
+            n,
+            self.sigma_m(self.b1, self.d1, m),
+            self.sigma_m(self.b2, self.d2, m),
+        )
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            ra: 21.0,
+            b1: 0.278,
+            b2: 0.365,
+            d1: 0.267,
+            d2: 0.445,
+            alpha_n: 0.028,
+            alpha_m: 0.147,
+            dt: 0.05,
+        }
+    }
+}
+
+/// Advances the field by one time step on the CPU, wrapping around the edges.
+///
+/// The fillings are sums over discs of rows, so each is read off per-row prefix sums.
+pub fn step(cells: &mut [Vec<f32>], params: &Params) {
+    let (h, w) = (cells.len(), cells[0].len());
+    let (ra, ri) = (params.ra, params.ri());
+    let outer = disc_spans(ra, (ra * ra) as i32);
+    let inner = disc_spans(ra, (ri * ri) as i32);
+
+    let prefix: Vec<Vec<f64>> = cells
+        .iter()
+        .map(|row| {
+            let mut sums = vec![0.0; w + 1];
+            for (x, &v) in row.iter().enumerate() {
+                sums[x + 1] = sums[x] + v as f64;
+            }
+            sums
+        })
+        .collect();
+    let disc = |spans: &[(i32, i32, i32)], cy: usize, cx: usize| -> f32 {
+        spans
+            .iter()
+            .map(|&(dy, x0, x1)| {
+                let row = &prefix[emod(cy as i32 + dy, h as i32) as usize];
+                wrapped_sum(row, cx as i64 + x0 as i64, (x1 - x0 + 1) as usize)
+            })
+            .sum::<f64>() as f32
+    };
+
+    let next: Vec<Vec<f32>> = (0..h)
+        .map(|cy| {
+            (0..w)
+                .map(|cx| {
+                    let m_sum = disc(&inner, cy, cx);
+                    let m = m_sum / (PI * ri * ri);
+                    let n = (disc(&outer, cy, cx) - m_sum) / (PI * (ra * ra - ri * ri));
+
+                    let cell = cells[cy][cx] + params.dt * (2.0 * params.s(m, n) - 1.0);
+                    cell.clamp(0.0, 1.0)
+                })
+                .collect()
+        })
+        .collect();
+    cells.clone_from_slice(&next);
+}
+
+// Rows `(dy, x0, x1)` of the offsets within `ra` of a cell with dx² + dy² <= r2. Offsets run
+// over -(ra - 1)..(ra - 1) on both axes.
+fn disc_spans(ra: f32, r2: i32) -> Vec<(i32, i32, i32)> {
+    let (lo, hi) = (-(ra - 1.0) as i32, (ra - 1.0) as i32 - 1);
+    (lo..=hi)
+        .filter_map(|dy| {
+            let rest = r2 - dy * dy;
+            if rest < 0 {
+                return None;
+            }
+            let a = (rest as f64).sqrt() as i32;
+            let (x0, x1) = ((-a).max(lo), a.min(hi));
+            (x0 <= x1).then_some((dy, x0, x1))
+        })
+        .collect()
+}
+
+// Sum of `len` consecutive values of a row starting at `start`, wrapping around it.
+fn wrapped_sum(prefix: &[f64], start: i64, len: usize) -> f64 {
+    let w = prefix.len() - 1;
+    let total = prefix[w];
+    let (laps, rest) = (len / w, len % w);
+    let s = start.rem_euclid(w as i64) as usize;
+    let tail = if s + rest <= w {
+        prefix[s + rest] - prefix[s]
+    } else {
+        total - prefix[s] + prefix[s + rest - w]
+    };
+    laps as f64 * total + tail
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng as _;
+
+    use super::*;
+    use crate::rng;
+
+    // The step as first written: every offset of the square around each cell, tested against
+    // both discs.
+    fn brute_force_step(cells: &mut [Vec<f32>], params: &Params) {
+        let prev = cells.to_vec();
+        let (h, w) = (prev.len() as i32, prev[0].len() as i32);
+        let (ra, ri) = (params.ra, params.ri());
+
+        for cy in 0..h {
+            for cx in 0..w {
+                let (mut m, mut n) = (0.0f32, 0.0f32);
+                for dy in -(ra - 1.0) as i32..(ra - 1.0) as i32 {
+                    for dx in -(ra - 1.0) as i32..(ra - 1.0) as i32 {
+                        let v = prev[emod(cy + dy, h) as usize][emod(cx + dx, w) as usize];
+                        if dx * dx + dy * dy <= (ri * ri) as i32 {
+                            m += v;
+                        } else if dx * dx + dy * dy <= (ra * ra) as i32 {
+                            n += v;
+                        }
+                    }
+                }
+                m /= PI * ri * ri;
+                n /= PI * (ra * ra - ri * ri);
+
+                let cell =
+                    prev[cy as usize][cx as usize] + params.dt * (2.0 * params.s(m, n) - 1.0);
+                cells[cy as usize][cx as usize] = cell.clamp(0.0, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn step_matches_the_brute_force_disc_sums() {
+        let mut rng = rng::from_seed(1);
+        // Fields narrower than the disc wrap around more than once.
+        for (ra, h, w) in [(21.0, 48, 64), (7.5, 24, 17), (12.0, 10, 9), (4.0, 3, 5)] {
+            let params = Params {
+                ra,
+                ..Params::default()
+            };
+            let field: Vec<Vec<f32>> = (0..h)
+                .map(|_| (0..w).map(|_| rng.gen_range(0.0..1.0)).collect())
+                .collect();
+            let (mut fast, mut slow) = (field.clone(), field);
+            for _ in 0..3 {
+                step(&mut fast, &params);
+                brute_force_step(&mut slow, &params);
+                for (a, b) in fast.iter().flatten().zip(slow.iter().flatten()) {
+                    assert!((a - b).abs() < 1e-4, "ra {ra} on {h}x{w}: {a} != {b}");
+                }
+                slow.clone_from(&fast);
+            }
+        }
+    }
+
+    #[test]
+    fn params_are_checked() {
+        assert_eq!(Params::default().check(), Ok(()));
+        let bad = [
+            Params {
+                ra: 1.5,
+                ..Params::default()
+            },
+            Params {
+                ra: f32::NAN,
+                ..Params::default()
+            },
+            Params {
+                alpha_n: 0.0,
+                ..Params::default()
+            },
+            Params {
+                alpha_m: -0.1,
+                ..Params::default()
+            },
+            Params {
+                dt: 0.0,
+                ..Params::default()
+            },
+            Params {
+                dt: f32::INFINITY,
+                ..Params::default()
+            },
+        ];
+        for params in bad {
+            assert!(params.check().is_err(), "{params:?}");
+        }
+    }
+
+    #[test]
+    fn wrapped_sums_match_summing_cell_by_cell() {
+        let row: Vec<f32> = (0..7).map(|x| (x * x) as f32).collect();
+        let mut prefix = vec![0.0; row.len() + 1];
+        for (x, &v) in row.iter().enumerate() {
+            prefix[x + 1] = prefix[x] + v as f64;
+        }
+        for start in -20..20i64 {
+            for len in 0..30 {
+                let naive: f64 = (0..len as i64)
+                    .map(|i| row[(start + i).rem_euclid(7) as usize] as f64)
+                    .sum();
+                assert_eq!(wrapped_sum(&prefix, start, len), naive, "{start} {len}");
+            }
+        }
+    }
+}
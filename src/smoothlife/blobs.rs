@@ -0,0 +1,373 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Field value at or above which a cell belongs to a blob.
+pub const DEFAULT_THRESHOLD: f32 = 0.5;
+
+// Blobs smaller than this many cells are noise, not gliders.
+const MIN_AREA: usize = 4;
+
+// Farthest a blob's centroid may move in one step and still be the same blob, in cells.
+const MAX_JUMP: f64 = 8.0;
+
+const NEIGHBOURS: [(i64, i64); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+// Weight of the latest step in a blob's smoothed velocity.
+const VELOCITY_SMOOTHING: f64 = 0.3;
+
+// Tracks shorter than this many steps are left out of summaries.
+const MIN_SUMMARY_STEPS: usize = 20;
+
+pub fn blob_error(err: &str) -> String {
+    format!("[BLOB ERROR]: {err}.")
+}
+
+/// A connected region of the thresholded field, followed from step to step.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Blob {
+    pub id: usize,
+    pub area: usize,
+    /// Sum of the field values of its cells.
+    pub mass: f64,
+    /// Mass-weighted centre, as (x, y) in cells.
+    pub centroid: (f64, f64),
+    /// Smoothed movement of the centroid, as (x, y) cells per step.
+    pub velocity: (f64, f64),
+}
+
+/// Where a blob was at one step.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TrackPoint {
+    pub step: u64,
+    pub blob: Blob,
+}
+
+/// Everything one blob did, from the step it appeared to the step it was last seen.
+#[derive(Clone, Debug, Default)]
+pub struct Track {
+    pub points: Vec<TrackPoint>,
+    // Sum of the wrapped centroid moves, so crossing an edge does not count as a jump back.
+    travelled: (f64, f64),
+}
+
+impl Track {
+    /// Average movement over the whole track, as (x, y) cells per step.
+    pub fn mean_velocity(&self) -> (f64, f64) {
+        match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) if last.step > first.step => {
+                let steps = (last.step - first.step) as f64;
+                (self.travelled.0 / steps, self.travelled.1 / steps)
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+}
+
+/// Speed of a velocity in cells per step, and its heading in degrees counterclockwise from the
+/// +x axis, with y pointing up as on screen.
+pub fn speed_and_heading((vx, vy): (f64, f64)) -> (f64, f64) {
+    let heading = (-vy).atan2(vx) * 180.0 / PI;
+    ((vx * vx + vy * vy).sqrt(), heading.rem_euclid(360.0))
+}
+
+/// Finds the blobs of a field on every step and tells which blob of one step is which of the
+/// next, keeping their trajectories.
+///
+/// Cells at or above the threshold are joined with their eight neighbours, wrapping around the
+/// edges of the field. Each blob of a step is matched to the nearest unclaimed blob of the step
+/// before it, closest pairs first, when it is at most a few cells away; others get a new id.
+pub struct Tracker {
+    threshold: f32,
+    blobs: Vec<Blob>,
+    tracks: Vec<Track>,
+    last_step: Option<u64>,
+}
+
+impl Tracker {
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold,
+            blobs: Vec::new(),
+            tracks: Vec::new(),
+            last_step: None,
+        }
+    }
+
+    /// The blobs of the latest step.
+    pub fn blobs(&self) -> &[Blob] {
+        &self.blobs
+    }
+
+    /// Every track so far, indexed by blob id.
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Labels the blobs of the field at `step` and matches them to those of the previous update.
+    pub fn update(&mut self, step: u64, cells: &[Vec<f32>]) {
+        let (h, w) = (cells.len() as f64, cells[0].len() as f64);
+        let steps = self
+            .last_step
+            .filter(|&last| step > last)
+            .map_or(1.0, |last| (step - last) as f64);
+        let mut found = label(cells, self.threshold);
+
+        // Every pair close enough to be the same blob, closest first.
+        let mut pairs: Vec<(f64, usize, usize)> = Vec::new();
+        for (i, old) in self.blobs.iter().enumerate() {
+            for (j, new) in found.iter().enumerate() {
+                let (dx, dy) = wrapped_delta(old.centroid, new.centroid, w, h);
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance <= MAX_JUMP * steps {
+                    pairs.push((distance, i, j));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut claimed = vec![false; self.blobs.len()];
+        let mut matched = vec![None; found.len()];
+        for (_, i, j) in pairs {
+            if !claimed[i] && matched[j].is_none() {
+                claimed[i] = true;
+                matched[j] = Some(i);
+            }
+        }
+
+        for (j, blob) in found.iter_mut().enumerate() {
+            match matched[j].map(|i| self.blobs[i]) {
+                Some(old) => {
+                    let (dx, dy) = wrapped_delta(old.centroid, blob.centroid, w, h);
+                    let (vx, vy) = (dx / steps, dy / steps);
+                    blob.id = old.id;
+                    blob.velocity = (
+                        old.velocity.0 + VELOCITY_SMOOTHING * (vx - old.velocity.0),
+                        old.velocity.1 + VELOCITY_SMOOTHING * (vy - old.velocity.1),
+                    );
+                    let track = &mut self.tracks[old.id];
+                    track.travelled.0 += dx;
+                    track.travelled.1 += dy;
+                }
+                None => {
+                    blob.id = self.tracks.len();
+                    self.tracks.push(Track::default());
+                }
+            }
+            self.tracks[blob.id]
+                .points
+                .push(TrackPoint { step, blob: *blob });
+        }
+        self.blobs = found;
+        self.last_step = Some(step);
+    }
+
+    /// Every track point as `id,step,x,y,mass,area,vx,vy` rows, by id then step.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "id,step,x,y,mass,area,vx,vy\n".to_string();
+        for point in self.tracks.iter().flat_map(|track| track.points.iter()) {
+            let blob = &point.blob;
+            writeln!(
+                csv,
+                "{},{},{:.3},{:.3},{:.3},{},{:.4},{:.4}",
+                blob.id,
+                point.step,
+                blob.centroid.0,
+                blob.centroid.1,
+                blob.mass,
+                blob.area,
+                blob.velocity.0,
+                blob.velocity.1
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_csv())
+            .map_err(|err| blob_error(&format!("cannot write {}: {err}", path.display())))
+    }
+
+    /// One line per track that lasted a while: its average speed, heading and mass.
+    pub fn summary(&self) -> Vec<String> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| track.points.len() >= MIN_SUMMARY_STEPS)
+            .map(|(id, track)| {
+                let (speed, heading) = speed_and_heading(track.mean_velocity());
+                let mass = track.points.iter().map(|p| p.blob.mass).sum::<f64>()
+                    / track.points.len() as f64;
+                let (first, last) = (track.points[0].step, track.points[track.points.len() - 1].step);
+                format!(
+                    "Blob {id}: steps {first}-{last}, speed {speed:.3} cells/step, heading {heading:.0}°, mass {mass:.1}"
+                )
+            })
+            .collect()
+    }
+}
+
+// The blobs of a thresholded field, with ids left at 0.
+fn label(cells: &[Vec<f32>], threshold: f32) -> Vec<Blob> {
+    let (h, w) = (cells.len(), cells[0].len());
+    let mut seen = vec![vec![false; w]; h];
+    let mut blobs = Vec::new();
+
+    for r in 0..h {
+        for c in 0..w {
+            if seen[r][c] || cells[r][c] < threshold {
+                continue;
+            }
+            // Cells are visited with unwrapped coordinates relative to the first one, so the
+            // centroid of a blob lying across an edge comes out whole.
+            seen[r][c] = true;
+            let mut queue = VecDeque::from([(r as i64, c as i64)]);
+            let (mut area, mut mass, mut sx, mut sy) = (0, 0.0, 0.0, 0.0);
+            while let Some((y, x)) = queue.pop_front() {
+                let v = cells[wrap(y, h)][wrap(x, w)] as f64;
+                area += 1;
+                mass += v;
+                sx += v * x as f64;
+                sy += v * y as f64;
+                for (dy, dx) in NEIGHBOURS {
+                    let (ny, nx) = (y + dy, x + dx);
+                    let (wy, wx) = (wrap(ny, h), wrap(nx, w));
+                    if !seen[wy][wx] && cells[wy][wx] >= threshold {
+                        seen[wy][wx] = true;
+                        queue.push_back((ny, nx));
+                    }
+                }
+            }
+            if area >= MIN_AREA && mass > 0.0 {
+                blobs.push(Blob {
+                    id: 0,
+                    area,
+                    mass,
+                    centroid: (
+                        (sx / mass).rem_euclid(w as f64),
+                        (sy / mass).rem_euclid(h as f64),
+                    ),
+                    velocity: (0.0, 0.0),
+                });
+            }
+        }
+    }
+    blobs
+}
+
+fn wrap(i: i64, len: usize) -> usize {
+    i.rem_euclid(len as i64) as usize
+}
+
+// Shortest move from `from` to `to` on a `w` x `h` torus, as (x, y).
+fn wrapped_delta(from: (f64, f64), to: (f64, f64), w: f64, h: f64) -> (f64, f64) {
+    let shortest = |d: f64, len: f64| d - len * (d / len).round();
+    (shortest(to.0 - from.0, w), shortest(to.1 - from.1, h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A `h` x `w` field holding `size` x `size` squares of 1.0 with their top-left corners at
+    // `corners` (x, y), wrapping around the edges.
+    fn field(h: usize, w: usize, size: usize, corners: &[(i64, i64)]) -> Vec<Vec<f32>> {
+        let mut cells = vec![vec![0.0; w]; h];
+        for &(x, y) in corners {
+            for dy in 0..size as i64 {
+                for dx in 0..size as i64 {
+                    cells[wrap(y + dy, h)][wrap(x + dx, w)] = 1.0;
+                }
+            }
+        }
+        cells
+    }
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+    }
+
+    #[test]
+    fn a_blob_across_the_edges_is_one_blob() {
+        // A 4x4 square on the corner of the field, split into four pieces by the edges.
+        let blobs = label(&field(32, 40, 4, &[(-2, -2)]), DEFAULT_THRESHOLD);
+        assert_eq!(blobs.len(), 1);
+        assert_eq!((blobs[0].area, blobs[0].mass), (16, 16.0));
+        assert!(close(blobs[0].centroid, (39.5, 31.5)), "{:?}", blobs[0]);
+
+        let blobs = label(&field(32, 40, 4, &[(-1, 10), (10, 10)]), DEFAULT_THRESHOLD);
+        let centroids: Vec<(f64, f64)> = blobs.iter().map(|b| b.centroid).collect();
+        assert_eq!(centroids.len(), 2);
+        assert!(centroids.iter().any(|&c| close(c, (0.5, 11.5))));
+        assert!(centroids.iter().any(|&c| close(c, (11.5, 11.5))));
+
+        // Specks are noise, not blobs.
+        assert!(label(&field(32, 40, 1, &[(5, 5)]), DEFAULT_THRESHOLD).is_empty());
+    }
+
+    #[test]
+    fn blobs_keep_their_id_as_they_wrap_around() {
+        let mut tracker = Tracker::new(DEFAULT_THRESHOLD);
+        // Two squares, one moving 3 cells right and 1 down each step across the right and bottom
+        // edges, the other standing still.
+        for step in 0..12 {
+            let x = 30 + 3 * step as i64;
+            let y = 26 + step as i64;
+            tracker.update(step, &field(32, 40, 4, &[(x, y), (20, 14)]));
+            let ids: Vec<usize> = tracker.blobs().iter().map(|b| b.id).collect();
+            assert_eq!(ids.len(), 2, "{step}");
+            assert!(ids.contains(&0) && ids.contains(&1), "{step}: {ids:?}");
+        }
+        assert_eq!(tracker.tracks().len(), 2);
+
+        let moving = tracker
+            .tracks()
+            .iter()
+            .find(|track| track.points.len() == 12 && track.mean_velocity().0 > 0.0)
+            .unwrap();
+        assert!(
+            close(moving.mean_velocity(), (3.0, 1.0)),
+            "{:?}",
+            moving.mean_velocity()
+        );
+        let last = moving.points.last().unwrap().blob;
+        assert!(close(
+            last.centroid,
+            ((30.0 + 33.0 + 1.5) % 40.0, (26.0 + 11.0 + 1.5) % 32.0)
+        ));
+        assert!(last.velocity.0 > 2.5 && last.velocity.1 > 0.5);
+
+        let still = tracker
+            .tracks()
+            .iter()
+            .find(|track| track.mean_velocity() == (0.0, 0.0))
+            .unwrap();
+        assert_eq!(still.points.len(), 12);
+    }
+
+    #[test]
+    fn velocities_are_per_step_when_updates_skip_steps() {
+        let mut tracker = Tracker::new(DEFAULT_THRESHOLD);
+        for update in 0..6u64 {
+            let x = 2 * 4 * update as i64;
+            tracker.update(4 * update, &field(20, 24, 3, &[(x, 8)]));
+        }
+        assert_eq!(tracker.tracks().len(), 1);
+        assert!(close(tracker.tracks()[0].mean_velocity(), (2.0, 0.0)));
+        let (speed, heading) = speed_and_heading(tracker.tracks()[0].mean_velocity());
+        assert!((speed - 2.0).abs() < 1e-9 && heading.abs() < 1e-9);
+    }
+}
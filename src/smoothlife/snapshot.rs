@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::Path;
+
+use super::Params;
+use crate::soup;
+
+pub const VERSION: u32 = 1;
+const MAGIC: &str = "# GoLrs SmoothLife snapshot";
+const DATA: &[u8] = b"\ndata\n";
+
+pub fn snapshot_error(err: &str) -> String {
+    format!("[SNAPSHOT ERROR]: {err}.")
+}
+
+/// The continuous field of a SmoothLife run, with what is needed to carry on from it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub cells: Vec<Vec<f32>>,
+    pub params: Params,
+    pub seed: u64,
+    pub step: u64,
+}
+
+impl Snapshot {
+    /// Writes `key value` lines after a `# GoLrs SmoothLife snapshot` header, then a `data` line
+    /// followed by the field as little-endian `f32`s, row by row. Floats are written in their
+    /// shortest round-tripping form, so nothing is lost either way.
+    pub fn write(&self) -> Vec<u8> {
+        let (rows, cols) = (self.cells.len(), self.cells[0].len());
+        let p = &self.params;
+
+        let mut header = format!("{MAGIC}\nversion {VERSION}\n");
+        header.push_str(&format!("size {rows}x{cols}\n"));
+        for (key, value) in [
+            ("ra", p.ra),
+            ("b1", p.b1),
+            ("b2", p.b2),
+            ("d1", p.d1),
+            ("d2", p.d2),
+            ("alpha-n", p.alpha_n),
+            ("alpha-m", p.alpha_m),
+            ("dt", p.dt),
+        ] {
+            header.push_str(&format!("{key} {value}\n"));
+        }
+        header.push_str(&format!("seed {}\n", self.seed));
+        header.push_str(&format!("step {}", self.step));
+
+        let mut out = header.into_bytes();
+        out.extend_from_slice(DATA);
+        for v in self.cells.iter().flatten() {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let split = bytes
+            .windows(DATA.len())
+            .position(|w| w == DATA)
+            .ok_or_else(|| snapshot_error("'data' is missing"))?;
+        let header = std::str::from_utf8(&bytes[..split])
+            .map_err(|_| snapshot_error("header is not valid UTF-8"))?;
+        let data = &bytes[split + DATA.len()..];
+
+        let mut lines = header.lines();
+        if lines.next().map(str::trim) != Some(MAGIC) {
+            return Err(snapshot_error("not a GoLrs SmoothLife snapshot"));
+        }
+
+        let mut version = None;
+        let mut size = None;
+        let mut params = Params::default();
+        let (mut seed, mut step) = (0, 0);
+
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            let invalid = || snapshot_error(&format!("invalid '{key}' value '{value}'"));
+            let param = || value.parse::<f32>().map_err(|_| invalid());
+            match key {
+                "version" => {
+                    let v = value.parse::<u32>().map_err(|_| invalid())?;
+                    if v > VERSION {
+                        return Err(snapshot_error(&format!(
+                            "snapshot version {v} is newer than the supported version {VERSION}"
+                        )));
+                    }
+                    version = Some(v);
+                }
+                "size" => size = Some(soup::parse_size(value).map_err(|_| invalid())?),
+                "ra" => params.ra = param()?,
+                "b1" => params.b1 = param()?,
+                "b2" => params.b2 = param()?,
+                "d1" => params.d1 = param()?,
+                "d2" => params.d2 = param()?,
+                "alpha-n" => params.alpha_n = param()?,
+                "alpha-m" => params.alpha_m = param()?,
+                "dt" => params.dt = param()?,
+                "seed" => seed = value.parse().map_err(|_| invalid())?,
+                "step" => step = value.parse().map_err(|_| invalid())?,
+                // Keys added by later minor revisions are skipped.
+                _ => {}
+            }
+        }
+
+        let missing = |key: &str| snapshot_error(&format!("'{key}' is missing"));
+        version.ok_or_else(|| missing("version"))?;
+        let (rows, cols) = size.ok_or_else(|| missing("size"))?;
+        params.check().map_err(|err| snapshot_error(&err))?;
+        let expected = rows.checked_mul(cols).and_then(|n| n.checked_mul(4));
+        if expected != Some(data.len()) {
+            return Err(snapshot_error(&format!(
+                "{} bytes of data do not fit a {rows}x{cols} field",
+                data.len()
+            )));
+        }
+
+        let values: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        if values.iter().any(|v| !(0.0..=1.0).contains(v)) {
+            return Err(snapshot_error("field values must lie in [0, 1]"));
+        }
+
+        Ok(Self {
+            cells: values.chunks(cols).map(<[f32]>::to_vec).collect(),
+            params,
+            seed,
+            step,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|err| snapshot_error(&format!("cannot read {}: {err}", path.display())))?;
+        Self::parse(&bytes)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.write())
+            .map_err(|err| snapshot_error(&format!("cannot write {}: {err}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            cells: vec![vec![0.0, 0.25, 1.0], vec![0.5, 0.125, 0.75]],
+            params: Params::default(),
+            seed: 7,
+            step: 42,
+        }
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let snapshot = snapshot();
+        assert_eq!(Snapshot::parse(&snapshot.write()).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn params_the_transition_cannot_run_with_are_refused() {
+        let written = snapshot().write();
+        let split = written.windows(DATA.len()).position(|w| w == DATA).unwrap();
+        let header = std::str::from_utf8(&written[..split]).unwrap();
+
+        for (key, value) in [
+            ("ra", "1"),
+            ("alpha-n", "0"),
+            ("alpha-m", "-0.1"),
+            ("dt", "0"),
+        ] {
+            let header: Vec<String> = header
+                .lines()
+                .map(|line| match line.split_once(' ') {
+                    Some((k, _)) if k == key => format!("{key} {value}"),
+                    _ => line.to_string(),
+                })
+                .collect();
+            let mut bytes = header.join("\n").into_bytes();
+            bytes.extend_from_slice(&written[split..]);
+            let err = Snapshot::parse(&bytes).unwrap_err();
+            assert!(err.contains(key), "{key} {value}: {err}");
+        }
+    }
+}
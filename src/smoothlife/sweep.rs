@@ -0,0 +1,525 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use rand::Rng as _;
+
+use super::blobs::{self, Tracker};
+use super::{step, Params};
+use crate::rng::Rng;
+
+/// Values a swept range takes on a grid when no count is given.
+pub const DEFAULT_COUNT: usize = 3;
+
+/// The parameters a sweep can vary, as they are named in snapshots.
+pub const PARAMETERS: [&str; 8] = ["ra", "b1", "b2", "d1", "d2", "alpha-n", "alpha-m", "dt"];
+
+// A field whose mean value ends below this died out, and above `FULL_MEAN` filled up.
+const DEAD_MEAN: f64 = 0.01;
+const FULL_MEAN: f64 = 0.9;
+
+// A field stands still when fewer than this share of its cells crossed the blob threshold over
+// the tracked steps, however much the edges of its blobs breathe.
+const STILL_SHARE: f64 = 0.01;
+
+// Moving structures are only looked for when blobs cover at most this share of the field, and
+// are blobs no larger than `MAX_LOCAL_AREA` of it.
+const MAX_LOCAL_COVERAGE: f64 = 0.3;
+const MAX_LOCAL_AREA: f64 = 0.05;
+
+// Blobs are tracked over the last `TRACKED_STEPS` steps, and count as moving when they were
+// followed for at least half of them at this many cells per step.
+const TRACKED_STEPS: u64 = 100;
+const MIN_MOVING_SPEED: f64 = 0.05;
+
+pub fn sweep_error(err: &str) -> String {
+    format!("[SWEEP ERROR]: {err}.")
+}
+
+/// The values one parameter takes in a sweep: `count` evenly spaced ones from `from` to `to` on
+/// a grid, or any in between when sampling.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Axis {
+    pub name: &'static str,
+    pub from: f32,
+    pub to: f32,
+    pub count: usize,
+}
+
+impl Axis {
+    /// Parses `name=value`, `name=from:to` or `name=from:to:count`, such as `b1=0.25:0.3:6`.
+    pub fn parse(axis: &str) -> Result<Self, String> {
+        let (name, range) = axis
+            .split_once('=')
+            .ok_or_else(|| sweep_error(&format!("expected name=from:to[:count], got '{axis}'")))?;
+        let name = PARAMETERS
+            .into_iter()
+            .find(|&p| p == name.trim().replace('_', "-"))
+            .ok_or_else(|| {
+                sweep_error(&format!(
+                    "unknown parameter '{name}', expected one of {}",
+                    PARAMETERS.join(", ")
+                ))
+            })?;
+
+        let value = |v: &str| {
+            v.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| sweep_error(&format!("invalid value '{v}' for {name}")))
+        };
+        let parts: Vec<&str> = range.split(':').collect();
+        let (from, to, count) = match parts[..] {
+            [v] => (value(v)?, value(v)?, 1),
+            [from, to] => (value(from)?, value(to)?, DEFAULT_COUNT),
+            [from, to, count] => {
+                let count = count
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| sweep_error(&format!("invalid count '{count}' for {name}")))?;
+                (value(from)?, value(to)?, count)
+            }
+            _ => {
+                return Err(sweep_error(&format!(
+                    "expected name=from:to[:count], got '{axis}'"
+                )))
+            }
+        };
+        // Every check is a lower bound, so the ends of the range cover the values between.
+        for value in [from, to] {
+            let mut params = Params::default();
+            set(&mut params, name, value);
+            params.check().map_err(|err| sweep_error(&err))?;
+        }
+        Ok(Self {
+            name,
+            from,
+            to,
+            count,
+        })
+    }
+
+    /// The grid values, from `from` to `to`.
+    pub fn values(&self) -> Vec<f32> {
+        match self.count {
+            1 => vec![self.from],
+            n => (0..n)
+                .map(|i| self.from + (self.to - self.from) * i as f32 / (n - 1) as f32)
+                .collect(),
+        }
+    }
+}
+
+/// The parameters of a sweep: those of `base`, with every axis varied.
+#[derive(Clone, Debug)]
+pub struct Space {
+    base: Params,
+    axes: Vec<Axis>,
+}
+
+impl Space {
+    pub fn new(base: Params, axes: Vec<Axis>) -> Result<Self, String> {
+        for (i, axis) in axes.iter().enumerate() {
+            if axes[..i].iter().any(|other| other.name == axis.name) {
+                return Err(sweep_error(&format!("{} is swept twice", axis.name)));
+            }
+        }
+        Ok(Self { base, axes })
+    }
+
+    /// The parameters every point starts from.
+    pub fn base(&self) -> Params {
+        self.base
+    }
+
+    pub fn axes(&self) -> &[Axis] {
+        &self.axes
+    }
+
+    /// Every combination of the axes' values, the last axis varying fastest.
+    pub fn grid(&self) -> Vec<Params> {
+        self.axes.iter().fold(vec![self.base], |points, axis| {
+            points
+                .iter()
+                .flat_map(|params| {
+                    axis.values().into_iter().map(|value| {
+                        let mut params = *params;
+                        set(&mut params, axis.name, value);
+                        params
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// `count` points drawn uniformly from the ranges of the axes.
+    pub fn sample(&self, count: usize, rng: &mut Rng) -> Vec<Params> {
+        (0..count)
+            .map(|_| {
+                let mut params = self.base;
+                for axis in self.axes.iter() {
+                    let (low, high) = (axis.from.min(axis.to), axis.from.max(axis.to));
+                    let value = if high > low {
+                        rng.gen_range(low..=high)
+                    } else {
+                        low
+                    };
+                    set(&mut params, axis.name, value);
+                }
+                params
+            })
+            .collect()
+    }
+}
+
+/// The value of the parameter called `name` in `params`.
+pub fn get(params: &Params, name: &str) -> f32 {
+    match name {
+        "ra" => params.ra,
+        "b1" => params.b1,
+        "b2" => params.b2,
+        "d1" => params.d1,
+        "d2" => params.d2,
+        "alpha-n" => params.alpha_n,
+        "alpha-m" => params.alpha_m,
+        "dt" => params.dt,
+        _ => unreachable!("unknown parameter {name}"),
+    }
+}
+
+/// Sets the parameter called `name` in `params`.
+pub fn set(params: &mut Params, name: &str, value: f32) {
+    let field = match name {
+        "ra" => &mut params.ra,
+        "b1" => &mut params.b1,
+        "b2" => &mut params.b2,
+        "d1" => &mut params.d1,
+        "d2" => &mut params.d2,
+        "alpha-n" => &mut params.alpha_n,
+        "alpha-m" => &mut params.alpha_m,
+        "dt" => &mut params.dt,
+        _ => unreachable!("unknown parameter {name}"),
+    };
+    *field = value;
+}
+
+/// What a field did under one set of parameters.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// Faded to nothing.
+    Dies,
+    /// Filled the whole field.
+    Full,
+    /// Stopped changing.
+    Static,
+    /// Kept changing, with nothing travelling across the field.
+    Chaotic,
+    /// Left separate structures that move, such as gliders.
+    Moving,
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 5] = [
+        Outcome::Moving,
+        Outcome::Chaotic,
+        Outcome::Static,
+        Outcome::Full,
+        Outcome::Dies,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::Dies => "dies",
+            Outcome::Full => "full",
+            Outcome::Static => "static",
+            Outcome::Chaotic => "chaotic",
+            Outcome::Moving => "moving",
+        }
+    }
+}
+
+/// How a configuration ended up after a trial run.
+#[derive(Clone, Debug)]
+pub struct Trial {
+    pub params: Params,
+    pub outcome: Outcome,
+    /// Steps run, fewer than asked when the field became uniform.
+    pub steps: u64,
+    /// Mean value of the final field.
+    pub mean: f64,
+    /// Blobs in the final field.
+    pub blobs: usize,
+    /// Speed of the fastest small blob, in cells per step.
+    pub speed: f64,
+    /// Standard deviation of the mass over the tracked steps, relative to its mean.
+    pub variation: f64,
+    pub cells: Vec<Vec<f32>>,
+}
+
+impl Trial {
+    /// Runs `cells` under `params` for `steps` steps on the CPU and classifies the outcome, with
+    /// blobs made of the cells at or above `threshold`. A uniform field that a step leaves as it
+    /// was never changes again, so the run stops there.
+    pub fn run(params: Params, mut cells: Vec<Vec<f32>>, steps: u64, threshold: f32) -> Self {
+        let tracked_from = steps.saturating_sub(TRACKED_STEPS);
+        let mut tracker = Tracker::new(threshold);
+        let mut tracked = None;
+        let mut masses = Vec::new();
+        let mut done = 0;
+
+        while done < steps {
+            let before = uniform_value(&cells);
+            step(&mut cells, &params);
+            done += 1;
+            if done >= tracked_from {
+                tracked.get_or_insert_with(|| mask(&cells, threshold));
+                tracker.update(done, &cells);
+                masses.push(cells.iter().flatten().map(|&v| v as f64).sum::<f64>());
+            }
+            if before.is_some() && before == uniform_value(&cells) {
+                break;
+            }
+        }
+
+        let crossed = tracked.map_or(0, |before| {
+            let after = mask(&cells, threshold);
+            before
+                .iter()
+                .zip(after.iter())
+                .filter(|(a, b)| a != b)
+                .count()
+        });
+        let area = (cells.len() * cells[0].len()) as f64;
+        let mean = cells.iter().flatten().map(|&v| v as f64).sum::<f64>() / area;
+        let coverage = tracker.blobs().iter().map(|b| b.area).sum::<usize>() as f64 / area;
+        let speed = tracker
+            .blobs()
+            .iter()
+            .filter(|blob| blob.area as f64 <= MAX_LOCAL_AREA * area)
+            .map(|blob| &tracker.tracks()[blob.id])
+            .filter(|track| track.points.len() as u64 >= TRACKED_STEPS.min(steps) / 2)
+            .map(|track| blobs::speed_and_heading(track.mean_velocity()).0)
+            .fold(0.0, f64::max);
+
+        let mass = masses.iter().sum::<f64>() / masses.len().max(1) as f64;
+        let variance =
+            masses.iter().map(|m| (m - mass).powi(2)).sum::<f64>() / masses.len().max(1) as f64;
+        let variation = if mass > 0.0 {
+            variance.sqrt() / mass
+        } else {
+            0.0
+        };
+
+        let outcome = if mean < DEAD_MEAN {
+            Outcome::Dies
+        } else if mean > FULL_MEAN {
+            Outcome::Full
+        } else if (crossed as f64) < STILL_SHARE * area {
+            Outcome::Static
+        } else if coverage <= MAX_LOCAL_COVERAGE && speed >= MIN_MOVING_SPEED {
+            Outcome::Moving
+        } else {
+            Outcome::Chaotic
+        };
+        Self {
+            params,
+            outcome,
+            steps: done,
+            mean,
+            blobs: tracker.blobs().len(),
+            speed,
+            variation,
+            cells,
+        }
+    }
+}
+
+// The value of every cell, if they all have the same.
+fn uniform_value(cells: &[Vec<f32>]) -> Option<f32> {
+    let first = cells[0][0];
+    cells.iter().flatten().all(|&v| v == first).then_some(first)
+}
+
+// Which cells are at or above `threshold`, row after row.
+fn mask(cells: &[Vec<f32>], threshold: f32) -> Vec<bool> {
+    cells.iter().flatten().map(|&v| v >= threshold).collect()
+}
+
+/// One configuration of a sweep, as reported.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// Position in the sweep, starting at 0.
+    pub index: usize,
+    pub trial: Trial,
+    /// Where the final field was saved, if it was.
+    pub thumbnail: Option<String>,
+}
+
+/// Every configuration of a sweep, in order.
+#[derive(Clone, Debug)]
+pub struct SweepReport {
+    axes: Vec<Axis>,
+    entries: Vec<Entry>,
+}
+
+impl SweepReport {
+    pub fn new(space: &Space) -> Self {
+        Self {
+            axes: space.axes().to_vec(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, entry: Entry) {
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn finish(&mut self) {
+        self.entries.sort_by_key(|entry| entry.index);
+    }
+
+    /// How many configurations ended each way, most interesting first, such as
+    /// `2 moving, 10 chaotic, 0 static, 5 full, 10 dies`.
+    pub fn counts(&self) -> String {
+        Outcome::ALL
+            .iter()
+            .map(|&outcome| {
+                let n = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.trial.outcome == outcome)
+                    .count();
+                format!("{n} {}", outcome.name())
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// One line per configuration with the swept parameters, then the counts.
+    pub fn table(&self) -> String {
+        let mut out = format!("{:>5}", "#");
+        for axis in self.axes.iter() {
+            write!(out, "  {:>8}", axis.name).unwrap();
+        }
+        writeln!(
+            out,
+            "  {:<8}  {:>6}  {:>5}  {:>6}",
+            "outcome", "mean", "blobs", "speed"
+        )
+        .unwrap();
+        for entry in self.entries.iter() {
+            let trial = &entry.trial;
+            write!(out, "{:>5}", entry.index).unwrap();
+            for axis in self.axes.iter() {
+                write!(out, "  {:>8.4}", get(&trial.params, axis.name)).unwrap();
+            }
+            writeln!(
+                out,
+                "  {:<8}  {:>6.3}  {:>5}  {:>6.3}",
+                trial.outcome.name(),
+                trial.mean,
+                trial.blobs,
+                trial.speed
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "{} configurations: {}",
+            self.entries.len(),
+            self.counts()
+        )
+        .unwrap();
+        out
+    }
+
+    /// One row per configuration, with every parameter.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!(
+            "index,{},outcome,steps,mean,blobs,speed,thumbnail\n",
+            PARAMETERS.join(",")
+        );
+        for entry in self.entries.iter() {
+            let trial = &entry.trial;
+            write!(csv, "{}", entry.index).unwrap();
+            for name in PARAMETERS {
+                write!(csv, ",{}", get(&trial.params, name)).unwrap();
+            }
+            writeln!(
+                csv,
+                ",{},{},{:.4},{},{:.4},{}",
+                trial.outcome.name(),
+                trial.steps,
+                trial.mean,
+                trial.blobs,
+                trial.speed,
+                entry.thumbnail.as_deref().unwrap_or("")
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_csv())
+            .map_err(|err| sweep_error(&format!("cannot write {}: {err}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(value: f32) -> Vec<Vec<f32>> {
+        vec![vec![value; 32]; 32]
+    }
+
+    #[test]
+    fn axes_outside_the_valid_parameters_are_refused() {
+        for axis in [
+            "ra=1:20",
+            "alpha-n=0",
+            "alpha_m=-0.1:0.2",
+            "dt=0.1:0",
+            "dt=0:0.1:4",
+        ] {
+            let err = Axis::parse(axis).unwrap_err();
+            assert!(err.starts_with("[SWEEP ERROR]"), "{axis}: {err}");
+        }
+        assert!(Axis::parse("dt=0.01:0.1:4").is_ok());
+        assert!(Axis::parse("b1=-0.5:0.5").is_ok());
+    }
+
+    #[test]
+    fn uniform_fields_run_until_a_step_leaves_them_unchanged() {
+        let params = Params::default();
+
+        let empty = Trial::run(params, field(0.0), 200, 0.5);
+        assert_eq!(empty.steps, 1);
+        assert_eq!(empty.outcome, Outcome::Dies);
+
+        // A full field loses a time step's worth every step, until it settles well below full.
+        for value in [1.0, 0.4] {
+            let mut cells = field(value);
+            step(&mut cells, &params);
+            assert_ne!(cells[0][0], value);
+
+            let trial = Trial::run(params, field(value), 200, 0.5);
+            assert!(
+                trial.steps > 1,
+                "{value} stopped after {} steps",
+                trial.steps
+            );
+            assert!(trial.mean < FULL_MEAN);
+            assert_eq!(trial.outcome, Outcome::Static);
+        }
+    }
+}
@@ -1,7 +1,33 @@
-use rand::Rng;
+use cellular_automata::history::{FieldSample, History};
+use cellular_automata::render::gif::GifRecorder;
+use cellular_automata::render::sequence::FrameSequence;
+use cellular_automata::render::stream::{FrameStream, StreamFormat};
+use cellular_automata::render::{
+    self, image_error, Capture, CaptureOptions, Captures, Frame, Palette,
+};
+use cellular_automata::rng::{self, Rng};
+use cellular_automata::smoothlife::blobs::{self, Tracker};
+use cellular_automata::smoothlife::snapshot::Snapshot;
+use cellular_automata::smoothlife::{self, Params};
+use cellular_automata::soup::FieldSoup;
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use rand::Rng as _;
 use raylib::consts::{TextureFilter, TextureWrap};
 use raylib::core::texture::Image;
 use raylib::prelude::*;
+use std::path::Path;
+
+const APP_NAME: &str = "SmoothLife";
+const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
+const BIN_NAME: Option<&str> = option_env!("CARGO_PKG_NAME");
+const DESCRIPTION: Option<&str> = option_env!("CARGO_PKG_DESCRIPTION");
+const AUTHORS: Option<&str> = option_env!("CARGO_PKG_AUTHORS");
+
+const HELP_TEMPLATE: &str = "\
+SmoothLife ({version}) - {about-with-newline}
+{usage-heading} {usage}
+{all-args}
+{author-section}";
 
 // RayLib constants
 const SCREEN_WIDTH: i32 = 1600;
@@ -9,6 +35,41 @@ const SCREEN_HEIGHT: i32 = 900;
 const FPS: u32 = 60;
 const SHADER: &str = include_str!("static/smoothlife.fs");
 const SCALAR: f32 = 0.8;
+const DEFAULT_SNAPSHOT_FILE: &str = "smoothlife.snapshot";
+const DEFAULT_PNG_FILE: &str = "field.png";
+const DEFAULT_CELL_SIZE: u32 = 1;
+const DEFAULT_GIF_FILE: &str = "field.gif";
+const DEFAULT_FRAME_DELAY: u32 = 100;
+const BLOB_COLOR: Color = Color::RED;
+// Velocity vectors are drawn as far as a blob would move in this many steps.
+const VELOCITY_SCALE: f32 = 30.0;
+
+// The shader runs its own tuning of the transition.
+const PARAMS: Params = Params {
+    ra: 21.0,
+    b1: 0.257,
+    b2: 0.336,
+    d1: 0.365,
+    d2: 0.549,
+    alpha_n: 0.028,
+    alpha_m: 0.147,
+    dt: 0.08,
+};
+
+#[derive(Clone, Copy)]
+enum InitialState {
+    Perlin,
+    Field(FieldSoup),
+}
+
+impl InitialState {
+    fn parse(kind: &str) -> Result<Self, String> {
+        match kind.trim() {
+            "perlin" => Ok(InitialState::Perlin),
+            kind => FieldSoup::parse(kind).map(InitialState::Field),
+        }
+    }
+}
 
 struct Board {
     image: Image,
@@ -22,24 +83,54 @@ impl Board {
         }
     }
 
-    fn randomize(&mut self) {
-        let mut rng = rand::thread_rng();
-        for y in 0..self.image.height {
-            for x in 0..self.image.width {
-                let c: u8 = rng.gen();
+    fn from_field(field: &[Vec<f32>]) -> Board {
+        let mut board = Board::new(field.len() as i32, field[0].len() as i32);
+        board.paint(field);
+        board
+    }
+
+    fn randomize(&mut self, soup: FieldSoup, density: f64, rng: &mut Rng) {
+        let (w, h) = (self.image.width, self.image.height);
+        let field = soup.generate(h as usize, w as usize, PARAMS.ra, density, rng);
+        self.paint(&field);
+    }
+
+    fn paint(&mut self, field: &[Vec<f32>]) {
+        for (y, row) in field.iter().enumerate() {
+            for (x, v) in row.iter().enumerate() {
+                let c = (v.clamp(0.0, 1.0) * 255.0) as u8;
                 let color = Color::new(c, c, c, 255);
-                self.image.draw_pixel(x, y, color);
+                self.image.draw_pixel(x as i32, y as i32, color);
             }
         }
     }
 
-    fn randomize_perlin_noize(&mut self) {
-        self.image = Image::gen_image_perlin_noise(self.image.width, self.image.height, 0, 0, 4.0)
+    fn randomize_perlin_noize(&mut self, rng: &mut Rng) {
+        let (w, h) = (self.image.width, self.image.height);
+        let (ox, oy) = (rng.gen_range(0..1 << 16), rng.gen_range(0..1 << 16));
+        self.image = Image::gen_image_perlin_noise(w, h, ox, oy, 4.0)
     }
 
     fn get_image(&self) -> &Image {
         &self.image
     }
+
+    fn to_field(&self) -> Vec<Vec<f32>> {
+        image_to_field(&self.image)
+    }
+}
+
+fn image_to_field(image: &Image) -> Vec<Vec<f32>> {
+    let w = image.width as usize;
+    image
+        .get_image_data()
+        .chunks(w)
+        .map(|row| {
+            row.iter()
+                .map(|c| c.r.max(c.g).max(c.b) as f32 / 255.0)
+                .collect()
+        })
+        .collect()
 }
 
 fn create_render_texture(
@@ -55,56 +146,615 @@ fn create_render_texture(
     t
 }
 
-fn main() {
-    let h = (SCREEN_HEIGHT as f32 * SCALAR) as i32;
-    let w = (SCREEN_WIDTH as f32 * SCALAR) as i32;
+// Both render textures for the field in `image`, the first one holding it.
+fn upload_field(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    image: &Image,
+) -> (RenderTexture2D, RenderTexture2D) {
+    let texture = rl.load_texture_from_image(thread, image).unwrap();
+    let (w, h) = (image.width as u32, image.height as u32);
+    let mut state0 = create_render_texture(rl, thread, w, h);
+    let state1 = create_render_texture(rl, thread, w, h);
+
+    {
+        let mut d = rl.begin_drawing(thread);
+        let mut d = d.begin_texture_mode(thread, &mut state0);
+        d.clear_background(Color::BLACK);
+        d.draw_texture(&texture, 0, 0, Color::WHITE);
+    }
+
+    (state0, state1)
+}
+
+// Reads the field back from the GPU. Render textures come back upside down.
+fn read_field(state: &RenderTexture2D) -> Result<Vec<Vec<f32>>, String> {
+    let mut image = state.get_texture_data()?;
+    image.flip_vertical();
+    Ok(image_to_field(&image))
+}
+
+// Starts recording a field to a GIF, beginning with `cells`.
+fn record_field(
+    cells: &[Vec<f32>],
+    path: &str,
+    cell_size: usize,
+    palette: &Palette,
+    options: CaptureOptions,
+) -> Result<GifRecorder, String> {
+    let frame = Frame::from_field(cells, cell_size, palette);
+    let mut recorder = GifRecorder::create(
+        path,
+        frame.width,
+        frame.height,
+        &palette.colours(256),
+        options,
+    )?;
+    recorder.offer(&frame)?;
+    Ok(recorder)
+}
+
+// Runs the field of `run` for `options.frames` frames, `options.skip` steps apart, into the
+// captures, a PNG sequence and the history. Returns what was written.
+// Steps `run` until the captures have all their frames, passing each new field to `observe`.
+fn run_headless(
+    run: &mut Snapshot,
+    mut observe: impl FnMut(u64, &[Vec<f32>]),
+    captures: &mut Captures,
+    out_path: Option<&String>,
+    cell_size: usize,
+    palette: &Palette,
+    options: CaptureOptions,
+) -> Result<Vec<String>, String> {
+    let mut sequence = out_path.map(|dir| FrameSequence::create(dir)).transpose()?;
+    let skip = options.skip.max(1);
+    let last = options.frames.unwrap_or(1).saturating_sub(1) * skip;
+
+    let mut reports = Vec::new();
+    for step in 0..=last {
+        if step > 0 {
+            smoothlife::step(&mut run.cells, &run.params);
+            run.step += 1;
+            observe(run.step, &run.cells);
+            reports.extend(captures.offer(|| Frame::from_field(&run.cells, cell_size, palette)));
+        }
+        if let Some(sequence) = &mut sequence {
+            if step.is_multiple_of(skip) {
+                sequence.write(&Frame::from_field(&run.cells, cell_size, palette))?;
+            }
+        }
+    }
+    // A single frame leaves the captures open.
+    reports.extend(captures.stop());
 
-    // Board Setup
-    let mut board = Board::new(h, w);
-    // board.randomize();
-    board.randomize_perlin_noize();
+    let mut reports = reports.into_iter().collect::<Result<Vec<_>, _>>()?;
+    if let Some(sequence) = &sequence {
+        reports.push(format!(
+            "Wrote {} frames to {}",
+            sequence.written(),
+            sequence.describe()
+        ));
+    }
+    Ok(reports)
+}
+
+fn set_uniforms(shader: &mut Shader, params: &Params, w: i32, h: i32) {
+    let loc = shader.get_shader_location("resolution");
+    shader.set_shader_value(loc, [w as f32, h as f32]);
+
+    for (name, value) in [
+        ("ra", params.ra),
+        ("b1", params.b1),
+        ("b2", params.b2),
+        ("d1", params.d1),
+        ("d2", params.d2),
+        ("alpha_n", params.alpha_n),
+        ("alpha_m", params.alpha_m),
+        ("dt", params.dt),
+    ] {
+        let loc = shader.get_shader_location(name);
+        shader.set_shader_value(loc, value);
+    }
+}
+
+fn start_tracking(threshold: f32, step: u64, cells: &[Vec<f32>]) -> Tracker {
+    let mut tracker = Tracker::new(threshold);
+    tracker.update(step, cells);
+    tracker
+}
+
+// Writes the trajectories to `path` and returns what to tell about them.
+fn save_tracks(tracker: &Tracker, path: &str) -> Result<Vec<String>, String> {
+    tracker.save(path)?;
+    let mut lines = tracker.summary();
+    lines.push(format!("Trajectories written to {path}"));
+    Ok(lines)
+}
+
+// Labels each blob with its id at its centroid and draws its velocity from there.
+fn draw_blobs(d: &mut RaylibDrawHandle, tracker: &Tracker, scale: f32) {
+    for blob in tracker.blobs() {
+        let (x, y) = blob.centroid;
+        let (vx, vy) = blob.velocity;
+        let from = Vector2::new(x as f32 * scale, y as f32 * scale);
+        let to = from + Vector2::new(vx as f32, vy as f32) * VELOCITY_SCALE * scale;
+        d.draw_line_ex(from, to, 2.0, BLOB_COLOR);
+        d.draw_circle_v(from, 3.0, BLOB_COLOR);
+        d.draw_text(
+            &blob.id.to_string(),
+            from.x as i32 + 4,
+            from.y as i32 + 4,
+            20,
+            BLOB_COLOR,
+        );
+    }
+}
+
+// Largest zoom at which the field fits the window.
+fn display_scale(w: i32, h: i32) -> f32 {
+    (SCREEN_WIDTH as f32 / w as f32).min(SCREEN_HEIGHT as f32 / h as f32)
+}
+
+fn main() -> Result<(), String> {
+    let matches = get_args();
+    let initial = matches
+        .get_one::<InitialState>("soup")
+        .unwrap_or(&InitialState::Perlin);
+    let density = matches.get_one::<f64>("density").unwrap_or(&0.5);
+    let snapshot_path = matches.get_one::<String>("snapshot");
+    let import_path = matches.get_one::<String>("import");
+    let png_path = matches
+        .get_one::<String>("png")
+        .map_or(DEFAULT_PNG_FILE, String::as_str);
+    let cell_size = *matches
+        .get_one::<u32>("cell-size")
+        .unwrap_or(&DEFAULT_CELL_SIZE) as usize;
+    let palette = matches
+        .get_one::<Palette>("palette")
+        .cloned()
+        .unwrap_or_else(Palette::gray);
+    let record_path = matches.get_one::<String>("record");
+    let stream_path = matches.get_one::<String>("stream");
+    let stream_format = *matches
+        .get_one::<StreamFormat>("stream-format")
+        .unwrap_or(&StreamFormat::Y4m);
+    let capture_options = create_capture_options(&matches);
+    let history_path = matches.get_one::<String>("history");
+    let track_path = matches.get_one::<String>("track");
+    let blob_threshold = *matches
+        .get_one::<f32>("blob-threshold")
+        .unwrap_or(&blobs::DEFAULT_THRESHOLD);
+
+    // Board Setup: an existing `--snapshot` file takes the place of the soup, as does `--import`.
+    let (board, mut params, mut seed, mut step) = match snapshot_path
+        .filter(|path| Path::new(path).exists())
+    {
+        Some(path) => {
+            let snapshot = Snapshot::load(path)?;
+            let board = Board::from_field(&snapshot.cells);
+            (board, snapshot.params, snapshot.seed, snapshot.step)
+        }
+        None => {
+            let seed = rng::resolve_seed(matches.get_one::<u64>("seed").copied());
+            let h = (SCREEN_HEIGHT as f32 * SCALAR) as i32;
+            let w = (SCREEN_WIDTH as f32 * SCALAR) as i32;
+
+            let mut rng = rng::from_seed_stream(seed, rng::SOUP_STREAM);
+            let mut board = Board::new(h, w);
+            match (import_path, initial) {
+                (Some(path), _) => {
+                    board = Board::from_field(&render::import_field(path, h as usize, w as usize)?)
+                }
+                (None, InitialState::Perlin) => board.randomize_perlin_noize(&mut rng),
+                (None, InitialState::Field(soup)) => board.randomize(*soup, *density, &mut rng),
+            }
+            (board, PARAMS, seed, 0)
+        }
+    };
+
+    let headless = matches.get_flag("headless");
+    let out_path = matches.get_one::<String>("out");
+    if stream_path.is_some_and(|target| target == "-") {
+        if !headless {
+            return Err(image_error(
+                "only --headless runs can stream to stdout, where messages go otherwise",
+            ));
+        }
+        if out_path.is_some_and(|dir| dir == "-") {
+            return Err(image_error(
+                "--stream and --out cannot both write to stdout",
+            ));
+        }
+    }
+
+    let field = board.to_field();
+    let mut captures = Captures {
+        gif: record_path
+            .map(|path| record_field(&field, path, cell_size, &palette, capture_options))
+            .transpose()?,
+        stream: stream_path
+            .map(|target| FrameStream::open(target, stream_format, capture_options))
+            .transpose()?,
+    };
+    // The recording starts with the current field, and so do the stream and the history.
+    if let Some(stream) = &mut captures.stream {
+        stream.offer(&Frame::from_field(&field, cell_size, &palette))?;
+    }
+    let mut history = History::default();
+    history.push(FieldSample::of(step, &field));
+    let mut tracker = track_path.map(|_| start_tracking(blob_threshold, step, &field));
+    let mut show_blobs = false;
+
+    // Without a window the field runs on the CPU engine, at full precision.
+    if headless {
+        // Frames sent to stdout leave no room for messages there.
+        let quiet = captures.is_stdout() || out_path.is_some_and(|dir| dir == "-");
+        let mut run = Snapshot {
+            cells: field,
+            params,
+            seed,
+            step,
+        };
+        let mut reports = run_headless(
+            &mut run,
+            |step, cells| {
+                history.push(FieldSample::of(step, cells));
+                if let Some(tracker) = &mut tracker {
+                    tracker.update(step, cells);
+                }
+            },
+            &mut captures,
+            out_path,
+            cell_size,
+            &palette,
+            capture_options,
+        )?;
+        if let Some(path) = history_path {
+            history.save(path)?;
+            reports.push(format!("History written to {path}"));
+        }
+        if let Some((path, tracker)) = track_path.zip(tracker.as_ref()) {
+            reports.extend(save_tracks(tracker, path)?);
+        }
+        for line in reports.iter().chain([&format!("Seed: {seed}")]) {
+            if quiet {
+                eprintln!("{line}");
+            } else {
+                println!("{line}");
+            }
+        }
+        return Ok(());
+    }
 
     // RayLib setup
     let (mut rl, thread) = raylib::init()
         .size(SCREEN_WIDTH, SCREEN_HEIGHT)
-        .title("SmoothLife")
+        .title(&format!("SmoothLife (seed {seed})"))
         .build();
     rl.set_target_fps(FPS);
 
-    // Texture setup
-    let texture = rl
-        .load_texture_from_image(&thread, board.get_image())
-        .unwrap();
-
     // RenderTexture setup
-    let mut state0 = create_render_texture(&mut rl, &thread, w as u32, h as u32);
-    let mut state1 = create_render_texture(&mut rl, &thread, w as u32, h as u32);
-
-    {
-        let mut d = rl.begin_drawing(&thread);
-        let mut d = d.begin_texture_mode(&thread, &mut state0);
-        d.clear_background(Color::BLACK);
-        d.draw_texture(&texture, 0, 0, Color::WHITE);
-    }
+    let (mut state0, mut state1) = upload_field(&mut rl, &thread, board.get_image());
 
     // Shader setup
     let mut shader = rl.load_shader_from_memory(&thread, None, Some(SHADER));
-    let loc = shader.get_shader_location("resolution");
-    shader.set_shader_value(loc, [texture.width as f32, texture.height as f32]);
+    set_uniforms(&mut shader, &params, state0.width(), state0.height());
+
+    let snapshot_file = snapshot_path.map_or(DEFAULT_SNAPSHOT_FILE, String::as_str);
+    let mut message: Option<String> = None;
 
     // Main loop
     while !rl.window_should_close() {
+        let ctrl = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+            || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        if ctrl && rl.is_key_pressed(KeyboardKey::KEY_S) {
+            let saved = read_field(&state0).and_then(|cells| {
+                Snapshot {
+                    cells,
+                    params,
+                    seed,
+                    step,
+                }
+                .save(snapshot_file)
+            });
+            message = Some(
+                saved.map_or_else(|err| err, |_| format!("Saved snapshot to {snapshot_file}")),
+            );
+        }
+        if ctrl && rl.is_key_pressed(KeyboardKey::KEY_O) {
+            match Snapshot::load(snapshot_file) {
+                Ok(snapshot) => {
+                    let board = Board::from_field(&snapshot.cells);
+                    (state0, state1) = upload_field(&mut rl, &thread, board.get_image());
+                    (params, seed, step) = (snapshot.params, snapshot.seed, snapshot.step);
+                    history = History::default();
+                    history.push(FieldSample::of(step, &snapshot.cells));
+                    if tracker.is_some() {
+                        tracker = Some(start_tracking(blob_threshold, step, &snapshot.cells));
+                    }
+                    set_uniforms(&mut shader, &params, state0.width(), state0.height());
+                    rl.set_window_title(&thread, &format!("SmoothLife (seed {seed})"));
+                    message = Some(format!("Restored snapshot from {snapshot_file}"));
+                }
+                Err(err) => message = Some(err),
+            }
+        }
+        if !ctrl && rl.is_key_pressed(KeyboardKey::KEY_P) {
+            let exported = read_field(&state0).and_then(|cells| {
+                Frame::from_field(&cells, cell_size, &palette).save_png(png_path)
+            });
+            message = Some(exported.map_or_else(|err| err, |_| format!("Exported {png_path}")));
+        }
+        if !ctrl && rl.is_key_pressed(KeyboardKey::KEY_T) {
+            show_blobs = !show_blobs;
+            if show_blobs && tracker.is_none() {
+                match read_field(&state0) {
+                    Ok(cells) => tracker = Some(start_tracking(blob_threshold, step, &cells)),
+                    Err(err) => {
+                        show_blobs = false;
+                        message = Some(err);
+                    }
+                }
+            }
+        }
+        if !ctrl && rl.is_key_pressed(KeyboardKey::KEY_G) {
+            let report = match render::stop(&mut captures.gif) {
+                Some(report) => report.unwrap_or_else(|err| err),
+                None => {
+                    let path = record_path.map_or(DEFAULT_GIF_FILE, String::as_str);
+                    let started = read_field(&state0).and_then(|cells| {
+                        record_field(&cells, path, cell_size, &palette, capture_options)
+                    });
+                    match started {
+                        Ok(started) => {
+                            captures.gif = Some(started);
+                            format!("Recording to {path}")
+                        }
+                        Err(err) => err,
+                    }
+                }
+            };
+            message = Some(report);
+        }
+
+        let scale = display_scale(state0.width(), state0.height());
         let mut d = rl.begin_drawing(&thread);
         d.clear_background(Color::BLACK);
-        d.draw_texture_ex(&state0, Vector2::zero(), 0.0, 1.0 / SCALAR, Color::WHITE);
+        d.draw_texture_ex(&state0, Vector2::zero(), 0.0, scale, Color::WHITE);
+        if let Some(tracker) = tracker.as_ref().filter(|_| show_blobs) {
+            draw_blobs(&mut d, tracker, scale);
+        }
+        if let Some(message) = &message {
+            d.draw_text(message, 10, 10, 20, Color::RAYWHITE);
+        }
 
         {
             let mut d = d.begin_texture_mode(&thread, &mut state1);
             let mut d = d.begin_shader_mode(&shader);
             d.draw_texture(&state0, 0, 0, Color::WHITE);
         }
+        drop(d);
 
         // Swap states
         std::mem::swap(&mut state0, &mut state1);
+        step += 1;
+
+        // Render textures are read back every step while something captures or logs them.
+        if captures.is_active() || history_path.is_some() || tracker.is_some() {
+            match read_field(&state0) {
+                Ok(cells) => {
+                    if history_path.is_some() {
+                        history.push(FieldSample::of(step, &cells));
+                    }
+                    if let Some(tracker) = &mut tracker {
+                        tracker.update(step, &cells);
+                    }
+                    for report in captures.offer(|| Frame::from_field(&cells, cell_size, &palette))
+                    {
+                        message = Some(report.unwrap_or_else(|err| err));
+                    }
+                }
+                Err(err) => {
+                    captures.stop();
+                    message = Some(err);
+                }
+            }
+        }
+    }
+
+    for report in captures.stop() {
+        match report {
+            Ok(report) => println!("{report}"),
+            Err(err) => eprintln!("{err}"),
+        }
+    }
+
+    if let Some(path) = history_path {
+        history.save(path)?;
+        println!("History written to {path}");
     }
+    if let Some((path, tracker)) = track_path.zip(tracker.as_ref()) {
+        for line in save_tracks(tracker, path)? {
+            println!("{line}");
+        }
+    }
+    if let Some(path) = snapshot_path {
+        let cells = read_field(&state0)?;
+        Snapshot {
+            cells,
+            params,
+            seed,
+            step,
+        }
+        .save(path)?;
+        println!("Saved snapshot to {path}");
+    }
+    Ok(())
+}
+
+fn create_capture_options(matches: &ArgMatches) -> CaptureOptions {
+    let mut options = CaptureOptions {
+        delay_ms: *matches
+            .get_one::<u32>("frame-delay")
+            .unwrap_or(&DEFAULT_FRAME_DELAY),
+        skip: *matches.get_one::<u32>("frame-skip").unwrap_or(&1) as usize,
+        frames: matches.get_one::<u32>("frames").map(|&n| n as usize),
+    };
+    if let Some(&seconds) = matches.get_one::<f64>("duration") {
+        options.frames = Some(options.frames_in(seconds));
+    }
+    options
+}
+
+fn get_args() -> ArgMatches {
+    Command::new(APP_NAME)
+        .display_name(BIN_NAME.unwrap_or("Unknown"))
+        .author(AUTHORS.unwrap_or("Unknown"))
+        .about(DESCRIPTION.unwrap_or("Unknown"))
+        .version(VERSION.unwrap_or("Unknown"))
+        .help_template(HELP_TEMPLATE)
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Seed for the random number generator")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("soup")
+                .long("soup")
+                .value_name("kind")
+                .action(ArgAction::Set)
+                .help("Initial field: perlin, uniform, noise, blobs or rings [default: perlin]")
+                .value_parser(InitialState::parse),
+        )
+        .arg(
+            Arg::new("density")
+                .long("density")
+                .value_name("p")
+                .action(ArgAction::Set)
+                .help("Coverage of blobs and rings soups [default: 0.5]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("snapshot")
+                .long("snapshot")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Resume the field saved in file, if it exists, and save it there on quit"),
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("CSV file the mass and mean value of every step are written to on exit"),
+        )
+        .arg(
+            Arg::new("track")
+                .long("track")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Track blobs of the field and write their trajectories to this CSV file on exit"),
+        )
+        .arg(
+            Arg::new("blob-threshold")
+                .long("blob-threshold")
+                .value_name("t")
+                .action(ArgAction::Set)
+                .help("Field value from which cells belong to a blob [default: 0.5]")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("import")
+                .long("import")
+                .value_name("image")
+                .action(ArgAction::Set)
+                .help("Start from the luminance of a PNG or PPM image instead of a soup"),
+        )
+        .arg(
+            Arg::new("png")
+                .long("png")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the field is exported to as PNG [default: field.png]"),
+        )
+        .arg(
+            Arg::new("cell-size")
+                .long("cell-size")
+                .value_name("px")
+                .action(ArgAction::Set)
+                .help("Pixels per cell in exported images [default: 1]")
+                .value_parser(value_parser!(u32).range(1..=64)),
+        )
+        .arg(
+            Arg::new("palette")
+                .long("palette")
+                .value_name("colours")
+                .action(ArgAction::Set)
+                .help("Palette of exported images: gray, inverted, heat, ocean or hex colours such as 000000,ffffff [default: gray]")
+                .value_parser(Palette::parse),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Record every step of the field into an animated GIF"),
+        )
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Stop recording after this many frames")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .value_name("seconds")
+                .action(ArgAction::Set)
+                .help("Stop recording once the GIF lasts this long")
+                .value_parser(render::parse_duration),
+        )
+        .group(ArgGroup::new("length").args(["frames", "duration"]))
+        .arg(
+            Arg::new("frame-delay")
+                .long("frame-delay")
+                .value_name("ms")
+                .action(ArgAction::Set)
+                .help("Delay between GIF frames, in milliseconds [default: 100]")
+                .value_parser(value_parser!(u32).range(10..=655350)),
+        )
+        .arg(
+            Arg::new("frame-skip")
+                .long("frame-skip")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Record only every num-th step [default: 1]")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("out")
+                .long("out")
+                .value_name("dir")
+                .action(ArgAction::Set)
+                .requires("headless")
+                .help("Directory headless frames are written to as numbered PNGs, or - for stdout"),
+        )
+        .group(
+            ArgGroup::new("output")
+                .args(["record", "stream", "out", "history", "track"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .action(ArgAction::SetTrue)
+                .requires_all(["output", "length"])
+                .help("Render, log history or track blobs without opening a window, then exit"),
+        )
+        .get_matches()
 }
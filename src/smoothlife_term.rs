@@ -1,46 +1,162 @@
-use rand::Rng;
-use std::io::{stdin, stdout, Write};
+use cellular_automata::evolution::{self, Checkpoint, Evolution, Objective, Traits};
+use cellular_automata::history::{self, FieldSample, History};
+use cellular_automata::render::gif::GifRecorder;
+use cellular_automata::render::stream::{FrameStream, StreamFormat};
+use cellular_automata::render::{
+    self, image_error, Capture, CaptureOptions, Captures, Frame, Palette,
+};
+use cellular_automata::rng::{self, Rng};
+use cellular_automata::smoothlife::blobs::{self, Tracker};
+use cellular_automata::smoothlife::snapshot::Snapshot;
+use cellular_automata::smoothlife::sweep::{self, Axis, Entry, Outcome, Space, SweepReport, Trial};
+use cellular_automata::smoothlife::{self, Params};
+use cellular_automata::soup::{self, FieldSoup};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use std::fs;
+use std::io::{stderr, stdin, stdout, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc;
-use std::{f32::consts::PI, thread, time::Duration};
+use std::thread;
+use std::time::{Duration, Instant};
 use termion::event::Key;
 use termion::input::TermRead;
 use termion::raw::IntoRawMode;
-use termion::{clear, cursor};
+use termion::{clear, color, cursor, style};
+
+const APP_NAME: &str = "SmoothLife";
+const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
+const BIN_NAME: Option<&str> = option_env!("CARGO_PKG_NAME");
+const DESCRIPTION: Option<&str> = option_env!("CARGO_PKG_DESCRIPTION");
+const AUTHORS: Option<&str> = option_env!("CARGO_PKG_AUTHORS");
+
+const HELP_TEMPLATE: &str = "\
+SmoothLife ({version}) - {about-with-newline}
+{usage-heading} {usage}
+{all-args}
+{author-section}";
 
 const WIDTH: usize = 150;
 const HEIGHT: usize = 150;
 
 const LEVEL: [char; 10] = [' ', '.', '-', '=', 'c', 'o', 'a', 'A', '@', '#'];
-const RA: f32 = 21.0;
-const RI: f32 = RA / 3.0;
-const ALPHA_N: f32 = 0.028;
-const ALPHA_M: f32 = 0.147;
-const B1: f32 = 0.278;
-const B2: f32 = 0.365;
-const D1: f32 = 0.267;
-const D2: f32 = 0.445;
-const DT: f32 = 0.05;
+const DEFAULT_SNAPSHOT_FILE: &str = "smoothlife.snapshot";
+const DEFAULT_PNG_FILE: &str = "field.png";
+const DEFAULT_CELL_SIZE: u32 = 4;
+const DEFAULT_GIF_FILE: &str = "field.gif";
+const DEFAULT_FRAME_DELAY: u32 = 100;
+const DEFAULT_HISTORY_FILE: &str = "history.csv";
+const BLOB_COLOR: &dyn color::Color = &color::LightRed;
+// Arrows for headings of 0°, 45°, ... counterclockwise from east.
+const ARROWS: [char; 8] = ['→', '↗', '↑', '↖', '←', '↙', '↓', '↘'];
+// Blobs slower than this many cells per step get no arrow.
+const MIN_ARROW_SPEED: f64 = 0.01;
+const SWEEP_SIZE: (usize, usize) = (128, 128);
+const SWEEP_STEPS: u64 = 300;
+const SWEEP_CELL_SIZE: u32 = 2;
+const EVOLVE_SIZE: (usize, usize) = (64, 64);
+const EVOLVE_GENERATIONS: u64 = 10;
+const EVOLVE_POPULATION: usize = 12;
+const EVOLVE_ELITE: usize = 2;
+const EVOLVE_TOURNAMENT: usize = 3;
+const EVOLVE_MUTATION: f64 = 0.2;
+const EVOLVE_TRIALS: usize = 2;
+const EVOLVE_FITNESS: &str = "motion";
+// The parameters evolved when none are given, around the defaults.
+const EVOLVE_PARAMS: [&str; 4] = ["b1=0.2:0.35", "b2=0.3:0.45", "d1=0.2:0.35", "d2=0.35:0.55"];
+// Blobs at which the diversity of a field reaches a half, and bins of its value histogram.
+const DIVERSITY_HALF: f64 = 5.0;
+const ENTROPY_BINS: usize = 16;
 
 struct Board {
     cells: Vec<Vec<f32>>,
+    params: Params,
+    seed: u64,
+    step: u64,
+    step_time: Option<Duration>,
+    message: Option<String>,
+    history: History<FieldSample>,
+    tracker: Option<Tracker>,
+    show_blobs: bool,
 }
 
 impl Board {
-    fn new(h: usize, w: usize) -> Board {
+    fn new(h: usize, w: usize, params: Params, seed: u64) -> Board {
         Board {
             cells: vec![vec![0.0; w]; h],
+            params,
+            seed,
+            step: 0,
+            step_time: None,
+            message: None,
+            history: History::default(),
+            tracker: None,
+            show_blobs: false,
         }
     }
 
-    fn randomize(&mut self) {
-        let mut rng = rand::thread_rng();
-        for row in self.cells.iter_mut() {
-            for cell in row.iter_mut() {
-                *cell = rng.gen();
-            }
+    fn restore(snapshot: Snapshot) -> Board {
+        Board {
+            cells: snapshot.cells,
+            params: snapshot.params,
+            seed: snapshot.seed,
+            step: snapshot.step,
+            step_time: None,
+            message: None,
+            history: History::default(),
+            tracker: None,
+            show_blobs: false,
         }
     }
 
+    fn to_snapshot(&self) -> Snapshot {
+        Snapshot {
+            cells: self.cells.clone(),
+            params: self.params,
+            seed: self.seed,
+            step: self.step,
+        }
+    }
+
+    fn randomize(&mut self, soup: FieldSoup, density: f64, rng: &mut Rng) {
+        self.cells = soup.generate(
+            self.cells.len(),
+            self.cells[0].len(),
+            self.params.ra,
+            density,
+            rng,
+        );
+    }
+
+    fn import(&mut self, path: &str) -> Result<(), String> {
+        self.cells = render::import_field(path, self.cells.len(), self.cells[0].len())?;
+        Ok(())
+    }
+
+    fn to_frame(&self, cell_size: usize, palette: &Palette) -> Frame {
+        Frame::from_field(&self.cells, cell_size, palette)
+    }
+
+    // Starts recording the field to a GIF, beginning with the current step.
+    fn record(
+        &self,
+        path: &str,
+        cell_size: usize,
+        palette: &Palette,
+        options: CaptureOptions,
+    ) -> Result<GifRecorder, String> {
+        let frame = self.to_frame(cell_size, palette);
+        let mut recorder = GifRecorder::create(
+            path,
+            frame.width,
+            frame.height,
+            &palette.colours(256),
+            options,
+        )?;
+        recorder.offer(&frame)?;
+        Ok(recorder)
+    }
+
     fn display<W: Write>(&self, s: &mut W) {
         write!(s, "{}{}", cursor::Goto(1, 1), clear::AfterCursor).unwrap();
 
@@ -54,60 +170,242 @@ impl Board {
             }
             writeln!(s).unwrap();
         }
+
+        write!(
+            s,
+            "{}{}{}{}",
+            cursor::Goto(1, (self.cells.len() + 2) as u16),
+            style::Invert,
+            self.status(),
+            style::Reset
+        )
+        .unwrap();
+        write!(
+            s,
+            "{}{}",
+            cursor::Goto(1, (self.cells.len() + 3) as u16),
+            self.history_line()
+        )
+        .unwrap();
+        if let Some(message) = &self.message {
+            write!(
+                s,
+                "{}{}",
+                cursor::Goto(1, (self.cells.len() + 4) as u16),
+                message
+            )
+            .unwrap();
+        }
+        if self.show_blobs {
+            self.display_blobs(s);
+        }
     }
 
-    fn next(&mut self) {
-        let cells = self.cells.clone();
-
-        for cy in 0..cells.len() as i32 {
-            for cx in 0..cells[0].len() as i32 {
-                let mut m: f32 = 0.0;
-                let mut n: f32 = 0.0;
-
-                for dy in -(RA - 1.0) as i32..(RA - 1.0) as i32 {
-                    for dx in -(RA - 1.0) as i32..(RA - 1.0) as i32 {
-                        let x = emod(cx + dx, cells[0].len() as i32) as usize;
-                        let y = emod(cy + dy, cells.len() as i32) as usize;
-
-                        if dx * dx + dy * dy <= (RI * RI) as i32 {
-                            m += cells[y][x];
-                        } else if dx * dx + dy * dy <= (RA * RA) as i32 {
-                            n += cells[y][x];
-                        }
-                    }
-                }
+    // Writes each blob's id at its centroid, followed by an arrow in the direction it moves.
+    fn display_blobs<W: Write>(&self, s: &mut W) {
+        let Some(tracker) = &self.tracker else {
+            return;
+        };
+        write!(s, "{}{}", style::Bold, color::Fg(BLOB_COLOR)).unwrap();
+        for blob in tracker.blobs() {
+            let (x, y) = blob.centroid;
+            let (speed, heading) = blobs::speed_and_heading(blob.velocity);
+            let arrow = if speed >= MIN_ARROW_SPEED {
+                ARROWS[((heading / 45.0).round() as usize) % ARROWS.len()].to_string()
+            } else {
+                String::new()
+            };
+            write!(
+                s,
+                "{}{}{arrow}",
+                cursor::Goto(x as u16 * 2 + 1, y as u16 + 1),
+                blob.id
+            )
+            .unwrap();
+        }
+        write!(s, "{}", style::Reset).unwrap();
+    }
 
-                m /= PI * RI * RI;
-                n /= PI * (RA * RA - RI * RI);
+    fn start_tracking(&mut self, threshold: f32) {
+        let mut tracker = Tracker::new(threshold);
+        tracker.update(self.step, &self.cells);
+        self.tracker = Some(tracker);
+    }
 
-                let cell = self.cells[cy as usize][cx as usize] + DT * (2.0 * s(m, n) - 1.0);
-                self.cells[cy as usize][cx as usize] = cell.clamp(0.0, 1.0);
-            }
+    fn toggle_blobs(&mut self, threshold: f32) {
+        self.show_blobs = !self.show_blobs;
+        if self.show_blobs && self.tracker.is_none() {
+            self.start_tracking(threshold);
         }
     }
-}
 
-fn sigma(x: f32, a: f32, alpha: f32) -> f32 {
-    1.0 / (1.0 + (-(x - a) * 4.0 / alpha).exp())
-}
+    // The mass of the last steps as a sparkline half the width of the field, with the lowest and
+    // highest mass it spans.
+    fn history_line(&self) -> String {
+        let width = self.cells[0].len();
+        let spark = self.history.sparkline(width);
+        if spark.is_empty() {
+            return String::new();
+        }
+        let (low, high) = history::bounds(self.history.recent(width));
+        format!("{spark} {low:.1}..{high:.1}")
+    }
 
-fn sigma_n(x: f32, a: f32, b: f32) -> f32 {
-    sigma(x, a, ALPHA_N) * (1.0 - sigma(x, b, ALPHA_N))
-}
+    // Such as `Step 42 | Mass 5120.3 (22.8%) | 150x150 | 81 ms/step | 3 blobs`.
+    fn status(&self) -> String {
+        let (h, w) = (self.cells.len(), self.cells[0].len());
+        let mass: f64 = self.cells.iter().flatten().map(|&v| v as f64).sum();
+        let step_time = self.step_time.map_or(String::new(), |time| {
+            format!(" | {} ms/step", time.as_millis())
+        });
+        let blobs = self.tracker.as_ref().map_or(String::new(), |tracker| {
+            format!(" | {} blobs", tracker.blobs().len())
+        });
+        format!(
+            " Step {} | Mass {mass:.1} ({:.1}%) | {w}x{h}{step_time}{blobs} ",
+            self.step,
+            100.0 * mass / (w * h) as f64
+        )
+    }
 
-fn sigma_m(x: f32, y: f32, m: f32) -> f32 {
-    x * (1.0 - sigma(m, 0.5, ALPHA_M)) + y * sigma(m, 0.5, ALPHA_M)
-}
+    fn next(&mut self) {
+        // The field a run starts from begins the history.
+        if self.history.is_empty() {
+            self.history.push(FieldSample::of(self.step, &self.cells));
+        }
+        let start = Instant::now();
+        smoothlife::step(&mut self.cells, &self.params);
+        self.step_time = Some(start.elapsed());
+        self.step += 1;
+        self.history.push(FieldSample::of(self.step, &self.cells));
+        if let Some(tracker) = &mut self.tracker {
+            tracker.update(self.step, &self.cells);
+        }
+    }
 
-fn s(m: f32, n: f32) -> f32 {
-    sigma_n(n, sigma_m(B1, D1, m), sigma_m(B2, D2, m))
+    // Writes the trajectories to `path` and returns what to tell about them.
+    fn save_tracks(&self, path: &str) -> Result<Vec<String>, String> {
+        let Some(tracker) = &self.tracker else {
+            return Ok(Vec::new());
+        };
+        tracker.save(path)?;
+        let mut lines = tracker.summary();
+        lines.push(format!("Trajectories written to {path}"));
+        Ok(lines)
+    }
 }
 
-fn emod(a: i32, b: i32) -> i32 {
-    (a % b + b) % b
-}
+fn main() -> Result<(), String> {
+    let matches = get_args();
+    match matches.subcommand() {
+        Some(("sweep", matches)) => return sweep(matches),
+        Some(("evolve", matches)) => return evolve(matches),
+        _ => {}
+    }
+    let seed = rng::resolve_seed(matches.get_one::<u64>("seed").copied());
+    let soup = matches
+        .get_one::<FieldSoup>("soup")
+        .unwrap_or(&FieldSoup::Uniform);
+    let density = matches.get_one::<f64>("density").unwrap_or(&0.5);
+    let snapshot_path = matches.get_one::<String>("snapshot");
+    let import_path = matches.get_one::<String>("import");
+    let png_path = matches
+        .get_one::<String>("png")
+        .map_or(DEFAULT_PNG_FILE, String::as_str);
+    let cell_size = *matches
+        .get_one::<u32>("cell-size")
+        .unwrap_or(&DEFAULT_CELL_SIZE) as usize;
+    let palette = matches
+        .get_one::<Palette>("palette")
+        .cloned()
+        .unwrap_or_else(Palette::gray);
+    let record_path = matches.get_one::<String>("record");
+    let stream_path = matches.get_one::<String>("stream");
+    let stream_format = *matches
+        .get_one::<StreamFormat>("stream-format")
+        .unwrap_or(&StreamFormat::Y4m);
+    let capture_options = create_capture_options(&matches);
+    let history_path = matches.get_one::<String>("history");
+    let track_path = matches.get_one::<String>("track");
+    let blob_threshold = *matches
+        .get_one::<f32>("blob-threshold")
+        .unwrap_or(&blobs::DEFAULT_THRESHOLD);
+
+    // An existing `--snapshot` file takes the place of the soup, as does `--import`.
+    let mut board = match snapshot_path.filter(|path| Path::new(path).exists()) {
+        Some(path) => Board::restore(Snapshot::load(path)?),
+        None => {
+            let mut board = Board::new(HEIGHT, WIDTH, Params::default(), seed);
+            match import_path {
+                Some(path) => board.import(path)?,
+                None => {
+                    let mut rng = rng::from_seed_stream(seed, rng::SOUP_STREAM);
+                    board.randomize(*soup, *density, &mut rng);
+                }
+            }
+            board
+        }
+    };
+
+    if track_path.is_some() {
+        board.start_tracking(blob_threshold);
+    }
+
+    let headless = matches.get_flag("headless");
+    if stream_path.is_some_and(|target| target == "-") && !headless {
+        return Err(image_error(
+            "only --headless runs can stream to stdout, where the field is drawn otherwise",
+        ));
+    }
+    let mut captures = Captures {
+        gif: record_path
+            .map(|path| board.record(path, cell_size, &palette, capture_options))
+            .transpose()?,
+        stream: stream_path
+            .map(|target| FrameStream::open(target, stream_format, capture_options))
+            .transpose()?,
+    };
+    // The recording starts with the current step, and so does the stream.
+    if let Some(stream) = &mut captures.stream {
+        stream.offer(&board.to_frame(cell_size, &palette))?;
+    }
+    if headless {
+        // Frames sent to stdout leave no room for messages there.
+        let quiet = captures.is_stdout();
+        let say = |line: &str| {
+            if quiet {
+                eprintln!("{line}")
+            } else {
+                println!("{line}")
+            }
+        };
+        let mut reports = Vec::new();
+        // A history or trajectories alone run as long as a recording of the same length would.
+        let logged = match history_path.or(track_path) {
+            Some(_) => capture_options.frames.unwrap_or(0) * capture_options.skip,
+            None => 0,
+        } as u64;
+        let start = board.step;
+        while captures.is_active() || board.step - start < logged {
+            board.next();
+            reports.extend(captures.offer(|| board.to_frame(cell_size, &palette)));
+        }
+        for report in reports {
+            say(&report?);
+        }
+        if let Some(path) = history_path {
+            board.history.save(path)?;
+            say(&format!("History written to {path}"));
+        }
+        if let Some(path) = track_path {
+            for line in board.save_tracks(path)? {
+                say(&line);
+            }
+        }
+        say(&format!("Seed: {}", board.seed));
+        return Ok(());
+    }
 
-fn main() {
     let mut stdout = stdout().into_raw_mode().unwrap();
     write!(stdout, "{}", termion::cursor::Hide).unwrap();
     stdout.flush().unwrap();
@@ -121,20 +419,81 @@ fn main() {
         }
     });
 
-    let mut board = Board::new(HEIGHT, WIDTH);
     let mut quit = false;
-
-    board.randomize();
+    let snapshot_file = snapshot_path.map_or(DEFAULT_SNAPSHOT_FILE, String::as_str);
 
     while !quit {
         board.display(&mut stdout);
         stdout.flush().unwrap();
 
         board.next();
+        for report in captures.offer(|| board.to_frame(cell_size, &palette)) {
+            board.message = Some(report.unwrap_or_else(|err| err));
+        }
 
         if let Ok(key) = rx.recv_timeout(timeout) {
             match key {
                 Key::Ctrl('c') | Key::Char('q') => quit = true,
+                Key::Ctrl('s') => {
+                    let message = board
+                        .to_snapshot()
+                        .save(snapshot_file)
+                        .map_or_else(|err| err, |_| format!("Saved snapshot to {snapshot_file}"));
+                    board.message = Some(message);
+                }
+                Key::Ctrl('o') => match Snapshot::load(snapshot_file) {
+                    Ok(snapshot) => {
+                        let show_blobs = board.show_blobs;
+                        board = Board::restore(snapshot);
+                        if track_path.is_some() || show_blobs {
+                            board.start_tracking(blob_threshold);
+                        }
+                        board.show_blobs = show_blobs;
+                        board.message = Some(format!("Restored snapshot from {snapshot_file}"));
+                    }
+                    Err(err) => board.message = Some(err),
+                },
+                Key::Char('p') => {
+                    let message = board
+                        .to_frame(cell_size, &palette)
+                        .save_png(png_path)
+                        .map_or_else(|err| err, |_| format!("Exported {png_path}"));
+                    board.message = Some(message);
+                }
+                Key::Char('i') => {
+                    let message = match import_path {
+                        Some(path) => board
+                            .import(path)
+                            .map_or_else(|err| err, |_| format!("Imported {path}")),
+                        None => "No image given with --import".to_string(),
+                    };
+                    board.message = Some(message);
+                }
+                Key::Char('h') => {
+                    let path = history_path.map_or(DEFAULT_HISTORY_FILE, String::as_str);
+                    let message = board
+                        .history
+                        .save(path)
+                        .map_or_else(|err| err, |_| format!("History written to {path}"));
+                    board.message = Some(message);
+                }
+                Key::Char('t') => board.toggle_blobs(blob_threshold),
+                Key::Char('g') => {
+                    let message = match render::stop(&mut captures.gif) {
+                        Some(report) => report.unwrap_or_else(|err| err),
+                        None => {
+                            let path = record_path.map_or(DEFAULT_GIF_FILE, String::as_str);
+                            match board.record(path, cell_size, &palette, capture_options) {
+                                Ok(started) => {
+                                    captures.gif = Some(started);
+                                    format!("Recording to {path}")
+                                }
+                                Err(err) => err,
+                            }
+                        }
+                    };
+                    board.message = Some(message);
+                }
                 _ => (),
             }
         }
@@ -148,4 +507,677 @@ fn main() {
         cursor::Show
     )
     .unwrap();
+    drop(stdout);
+
+    for report in captures.stop() {
+        println!("{}", report.unwrap_or_else(|err| err));
+    }
+    println!("Seed: {}", board.seed);
+    if let Some(path) = history_path {
+        board.history.save(path)?;
+        println!("History written to {path}");
+    }
+    if let Some(path) = track_path {
+        for line in board.save_tracks(path)? {
+            println!("{line}");
+        }
+    }
+    if let Some(path) = snapshot_path {
+        board.to_snapshot().save(path)?;
+        println!("Saved snapshot to {path}");
+    }
+    Ok(())
+}
+
+// Runs every configuration of the swept parameters from the same soup on all cores, classifies
+// how each field ended up and writes the table, with a thumbnail of each final field.
+fn sweep(matches: &ArgMatches) -> Result<(), String> {
+    let seed = rng::resolve_seed(matches.get_one::<u64>("seed").copied());
+    let soup = *matches
+        .get_one::<FieldSoup>("soup")
+        .unwrap_or(&FieldSoup::Uniform);
+    let density = *matches.get_one::<f64>("density").unwrap_or(&0.5);
+    let threshold = *matches
+        .get_one::<f32>("blob-threshold")
+        .unwrap_or(&blobs::DEFAULT_THRESHOLD);
+    let cell_size = *matches
+        .get_one::<u32>("cell-size")
+        .unwrap_or(&SWEEP_CELL_SIZE) as usize;
+    let palette = matches
+        .get_one::<Palette>("palette")
+        .cloned()
+        .unwrap_or_else(Palette::gray);
+    let (rows, cols) = *matches
+        .get_one::<(usize, usize)>("size")
+        .unwrap_or(&SWEEP_SIZE);
+    let steps = *matches.get_one::<u64>("steps").unwrap_or(&SWEEP_STEPS);
+    let axes: Vec<Axis> = matches
+        .get_many::<Axis>("param")
+        .map_or_else(Vec::new, |axes| axes.copied().collect());
+    let space = Space::new(Params::default(), axes)?;
+    let configurations = match matches.get_one::<usize>("samples") {
+        Some(&samples) => space.sample(
+            samples,
+            &mut rng::from_seed_stream(seed, rng::ENGINE_STREAM),
+        ),
+        None => space.grid(),
+    };
+    let total = configurations.len();
+    let threads = matches
+        .get_one::<u32>("threads")
+        .map(|&n| n as usize)
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .min(total.max(1));
+    let out_dir = matches.get_one::<String>("out").map(PathBuf::from);
+    if let Some(dir) = &out_dir {
+        fs::create_dir_all(dir).map_err(|err| {
+            sweep::sweep_error(&format!("cannot create {}: {err}", dir.display()))
+        })?;
+    }
+
+    let mut report = SweepReport::new(&space);
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let progress = stderr().is_terminal();
+
+    thread::scope(|scope| -> Result<(), String> {
+        for _ in 0..threads {
+            let tx = tx.clone();
+            let (configurations, next) = (&configurations, &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= total {
+                    break;
+                }
+                // The soup is drawn for each radius, from the same seed every time.
+                let params = configurations[i];
+                let mut rng = rng::from_seed_stream(seed, rng::SOUP_STREAM);
+                let cells = soup.generate(rows, cols, params.ra, density, &mut rng);
+                if tx
+                    .send((i, Trial::run(params, cells, steps, threshold)))
+                    .is_err()
+                {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (done, (index, trial)) in rx.iter().enumerate() {
+            let thumbnail = match &out_dir {
+                Some(dir) => {
+                    let path = dir.join(format!("{index:04}.png"));
+                    Frame::from_field(&trial.cells, cell_size, &palette).save_png(&path)?;
+                    Some(path.display().to_string())
+                }
+                None => None,
+            };
+            report.add(Entry {
+                index,
+                trial,
+                thumbnail,
+            });
+            if progress {
+                eprint!("\rRan {}/{total} configurations", done + 1);
+            }
+        }
+        if progress {
+            eprintln!();
+        }
+        Ok(())
+    })?;
+    report.finish();
+
+    print!("{}", report.table());
+    println!("Seed: {seed}");
+    if let Some(dir) = &out_dir {
+        let path = dir.join("sweep.csv");
+        report.save(&path)?;
+        println!("Results and thumbnails written to {}", dir.display());
+    }
+    Ok(())
+}
+
+// How every parameter set of a genetic search is tried: soups of a field, run for some steps.
+struct EvolveTrial {
+    rows: usize,
+    cols: usize,
+    soup: FieldSoup,
+    density: f64,
+    steps: u64,
+    threshold: f32,
+}
+
+impl EvolveTrial {
+    fn describe(&self) -> String {
+        format!(
+            "{}x{} fields, {} soups of density {}, {} steps, blobs at {}",
+            self.rows,
+            self.cols,
+            format!("{:?}", self.soup).to_lowercase(),
+            self.density,
+            self.steps,
+            self.threshold
+        )
+    }
+
+    // Runs the soup seeded `seed` under `params` and measures it: how steady its mass ends up,
+    // how many blobs it leaves and whether they move, and the entropy of its values.
+    fn run(&self, params: Params, seed: u64) -> Traits {
+        let mut rng = rng::from_seed_stream(seed, rng::SOUP_STREAM);
+        let cells = self
+            .soup
+            .generate(self.rows, self.cols, params.ra, self.density, &mut rng);
+        let trial = Trial::run(params, cells, self.steps, self.threshold);
+        if trial.outcome == Outcome::Dies {
+            return Traits::default();
+        }
+
+        let mut histogram = [0; ENTROPY_BINS];
+        for &v in trial.cells.iter().flatten() {
+            histogram
+                [((v.clamp(0.0, 1.0) * ENTROPY_BINS as f32) as usize).min(ENTROPY_BINS - 1)] += 1;
+        }
+        Traits {
+            stability: 1.0 - trial.variation.min(1.0),
+            diversity: evolution::saturate(trial.blobs as f64, DIVERSITY_HALF),
+            motion: if trial.outcome == Outcome::Moving {
+                1.0
+            } else {
+                0.0
+            },
+            entropy: evolution::entropy(&histogram),
+        }
+    }
+}
+
+// Evolves SmoothLife parameters for the fitness asked for, checkpointing every generation.
+fn evolve(matches: &ArgMatches) -> Result<(), String> {
+    let (rows, cols) = *matches
+        .get_one::<(usize, usize)>("size")
+        .unwrap_or(&EVOLVE_SIZE);
+    let trial = EvolveTrial {
+        rows,
+        cols,
+        soup: *matches
+            .get_one::<FieldSoup>("soup")
+            .unwrap_or(&FieldSoup::Uniform),
+        density: *matches.get_one::<f64>("density").unwrap_or(&0.5),
+        steps: *matches.get_one::<u64>("steps").unwrap_or(&SWEEP_STEPS),
+        threshold: *matches
+            .get_one::<f32>("blob-threshold")
+            .unwrap_or(&blobs::DEFAULT_THRESHOLD),
+    };
+    let generations = *matches
+        .get_one::<u64>("generations")
+        .unwrap_or(&EVOLVE_GENERATIONS);
+    let resume_path = matches.get_one::<String>("resume");
+    let checkpoint_path = matches.get_one::<String>("checkpoint").or(resume_path);
+    let conditions = trial.describe();
+
+    let mut evolution = match resume_path {
+        Some(path) => {
+            let checkpoint = Checkpoint::load(path)?;
+            let axes = checkpoint
+                .settings
+                .space
+                .split_whitespace()
+                .map(Axis::parse)
+                .collect::<Result<_, _>>()?;
+            Evolution::resume(
+                Space::new(Params::default(), axes)?,
+                checkpoint,
+                &conditions,
+            )?
+        }
+        None => {
+            let axes = match matches.get_many::<Axis>("param") {
+                Some(axes) => axes.copied().collect(),
+                None => EVOLVE_PARAMS
+                    .iter()
+                    .map(|axis| Axis::parse(axis))
+                    .collect::<Result<_, _>>()?,
+            };
+            let settings = evolution::Settings {
+                seed: rng::resolve_seed(matches.get_one::<u64>("seed").copied()),
+                population: *matches
+                    .get_one::<usize>("population")
+                    .unwrap_or(&EVOLVE_POPULATION),
+                elite: *matches.get_one::<usize>("elite").unwrap_or(&EVOLVE_ELITE),
+                tournament: *matches
+                    .get_one::<usize>("tournament")
+                    .unwrap_or(&EVOLVE_TOURNAMENT),
+                mutation: *matches
+                    .get_one::<f64>("mutation")
+                    .unwrap_or(&EVOLVE_MUTATION),
+                trials: *matches.get_one::<usize>("trials").unwrap_or(&EVOLVE_TRIALS),
+                objective: match matches.get_one::<Objective>("fitness") {
+                    Some(objective) => objective.clone(),
+                    None => Objective::parse(EVOLVE_FITNESS)?,
+                },
+                space: String::new(),
+                conditions,
+            };
+            // The default parameters are the first of the first generation, if they are in range.
+            let space = Space::new(Params::default(), axes)?;
+            let base = space.base();
+            let in_range = space.axes().iter().all(|axis| {
+                let value = sweep::get(&base, axis.name);
+                (axis.from.min(axis.to)..=axis.from.max(axis.to)).contains(&value)
+            });
+            let first = if in_range { vec![base] } else { Vec::new() };
+            Evolution::new(space, settings, first)?
+        }
+    };
+    let threads = matches
+        .get_one::<u32>("threads")
+        .map(|&n| n as usize)
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+    let settings = evolution.settings().clone();
+    println!(
+        "Evolving {} parameter sets of {} for {} over {} soups each, seed {}: {}",
+        settings.population,
+        settings.space,
+        settings.objective,
+        settings.trials,
+        settings.seed,
+        settings.conditions
+    );
+    let progress = stderr().is_terminal();
+
+    while evolution.generation() < generations {
+        let genomes = evolution.genomes();
+        let seeds = evolution.soup_seeds();
+        let total = genomes.len() * seeds.len();
+        let mut runs = vec![Traits::default(); total];
+        let next = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..threads.min(total) {
+                let tx = tx.clone();
+                let (genomes, seeds, trial, next) = (&genomes, &seeds, &trial, &next);
+                scope.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= total {
+                        break;
+                    }
+                    let run = trial.run(genomes[i / seeds.len()], seeds[i % seeds.len()]);
+                    if tx.send((i, run)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+            for (done, (i, run)) in rx.iter().enumerate() {
+                runs[i] = run;
+                if progress {
+                    eprint!("\rRan {}/{total} fields", done + 1);
+                }
+            }
+            if progress {
+                eprint!("\r");
+            }
+        });
+
+        let traits: Vec<Traits> = runs.chunks(seeds.len()).map(Traits::mean).collect();
+        let record = evolution.advance(&traits);
+        println!(
+            "Generation {}: best {:.3} {}, mean {:.3}, {} distinct parameter sets",
+            record.generation,
+            record.best.fitness,
+            record.best.genome,
+            record.mean_fitness,
+            record.distinct
+        );
+        if let Some(path) = checkpoint_path {
+            evolution.checkpoint().save(path)?;
+        }
+    }
+
+    print!("{}", evolution.table());
+    if let Some(path) = checkpoint_path {
+        println!("Checkpoint written to {path}");
+    }
+    Ok(())
+}
+
+fn create_capture_options(matches: &ArgMatches) -> CaptureOptions {
+    let mut options = CaptureOptions {
+        delay_ms: *matches
+            .get_one::<u32>("frame-delay")
+            .unwrap_or(&DEFAULT_FRAME_DELAY),
+        skip: *matches.get_one::<u32>("frame-skip").unwrap_or(&1) as usize,
+        frames: matches.get_one::<u32>("frames").map(|&n| n as usize),
+    };
+    if let Some(&seconds) = matches.get_one::<f64>("duration") {
+        options.frames = Some(options.frames_in(seconds));
+    }
+    options
+}
+
+fn get_args() -> ArgMatches {
+    Command::new(APP_NAME)
+        .display_name(BIN_NAME.unwrap_or("Unknown"))
+        .author(AUTHORS.unwrap_or("Unknown"))
+        .about(DESCRIPTION.unwrap_or("Unknown"))
+        .version(VERSION.unwrap_or("Unknown"))
+        .help_template(HELP_TEMPLATE)
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .global(true)
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Seed for the random number generator")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("soup")
+                .long("soup")
+                .global(true)
+                .value_name("kind")
+                .action(ArgAction::Set)
+                .help("Initial field: uniform, noise, blobs or rings [default: uniform]")
+                .value_parser(FieldSoup::parse),
+        )
+        .arg(
+            Arg::new("density")
+                .long("density")
+                .global(true)
+                .value_name("p")
+                .action(ArgAction::Set)
+                .help("Coverage of blobs and rings soups [default: 0.5]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("snapshot")
+                .long("snapshot")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Resume the field saved in file, if it exists, and save it there on quit"),
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("CSV file the mass and mean value of every step are written to on exit"),
+        )
+        .arg(
+            Arg::new("track")
+                .long("track")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Track blobs of the field and write their trajectories to this CSV file on exit"),
+        )
+        .arg(
+            Arg::new("blob-threshold")
+                .long("blob-threshold")
+                .global(true)
+                .value_name("t")
+                .action(ArgAction::Set)
+                .help("Field value from which cells belong to a blob [default: 0.5]")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("import")
+                .long("import")
+                .value_name("image")
+                .action(ArgAction::Set)
+                .help("Start from the luminance of a PNG or PPM image instead of a soup"),
+        )
+        .arg(
+            Arg::new("png")
+                .long("png")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("File the field is exported to as PNG [default: field.png]"),
+        )
+        .arg(
+            Arg::new("cell-size")
+                .long("cell-size")
+                .global(true)
+                .value_name("px")
+                .action(ArgAction::Set)
+                .help("Pixels per cell in exported images [default: 4]")
+                .value_parser(value_parser!(u32).range(1..=64)),
+        )
+        .arg(
+            Arg::new("palette")
+                .long("palette")
+                .global(true)
+                .value_name("colours")
+                .action(ArgAction::Set)
+                .help("Palette of exported images: gray, inverted, heat, ocean or hex colours such as 000000,ffffff [default: gray]")
+                .value_parser(Palette::parse),
+        )
+        .arg(
+            Arg::new("record")
+                .long("record")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Record every step of the field into an animated GIF"),
+        )
+        .arg(
+            Arg::new("frames")
+                .long("frames")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Stop recording after this many frames")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .value_name("seconds")
+                .action(ArgAction::Set)
+                .help("Stop recording once the GIF lasts this long")
+                .value_parser(render::parse_duration),
+        )
+        .group(ArgGroup::new("length").args(["frames", "duration"]))
+        .arg(
+            Arg::new("frame-delay")
+                .long("frame-delay")
+                .value_name("ms")
+                .action(ArgAction::Set)
+                .help("Delay between GIF frames, in milliseconds [default: 100]")
+                .value_parser(value_parser!(u32).range(10..=655350)),
+        )
+        .arg(
+            Arg::new("frame-skip")
+                .long("frame-skip")
+                .value_name("num")
+                .action(ArgAction::Set)
+                .help("Record only every num-th step [default: 1]")
+                .value_parser(value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .value_name("file")
+                .action(ArgAction::Set)
+                .help("Stream every step as raw video into a file, a named pipe or - for stdout"),
+        )
+        .arg(
+            Arg::new("stream-format")
+                .long("stream-format")
+                .value_name("format")
+                .action(ArgAction::Set)
+                .requires("stream")
+                .help("Raw video format of --stream: y4m or ppm [default: y4m]")
+                .value_parser(StreamFormat::parse),
+        )
+        .group(
+            ArgGroup::new("output")
+                .args(["record", "stream", "history", "track"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .action(ArgAction::SetTrue)
+                .requires_all(["output", "length"])
+                .help("Record, stream, log history or track blobs without drawing the field, then exit"),
+        )
+        .subcommand(
+            Command::new("sweep")
+                .about("Run the field under a grid or a random sample of parameters and classify how it ends up, on all cores")
+                .arg(
+                    Arg::new("param")
+                        .long("param")
+                        .value_name("name=from:to[:count]")
+                        .action(ArgAction::Append)
+                        .help("Parameter to sweep: ra, b1, b2, d1, d2, alpha-n, alpha-m or dt, over count values [default count: 3]")
+                        .value_parser(Axis::parse),
+                )
+                .arg(
+                    Arg::new("samples")
+                        .long("samples")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Draw this many random configurations from the ranges instead of a grid")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("steps")
+                        .long("steps")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Steps to run each configuration for [default: 300]")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .value_name("rowsxcols")
+                        .action(ArgAction::Set)
+                        .help("Size of the field [default: 128x128]")
+                        .value_parser(soup::parse_size),
+                )
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Threads to run configurations on [default: one per core]")
+                        .value_parser(value_parser!(u32).range(1..)),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("dir")
+                        .action(ArgAction::Set)
+                        .help("Directory the results are written to as sweep.csv, with a PNG thumbnail per configuration"),
+                ),
+        )
+        .subcommand(
+            Command::new("evolve")
+                .about("Evolve parameters that score well on a fitness over random soups, with a genetic algorithm")
+                .arg(
+                    Arg::new("param")
+                        .long("param")
+                        .value_name("name=from:to")
+                        .action(ArgAction::Append)
+                        .help("Parameter to evolve within a range: ra, b1, b2, d1, d2, alpha-n, alpha-m or dt [default: b1, b2, d1 and d2]")
+                        .value_parser(Axis::parse),
+                )
+                .arg(
+                    Arg::new("fitness")
+                        .long("fitness")
+                        .value_name("fitness")
+                        .action(ArgAction::Set)
+                        .help("Fitnesses to score parameters on, with optional weights: stability, diversity, motion, entropy, e.g. motion,diversity=0.5 [default: motion]")
+                        .value_parser(Objective::parse),
+                )
+                .arg(
+                    Arg::new("generations")
+                        .long("generations")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Generation to evolve up to, counting those of a resumed run [default: 10]")
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("population")
+                        .long("population")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Parameter sets in each generation [default: 12]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("elite")
+                        .long("elite")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Best parameter sets kept unchanged in the next generation [default: 2]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("tournament")
+                        .long("tournament")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Parameter sets drawn to pick each parent from, the fittest winning [default: 3]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("mutation")
+                        .long("mutation")
+                        .value_name("rate")
+                        .action(ArgAction::Set)
+                        .help("Chance of each parameter moving in a child, besides one that always does [default: 0.2]")
+                        .value_parser(value_parser!(f64)),
+                )
+                .arg(
+                    Arg::new("trials")
+                        .long("trials")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Soups each parameter set is scored over [default: 2]")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("steps")
+                        .long("steps")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Steps to run each soup for [default: 300]")
+                        .value_parser(value_parser!(u64).range(1..)),
+                )
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .value_name("rowsxcols")
+                        .action(ArgAction::Set)
+                        .help("Size of the field [default: 64x64]")
+                        .value_parser(soup::parse_size),
+                )
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_name("num")
+                        .action(ArgAction::Set)
+                        .help("Threads to run soups on [default: one per core]")
+                        .value_parser(value_parser!(u32).range(1..)),
+                )
+                .arg(
+                    Arg::new("checkpoint")
+                        .long("checkpoint")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("File the state of the run is written to after every generation [default: the --resume file]"),
+                )
+                .arg(
+                    Arg::new("resume")
+                        .long("resume")
+                        .value_name("file")
+                        .action(ArgAction::Set)
+                        .help("Checkpoint to carry on from, with its parameters, population and fitness settings; --size, the soup options, --steps and --blob-threshold must be given again as they were"),
+                ),
+        )
+        .get_matches()
 }
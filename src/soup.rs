@@ -0,0 +1,237 @@
+use std::f32::consts::PI;
+use std::fmt;
+
+use rand::Rng as _;
+
+use crate::rng::Rng;
+
+type Rows = usize;
+type Cols = usize;
+
+fn soup_error(err: &str) -> String {
+    format!("[SOUP ERROR]: {err}.")
+}
+
+/// Parses a `<rows>x<cols>` size, e.g. `16x16`.
+pub fn parse_size(size: &str) -> Result<(Rows, Cols), String> {
+    size.split_once('x')
+        .and_then(|(r, c)| Some((r.trim().parse().ok()?, c.trim().parse().ok()?)))
+        .filter(|&(r, c)| r > 0 && c > 0)
+        .ok_or_else(|| soup_error(&format!("'{size}' is not a <rows>x<cols> size")))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Symmetry {
+    C1,
+    /// Mirrored left to right, about a vertical axis.
+    D2V,
+    /// Mirrored top to bottom, about a horizontal axis.
+    D2H,
+    C2,
+    C4,
+    D4,
+    D8,
+}
+
+impl Symmetry {
+    pub fn parse(symmetry: &str) -> Result<Self, String> {
+        match symmetry.trim().to_ascii_uppercase().as_str() {
+            "C1" => Ok(Symmetry::C1),
+            "D2V" => Ok(Symmetry::D2V),
+            "D2H" => Ok(Symmetry::D2H),
+            "C2" => Ok(Symmetry::C2),
+            "C4" => Ok(Symmetry::C4),
+            "D4" => Ok(Symmetry::D4),
+            "D8" => Ok(Symmetry::D8),
+            _ => Err(soup_error(&format!("unknown symmetry '{symmetry}'"))),
+        }
+    }
+
+    pub fn needs_square(&self) -> bool {
+        matches!(self, Symmetry::C4 | Symmetry::D8)
+    }
+
+    /// All images of (r, c) under the group, inside an h x w rectangle.
+    pub fn orbit(&self, r: Rows, c: Cols, h: Rows, w: Cols) -> Vec<(Rows, Cols)> {
+        let (fr, fc) = (h - 1 - r, w - 1 - c);
+        match self {
+            Symmetry::C1 => vec![(r, c)],
+            Symmetry::D2V => vec![(r, c), (r, fc)],
+            Symmetry::D2H => vec![(r, c), (fr, c)],
+            Symmetry::C2 => vec![(r, c), (fr, fc)],
+            Symmetry::C4 => vec![(r, c), (c, fr), (fr, fc), (fc, r)],
+            Symmetry::D4 => vec![(r, c), (fr, c), (r, fc), (fr, fc)],
+            Symmetry::D8 => vec![
+                (r, c),
+                (c, fr),
+                (fr, fc),
+                (fc, r),
+                (fr, c),
+                (r, fc),
+                (c, r),
+                (fc, fr),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Generator for binary soups.
+#[derive(Clone, Debug)]
+pub struct Soup {
+    pub density: f64,
+    pub symmetry: Symmetry,
+    /// Threshold value noise of this lattice period instead of sampling cells independently.
+    pub noise_scale: Option<f32>,
+}
+
+impl Default for Soup {
+    fn default() -> Self {
+        Self {
+            density: 0.5,
+            symmetry: Symmetry::C1,
+            noise_scale: None,
+        }
+    }
+}
+
+impl Soup {
+    /// Generates an `h x w` soup. `C4` and `D8` soups are cropped to the largest centred square.
+    pub fn generate(&self, h: Rows, w: Cols, rng: &mut Rng) -> Vec<Vec<bool>> {
+        let mut soup = vec![vec![false; w]; h];
+
+        let (sh, sw) = if self.symmetry.needs_square() {
+            (h.min(w), h.min(w))
+        } else {
+            (h, w)
+        };
+        let (r0, c0) = ((h - sh) / 2, (w - sw) / 2);
+
+        let field = match self.noise_scale {
+            Some(scale) => value_noise(sh, sw, scale, rng),
+            None => (0..sh)
+                .map(|_| (0..sw).map(|_| rng.gen::<f32>()).collect())
+                .collect(),
+        };
+
+        for r in 0..sh {
+            for c in 0..sw {
+                let &(or, oc) = self.symmetry.orbit(r, c, sh, sw).iter().min().unwrap();
+                soup[r0 + r][c0 + c] = (field[or][oc] as f64) < self.density;
+            }
+        }
+
+        soup
+    }
+}
+
+/// Smoothly interpolated value noise in `[0, 1]` with lattice period `scale` cells.
+pub fn value_noise(h: Rows, w: Cols, scale: f32, rng: &mut Rng) -> Vec<Vec<f32>> {
+    let scale = scale.max(1.0);
+    let lh = (h as f32 / scale).ceil() as usize + 2;
+    let lw = (w as f32 / scale).ceil() as usize + 2;
+    let lattice: Vec<Vec<f32>> = (0..lh)
+        .map(|_| (0..lw).map(|_| rng.gen()).collect())
+        .collect();
+
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    (0..h)
+        .map(|r| {
+            (0..w)
+                .map(|c| {
+                    let (y, x) = (r as f32 / scale, c as f32 / scale);
+                    let (iy, ix) = (y as usize, x as usize);
+                    let (ty, tx) = (smooth(y.fract()), smooth(x.fract()));
+                    let top = lerp(lattice[iy][ix], lattice[iy][ix + 1], tx);
+                    let bottom = lerp(lattice[iy + 1][ix], lattice[iy + 1][ix + 1], tx);
+                    lerp(top, bottom, ty)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FieldSoup {
+    Uniform,
+    Noise,
+    Blobs,
+    Rings,
+}
+
+impl FieldSoup {
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        match kind.trim() {
+            "uniform" => Ok(FieldSoup::Uniform),
+            "noise" => Ok(FieldSoup::Noise),
+            "blobs" => Ok(FieldSoup::Blobs),
+            "rings" => Ok(FieldSoup::Rings),
+            _ => Err(soup_error(&format!("unknown soup '{kind}'"))),
+        }
+    }
+
+    /// Generates a continuous `h x w` field in `[0, 1]`.
+    ///
+    /// `radius` is the typical feature size (e.g. the SmoothLife outer radius) and `density`
+    /// controls how much of the board the blobs and rings cover.
+    pub fn generate(
+        &self,
+        h: Rows,
+        w: Cols,
+        radius: f32,
+        density: f64,
+        rng: &mut Rng,
+    ) -> Vec<Vec<f32>> {
+        match self {
+            FieldSoup::Uniform => (0..h)
+                .map(|_| (0..w).map(|_| rng.gen()).collect())
+                .collect(),
+            FieldSoup::Noise => value_noise(h, w, radius, rng),
+            FieldSoup::Blobs | FieldSoup::Rings => {
+                let area = (h * w) as f32;
+                let count =
+                    ((density as f32 * area / (PI * 4.0 * radius * radius)) as usize).max(1);
+                let shapes: Vec<(f32, f32, f32)> = (0..count)
+                    .map(|_| {
+                        let y = rng.gen_range(0.0..h as f32);
+                        let x = rng.gen_range(0.0..w as f32);
+                        let size = rng.gen_range(0.5..1.5) * radius;
+                        (y, x, size)
+                    })
+                    .collect();
+
+                let mut field = vec![vec![0.0f32; w]; h];
+                for (r, row) in field.iter_mut().enumerate() {
+                    for (c, item) in row.iter_mut().enumerate() {
+                        for &(y, x, size) in shapes.iter() {
+                            let d = torus_distance(r as f32, c as f32, y, x, h as f32, w as f32);
+                            let v = match self {
+                                FieldSoup::Blobs => gaussian(d, size / 2.0),
+                                _ => gaussian(d - size, size / 6.0),
+                            };
+                            *item = item.max(v);
+                        }
+                    }
+                }
+                field
+            }
+        }
+    }
+}
+
+fn gaussian(d: f32, sigma: f32) -> f32 {
+    (-(d * d) / (2.0 * sigma * sigma)).exp()
+}
+
+fn torus_distance(r0: f32, c0: f32, r1: f32, c1: f32, h: f32, w: f32) -> f32 {
+    let dy = (r0 - r1).abs().min(h - (r0 - r1).abs());
+    let dx = (c0 - c1).abs().min(w - (c0 - c1).abs());
+    (dy * dy + dx * dx).sqrt()
+}
@@ -9,20 +9,19 @@ uniform sampler2D texture0;
 uniform vec4 colDiffuse;
 uniform vec2 resolution;
 
+// SmoothLife parameters
+uniform float ra;
+uniform float b1;
+uniform float b2;
+uniform float d1;
+uniform float d2;
+uniform float alpha_n;
+uniform float alpha_m;
+uniform float dt;
+
 // Output fragment color
 out vec4 finalColor;
 
-float ra = 21;
-
-// SmoothLife constants
-float b1 = 0.257;
-float b2 = 0.336;
-float d1 = 0.365;
-float d2 = 0.549;
-float alpha_n = 0.028;
-float alpha_m = 0.147;
-float dt = 0.08;
-
 float sigma(float x, float a, float alpha)
 {
     return 1.0/(1.0 + exp(-(x - a)*4.0/alpha));